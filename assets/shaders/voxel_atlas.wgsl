@@ -0,0 +1,79 @@
+#import bevy_pbr::mesh_view_bindings
+#import bevy_pbr::mesh_bindings
+#import bevy_pbr::pbr_types
+
+#import bevy_pbr::utils
+#import bevy_pbr::clustered_forward
+#import bevy_pbr::lighting
+#import bevy_pbr::pbr_ambient
+#import bevy_pbr::shadows
+#import bevy_pbr::fog
+#import bevy_pbr::pbr_functions
+
+// Greedy quads span several voxels, so the mesher can't bake atlas UVs directly.
+// Instead uv = cell * CELL_STRIDE + local, where cell is the atlas tile and local counts voxels across the quad.
+// Keep these in sync with TEX_ATLAS_DIM and GREEDY_UV_CELL_STRIDE in vdt/mesh.rs
+// Keep the shading values in sync with VoxelMaterials::base_material
+
+@group(1) @binding(0)
+var atlas_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var atlas_sampler: sampler;
+
+struct VoxelAtlasMaterial {
+    tint: vec4<f32>,
+};
+
+@group(1) @binding(2)
+var<uniform> voxel_material: VoxelAtlasMaterial;
+
+struct FragmentInput {
+    @builtin(front_facing) is_front: bool,
+    @builtin(position) frag_coord: vec4<f32>,
+    #import bevy_pbr::mesh_vertex_output
+};
+
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    let atlas_dim = 32.0;
+    let cell_stride = 256.0;
+    let cell_inset = 0.01;
+
+    var uv = vec2<f32>(0.0, 0.0);
+#ifdef VERTEX_UVS
+    uv = in.uv;
+#endif
+    let cell = floor(uv / cell_stride);
+    let local = fract(uv - cell * cell_stride);
+    let atlas_uv = (cell + cell_inset + local * (1.0 - 2.0 * cell_inset)) / atlas_dim;
+
+    var pbr_input: PbrInput = pbr_input_new();
+    pbr_input.material.base_color = textureSampleGrad(atlas_texture, atlas_sampler, atlas_uv, dpdx(uv) / atlas_dim, dpdy(uv) / atlas_dim) * voxel_material.tint;
+    if (voxel_material.tint.a < 1.0) {
+        pbr_input.material.flags = STANDARD_MATERIAL_FLAGS_ALPHA_MODE_BLEND;
+    }
+#ifdef VERTEX_COLORS
+    pbr_input.material.base_color = pbr_input.material.base_color * in.color;
+#endif
+    pbr_input.material.perceptual_roughness = 0.9;
+    pbr_input.material.reflectance = 0.0;
+
+    pbr_input.frag_coord = in.frag_coord;
+    pbr_input.world_position = in.world_position;
+    pbr_input.world_normal = prepare_world_normal(in.world_normal, false, in.is_front);
+    pbr_input.is_orthographic = view.projection[3].w == 1.0;
+    pbr_input.N = normalize(pbr_input.world_normal);
+    pbr_input.V = calculate_view(in.world_position, pbr_input.is_orthographic);
+    pbr_input.flags = mesh.flags;
+
+    var output_color = pbr(pbr_input);
+
+    if (fog.mode != FOG_MODE_OFF) {
+        output_color = apply_fog(output_color, in.world_position.xyz, view.world_position.xyz);
+    }
+
+#ifdef TONEMAP_IN_SHADER
+    output_color = tone_mapping(output_color);
+#endif
+    return output_color;
+}
@@ -7,7 +7,7 @@ use crate::*;
 pub struct EditorPlugin;
 impl Plugin for EditorPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(EditorState::default());
+        app.init_resource::<EditorState>();
     }
 }
 
@@ -19,11 +19,12 @@ pub struct EditorState {
     pub secondary_voxel: Voxel,
 }
 
-impl Default for EditorState {
-    fn default() -> Self {
+impl FromWorld for EditorState {
+    fn from_world(world: &mut World) -> Self {
+        let defs = world.resource::<Defs>();
         Self {
-            primary_voxel: Voxel::from_matter_id(1),
-            secondary_voxel: Voxel::from_matter_id(2),
+            primary_voxel: Voxel::from_matter_id(defs.matter.id_u8("dirt")),
+            secondary_voxel: Voxel::from_matter_id(defs.matter.id_u8("grass")),
         }
     }
 }
@@ -19,6 +19,43 @@ impl Plugin for GeneratorPlugin {
 // STRUCTS
 pub struct LevelGen;
 impl LevelGen {
+    /// Stone floor under a layer of random soil & stone, 101 voxels across around the origin
+    pub fn test_level(
+        random: &Random,
+        defs: &Res<Defs>,
+    ) -> TrunkGenerator<Voxel> {
+        let random = random.clone();
+        let dirt = Voxel::from_matter_id(defs.matter.id_u8("dirt"));
+        let grass = Voxel::from_matter_id(defs.matter.id_u8("grass"));
+        let tilled_dirt = Voxel::from_matter_id(defs.matter.id_u8("tilled dirt"));
+        let stone = Voxel::from_matter_id(defs.matter.id_u8("stone"));
+        Arc::new(move |min: &IVec3, max: &IVec3, voxels: &mut Accessor<Voxel>| {
+            let horizontal_dim = 50;
+            let min = min.max(IVec3::new(-horizontal_dim, -5, -horizontal_dim));
+            let max = max.min(IVec3::new(horizontal_dim, 5, horizontal_dim));
+            if min.cmpgt(max).any() {
+                return;
+            }
+
+            let mut rng = random.get_rng_at(&min);
+
+            if min.y <= 0 {
+                voxels.fill(&AreaShape::Rect { start: min, end: IVec3::new(max.x, max.y.min(0), max.z) }, &stone);
+            }
+
+            for z in min.z..=max.z { for y in min.y.max(1)..=max.y { for x in min.x..=max.x {
+                let chance = rng.gen_range(0..4);
+                match chance {
+                    0 => { voxels.set_value_on(&IVec3::new(x, y, z), &dirt); },
+                    1 => { voxels.set_value_on(&IVec3::new(x, y, z), &grass); },
+                    2 => { voxels.set_value_on(&IVec3::new(x, y, z), &tilled_dirt); },
+                    3 => { voxels.set_value_on(&IVec3::new(x, y, z), &stone); },
+                    _ => { voxels.set_value_on(&IVec3::new(x, y, z), &grass); },
+                }
+            }}}
+        })
+    }
+
     pub fn plains(
         
     ) {
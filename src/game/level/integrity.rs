@@ -0,0 +1,356 @@
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// USE
+use crate::*;
+use bevy::utils::HashSet;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// PLUGIN
+pub struct IntegrityPlugin;
+impl Plugin for IntegrityPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(IntegritySim::default())
+            .add_system(evsys_queue_integrity_checks)
+            .add_system(sys_step_integrity_sim.in_schedule(CoreSchedule::FixedUpdate));
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// CONSTANTS
+/// Default for [IntegritySim::max_island_size]
+pub const INTEGRITY_MAX_ISLAND_SIZE: usize = 4096;
+/// Health taken from a unit per voxel fallen by a collapse that lands on it
+const INTEGRITY_DAMAGE_PER_FALL: u32 = 2;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// STRUCTS
+/// A structural island cut off from everything holding it up, falls one voxel a step as one rigid body
+pub struct Collapse {
+    /// Lowest first, so each can move into the space the one beneath it left
+    coords: Vec<IVec3>,
+    fallen: u32,
+}
+
+impl Collapse {
+    pub fn coords(&self) -> &Vec<IVec3> { &self.coords }
+    pub fn fallen(&self) -> u32 { self.fallen }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// RESOURCES
+/// Support checks for structural matter in the level tree, see [MatterDef::is_structural]
+///
+/// Every removal checks the structural voxels next to it. Those connected through structural matter to something
+/// solid beneath them, or to a unit, stay up. Islands larger than `max_island_size` or reaching trunks that aren't
+/// resident are assumed to be held up too.
+#[derive(Resource)]
+pub struct IntegritySim {
+    /// Voxels next to removals, checked next step
+    pending: HashSet<IVec3>,
+    collapses: Vec<Collapse>,
+    /// Every voxel of every collapse, so their own moves don't start new checks
+    falling: HashSet<IVec3>,
+    pub max_island_size: usize,
+}
+
+impl Default for IntegritySim {
+    fn default() -> Self {
+        Self {
+            pending: HashSet::default(),
+            collapses: vec![],
+            falling: HashSet::default(),
+            max_island_size: INTEGRITY_MAX_ISLAND_SIZE,
+        }
+    }
+}
+
+impl IntegritySim {
+    pub fn is_idle(&self) -> bool { self.pending.is_empty() && self.collapses.is_empty() }
+    pub fn collapses(&self) -> &Vec<Collapse> { &self.collapses }
+
+    /// Checks whatever was leaning on `coord` next step
+    pub fn queue_removal(
+        &mut self,
+        coord: &IVec3,
+    ) {
+        for offset in GRID_DIRECTIONS.iter() {
+            self.pending.insert(*coord + *offset);
+        }
+    }
+
+    /// Checks whatever was leaning on the solids `changes` removed
+    pub fn queue_changes(
+        &mut self,
+        voxel_tree: &VoxelTree,
+        changes: &[VoxelChange],
+        defs: &Res<Defs>,
+    ) {
+        for change in changes.iter() {
+            if !change.old.is_solid(defs) || change.new.is_solid(defs) {
+                continue;
+            }
+
+            if change.dim == 1 {
+                self.queue_removal(&change.coord);
+                continue;
+            }
+
+            // Tiles are only ever filled or cleared whole, so only the layers just outside their faces were leaning on them
+            let max = change.coord + IVec3::splat(change.dim as i32 - 1);
+            for direction in GRID_DIRECTIONS.iter() {
+                let face_min = IVec3::select(direction.cmpgt(IVec3::ZERO), max + *direction, change.coord + *direction);
+                let face_max = IVec3::select(direction.cmplt(IVec3::ZERO), change.coord + *direction, max + *direction);
+                for region in voxel_tree.iter_active_regions().with_bounds(&face_min, &face_max) {
+                    // A region is one connected matter, any voxel of it finds the whole island
+                    if region.value.is_structural(defs) {
+                        self.pending.insert(region.min);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Starts collapses for the islands found around pending removals, then drops each collapse by one voxel
+    ///
+    /// Returns the units landed on & the damage dealt to each
+    pub fn step(
+        &mut self,
+        voxel_tree: &VoxelTree,
+        entity_tree: &EntityTree,
+        defs: &Res<Defs>,
+    ) -> Vec<(Entity, u32)> {
+        let mut voxels = voxel_tree.get_accessor();
+        let mut entities = entity_tree.get_accessor();
+
+        let mut starts: Vec<IVec3> = self.pending.drain().collect();
+        starts.sort_by_key(|coord| (coord.y, coord.x, coord.z));
+        for start in starts.iter() {
+            if self.falling.contains(start) || !voxels.get_value(start).is_structural(defs) {
+                continue;
+            }
+
+            if let Some(mut island) = self.find_island(start, voxel_tree, &mut voxels, &mut entities, defs) {
+                island.sort_by_key(|coord| (coord.y, coord.x, coord.z));
+                self.falling.extend(island.iter().copied());
+                self.collapses.push(Collapse { coords: island, fallen: 0 });
+            }
+        }
+
+        let mut hits: Vec<(Entity, u32)> = vec![];
+        let mut landed: Vec<usize> = vec![];
+        for (index, collapse) in self.collapses.iter_mut().enumerate() {
+            let island: HashSet<IVec3> = collapse.coords.iter().copied().collect();
+            let mut blocked = false;
+            let mut units: Vec<Entity> = vec![];
+            for coord in collapse.coords.iter() {
+                let below = *coord - IVec3::Y;
+                if island.contains(&below) {
+                    continue;
+                }
+
+                if !voxel_tree.is_resident(&below) || voxels.get_value(&below).is_solid(defs) {
+                    blocked = true;
+                } else if let Some(entity) = entities.get_value(&below) {
+                    blocked = true;
+                    if !units.contains(&entity) {
+                        units.push(entity);
+                    }
+                }
+            }
+
+            if blocked {
+                let damage = INTEGRITY_DAMAGE_PER_FALL * collapse.fallen.max(1);
+                hits.extend(units.into_iter().map(|entity| (entity, damage)));
+                landed.push(index);
+                continue;
+            }
+
+            for coord in collapse.coords.iter_mut() {
+                let below = *coord - IVec3::Y;
+                voxels.move_value(coord, &below);
+                self.falling.remove(coord);
+                *coord = below;
+            }
+
+            self.falling.extend(collapse.coords.iter().copied());
+            collapse.fallen += 1;
+        }
+
+        for index in landed.into_iter().rev() {
+            for coord in self.collapses.remove(index).coords.iter() {
+                self.falling.remove(coord);
+            }
+        }
+
+        hits
+    }
+
+    /// Every structural voxel connected to `start`, `None` if any of them is held up
+    fn find_island(
+        &self,
+        start: &IVec3,
+        voxel_tree: &VoxelTree,
+        voxels: &mut Accessor<Voxel>,
+        entities: &mut EntityAccessor,
+        defs: &Res<Defs>,
+    ) -> Option<Vec<IVec3>> {
+        let mut island: HashSet<IVec3> = HashSet::default();
+        island.insert(*start);
+        let mut queue = vec![*start];
+        while let Some(coord) = queue.pop() {
+            if island.len() > self.max_island_size {
+                return None;
+            }
+
+            for offset in GRID_DIRECTIONS.iter() {
+                let neighbor = coord + *offset;
+                if island.contains(&neighbor) {
+                    continue;
+                }
+
+                if !voxel_tree.is_resident(&neighbor) {
+                    return None;
+                }
+
+                let voxel = voxels.get_value(&neighbor);
+                if voxel.is_structural(defs) && !self.falling.contains(&neighbor) {
+                    island.insert(neighbor);
+                    queue.push(neighbor);
+                } else if *offset == GridDirection::Bottom.as_ivec3() && (voxel.is_solid(defs) || !entities.is_empty(&neighbor)) {
+                    return None;
+                }
+            }
+        }
+
+        Some(island.into_iter().collect())
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// EVENT SYSTEMS
+fn evsys_queue_integrity_checks(
+    mut voxel_changed_events: EventReader<VoxelChangedEvent>,
+    mut integrity_sim: ResMut<IntegritySim>,
+    level_tree_query: Query<(Entity, &VoxelTree), With<LevelTree>>,
+    defs: Res<Defs>,
+) {
+    let (level_entity, voxel_tree) = level_tree_query.single();
+    for voxel_changed_event in voxel_changed_events.iter() {
+        if voxel_changed_event.tree != level_entity {
+            continue;
+        }
+
+        integrity_sim.queue_changes(voxel_tree, &voxel_changed_event.changes, &defs);
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// SYSTEMS
+fn sys_step_integrity_sim(
+    mut integrity_sim: ResMut<IntegritySim>,
+    mut health_events: EventWriter<HealthEvent>,
+    level_tree_query: Query<(&VoxelTree, &EntityTree), With<LevelTree>>,
+    game_time: Res<GameTime>,
+    defs: Res<Defs>,
+) {
+    if game_time.delta_steps() == 0 || integrity_sim.is_idle() {
+        return;
+    }
+
+    let (voxel_tree, entity_tree) = level_tree_query.single();
+    for (entity, damage) in integrity_sim.step(voxel_tree, entity_tree, &defs) {
+        health_events.send(HealthEvent::Sub(entity, damage));
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::SystemState;
+
+    const TRUNK_DIM: usize = TreeDims::<LEAF_LOG2DIM, BRANCH_LOG2DIM, TRUNK_LOG2DIM>::TRUNK_DIM;
+
+    fn load_defs(
+        world: &mut World,
+    ) -> SystemState<Res<'static, Defs>> {
+        world.insert_resource(Defs::load(&mut DefsReport::default()));
+        SystemState::new(world)
+    }
+
+    /// Sets every coord of `coords` to `matter`, returning them
+    fn build(
+        voxel_tree: &VoxelTree,
+        coords: &[IVec3],
+        matter: u8,
+    ) -> Vec<IVec3> {
+        let mut voxels = voxel_tree.get_accessor();
+        for coord in coords.iter() {
+            voxels.set_value_on(coord, &Voxel::from_matter_id(matter));
+        }
+
+        coords.to_vec()
+    }
+
+    /// Queues the changes written since the last call & steps once, returning what is falling, where it started
+    fn step(
+        integrity_sim: &mut IntegritySim,
+        voxel_tree: &VoxelTree,
+        defs: &Res<Defs>,
+    ) -> Vec<Vec<IVec3>> {
+        integrity_sim.queue_changes(voxel_tree, &voxel_tree.drain_changes(), defs);
+        integrity_sim.step(voxel_tree, &EntityTree::new(), defs);
+        integrity_sim.collapses().iter().map(|collapse| {
+            let mut coords: Vec<IVec3> = collapse.coords().iter().map(|coord| *coord + IVec3::Y * collapse.fallen() as i32).collect();
+            coords.sort_by_key(|coord| (coord.y, coord.x, coord.z));
+            coords
+        }).collect()
+    }
+
+    #[test]
+    fn cutting_a_tower_drops_only_what_it_held_up() {
+        let mut world = World::new();
+        let mut defs_state = load_defs(&mut world);
+        let defs = defs_state.get(&world);
+        let (dirt, stone) = (defs.matter.id_u8("dirt"), defs.matter.id_u8("stone"));
+        assert!(!defs.matter.get_u8(dirt).is_structural());
+        assert!(defs.matter.get_u8(stone).is_structural());
+
+        let voxel_tree = VoxelTree::new(Voxel::default());
+        let floor: Vec<IVec3> = (0..16).flat_map(|x| (0..16).map(move |z| IVec3::new(x, 0, z))).collect();
+        build(&voxel_tree, &floor, dirt);
+        // A tower with an arm off its top, next to one left standing
+        let mut tower = build(&voxel_tree, &(1..=5).map(|y| IVec3::new(4, y, 4)).collect::<Vec<IVec3>>(), stone);
+        tower.extend(build(&voxel_tree, &[IVec3::new(5, 5, 4), IVec3::new(6, 5, 4)], stone));
+        build(&voxel_tree, &(1..=5).map(|y| IVec3::new(8, y, 4)).collect::<Vec<IVec3>>(), stone);
+
+        let mut integrity_sim = IntegritySim::default();
+        assert!(step(&mut integrity_sim, &voxel_tree, &defs).is_empty());
+
+        voxel_tree.get_accessor().set_value_off(&IVec3::new(4, 1, 4));
+        let mut island: Vec<IVec3> = tower.into_iter().filter(|coord| coord.y > 1).collect();
+        island.sort_by_key(|coord| (coord.y, coord.x, coord.z));
+        assert_eq!(step(&mut integrity_sim, &voxel_tree, &defs), vec![island]);
+        assert_eq!(integrity_sim.collapses()[0].fallen(), 1);
+    }
+
+    #[test]
+    fn clearing_a_trunk_tile_only_checks_its_faces() {
+        let mut world = World::new();
+        let mut defs_state = load_defs(&mut world);
+        let defs = defs_state.get(&world);
+        let (dirt, stone) = (defs.matter.id_u8("dirt"), defs.matter.id_u8("stone"));
+
+        let voxel_tree = VoxelTree::new(Voxel::default());
+        let key = IVec3::new(0, -(TRUNK_DIM as i32), 0);
+        voxel_tree.get_accessor().set_tile(&key, TRUNK_DIM, &Voxel::from_matter_id(dirt), true);
+        let tower = build(&voxel_tree, &(0..4).map(|y| IVec3::new(4, y, 4)).collect::<Vec<IVec3>>(), stone);
+
+        let mut integrity_sim = IntegritySim::default();
+        assert!(step(&mut integrity_sim, &voxel_tree, &defs).is_empty());
+
+        voxel_tree.get_accessor().set_tile(&key, TRUNK_DIM, &Voxel::default(), false);
+        assert_eq!(step(&mut integrity_sim, &voxel_tree, &defs), vec![tower]);
+    }
+}
@@ -0,0 +1,293 @@
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// USE
+use crate::*;
+use bevy::utils::HashSet;
+use rand_pcg::Pcg64;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// PLUGIN
+pub struct LiquidPlugin;
+impl Plugin for LiquidPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LiquidSim::default())
+            .add_startup_system(stsys_seed_liquid_sim)
+            .add_system(evsys_wake_liquid_sim)
+            .add_system(sys_step_liquid_sim.in_schedule(CoreSchedule::FixedUpdate));
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// CONSTANTS
+/// Default for [LiquidSim::max_updates_per_step]
+pub const LIQUID_MAX_UPDATES_PER_STEP: usize = 4096;
+/// `Dense` liquids only flow every this many steps
+const LIQUID_DENSE_STEP_INTERVAL: u32 = 4;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// RESOURCES
+/// Fixed step cellular simulation of liquids in the level tree, fill levels are kept in [Voxel::liquid_level]
+///
+/// Liquid falls into anything empty below it, then spreads one level at a time to lower sides until neighbours are
+/// within a level of each other. Only voxels woken by a change next to them are stepped, lowest first.
+/// Steps are deterministic for a given [Random] seed & order of changes.
+#[derive(Resource)]
+pub struct LiquidSim {
+    /// Liquid voxels to step next, woken by changes around them
+    awake: HashSet<IVec3>,
+    rng: Pcg64,
+    steps: u32,
+    pub max_updates_per_step: usize,
+}
+
+impl Default for LiquidSim {
+    fn default() -> Self {
+        Self {
+            awake: HashSet::default(),
+            rng: Random::default().get_rng_for("liquid"),
+            steps: 0,
+            max_updates_per_step: LIQUID_MAX_UPDATES_PER_STEP,
+        }
+    }
+}
+
+impl LiquidSim {
+    pub fn is_idle(&self) -> bool { self.awake.is_empty() }
+
+    pub fn reseed(
+        &mut self,
+        random: &Random,
+    ) {
+        self.rng = random.get_rng_for("liquid");
+    }
+
+    /// Wakes the liquid voxels on & around the `dim` sized block at `origin`, its inside can only flow once its shell has
+    pub fn wake_block(
+        &mut self,
+        voxel_tree: &VoxelTree,
+        origin: &IVec3,
+        dim: usize,
+        defs: &Res<Defs>,
+    ) {
+        let (min, max) = (*origin - IVec3::ONE, *origin + IVec3::splat(dim as i32));
+        let (inner_min, inner_max) = (*origin + IVec3::ONE, *origin + IVec3::splat(dim as i32 - 2));
+        for (coord, voxel) in voxel_tree.iter_active_regions().with_bounds(&min, &max).voxels() {
+            if coord.cmpge(inner_min).all() && coord.cmple(inner_max).all() {
+                continue;
+            }
+
+            if voxel.is_liquid(defs) {
+                self.awake.insert(coord);
+            }
+        }
+    }
+
+    /// Flows every awake voxel once, the voxels it changes wake their neighbours through [VoxelChangedEvent]
+    pub fn step(
+        &mut self,
+        voxel_tree: &VoxelTree,
+        defs: &Res<Defs>,
+    ) {
+        self.steps = self.steps.wrapping_add(1);
+        let dense_step = self.steps.is_multiple_of(LIQUID_DENSE_STEP_INTERVAL);
+
+        let mut coords: Vec<IVec3> = self.awake.drain().collect();
+        coords.sort_by_key(|coord| (coord.y, coord.x, coord.z));
+        if coords.len() > self.max_updates_per_step {
+            self.awake.extend(coords.drain(self.max_updates_per_step..));
+        }
+
+        let mut voxels = voxel_tree.get_accessor();
+        for coord in coords.iter() {
+            if !voxel_tree.is_resident(coord) {
+                continue;
+            }
+
+            let voxel = voxels.get_value(coord);
+            let matter = defs.matter.get_u8(voxel.matter_id());
+            if !matter.is_liquid() {
+                continue;
+            }
+
+            if matter.has_property(VoxelMatterProperty::Dense) && !dense_step {
+                self.awake.insert(*coord);
+                continue;
+            }
+
+            let below = *coord - IVec3::Y;
+            let mut level = voxel.liquid_level();
+            if voxel_tree.is_resident(&below) {
+                let below_voxel = voxels.get_value(&below);
+                if defs.matter.get_u8(below_voxel.matter_id()).is_void() {
+                    voxels.move_value(coord, &below);
+                    continue;
+                }
+
+                if below_voxel.matter_id() == voxel.matter_id() && below_voxel.liquid_level() < VOXEL_LIQUID_LEVELS {
+                    let flow = level.min(VOXEL_LIQUID_LEVELS - below_voxel.liquid_level());
+                    voxels.set_value_on(&below, &below_voxel.with_liquid_level(below_voxel.liquid_level() + flow));
+                    level -= flow;
+                }
+            }
+
+            // Sides are visited from a random one so nothing drifts in one direction
+            let first_side = self.rng.gen_range(0..VOXEL_SIDE_FACE_CHECKS.len());
+            for side_index in 0..VOXEL_SIDE_FACE_CHECKS.len() {
+                if level <= 1 {
+                    break;
+                }
+
+                let side = *coord + VOXEL_SIDE_FACE_CHECKS[(first_side + side_index) % VOXEL_SIDE_FACE_CHECKS.len()];
+                if !voxel_tree.is_resident(&side) {
+                    continue;
+                }
+
+                let side_voxel = voxels.get_value(&side);
+                if defs.matter.get_u8(side_voxel.matter_id()).is_void() {
+                    voxels.set_value_on(&side, &voxel.with_liquid_level(1));
+                    level -= 1;
+                } else if side_voxel.matter_id() == voxel.matter_id() && side_voxel.liquid_level() + 1 < level {
+                    voxels.set_value_on(&side, &side_voxel.with_liquid_level(side_voxel.liquid_level() + 1));
+                    level -= 1;
+                }
+            }
+
+            if level == 0 {
+                voxels.set_value_off(coord);
+            } else if level != voxel.liquid_level() {
+                voxels.set_value_on(coord, &voxel.with_liquid_level(level));
+            }
+        }
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// STARTUP SYSTEMS
+fn stsys_seed_liquid_sim(
+    mut liquid_sim: ResMut<LiquidSim>,
+    random: Res<Random>,
+) {
+    liquid_sim.reseed(&random);
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// EVENT SYSTEMS
+fn evsys_wake_liquid_sim(
+    mut voxel_changed_events: EventReader<VoxelChangedEvent>,
+    mut liquid_sim: ResMut<LiquidSim>,
+    level_tree_query: Query<(Entity, &VoxelTree), With<LevelTree>>,
+    defs: Res<Defs>,
+) {
+    let (level_entity, voxel_tree) = level_tree_query.single();
+    for voxel_changed_event in voxel_changed_events.iter() {
+        if voxel_changed_event.tree != level_entity {
+            continue;
+        }
+
+        for change in voxel_changed_event.changes.iter() {
+            liquid_sim.wake_block(voxel_tree, &change.coord, change.dim, &defs);
+        }
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// SYSTEMS
+fn sys_step_liquid_sim(
+    mut liquid_sim: ResMut<LiquidSim>,
+    level_tree_query: Query<&VoxelTree, With<LevelTree>>,
+    game_time: Res<GameTime>,
+    defs: Res<Defs>,
+) {
+    if game_time.delta_steps() == 0 || liquid_sim.is_idle() {
+        return;
+    }
+
+    liquid_sim.step(level_tree_query.single(), &defs);
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::SystemState;
+
+    const WATER_SOURCE: IVec3 = IVec3::new(8, 1, 8);
+    const LAVA_SOURCE: IVec3 = IVec3::new(24, 1, 24);
+
+    /// A stone floor with a full voxel of water & of lava on it, far enough apart that they never meet
+    fn test_level(
+        defs: &Defs,
+    ) -> VoxelTree {
+        let voxel_tree = VoxelTree::new(Voxel::default());
+        let mut voxels = voxel_tree.get_accessor();
+        let stone = Voxel::from_matter_id(defs.matter.id_u8("stone"));
+        for x in 0..32 {
+            for z in 0..32 {
+                voxels.set_value_on(&IVec3::new(x, 0, z), &stone);
+            }
+        }
+
+        voxels.set_value_on(&WATER_SOURCE, &Voxel::from_matter_id(defs.matter.id_u8("water")));
+        voxels.set_value_on(&LAVA_SOURCE, &Voxel::from_matter_id(defs.matter.id_u8("lava")));
+        voxel_tree
+    }
+
+    /// Voxels of `matter_id` & their summed fill levels
+    fn liquid_spread(
+        voxel_tree: &VoxelTree,
+        matter_id: u8,
+    ) -> (usize, u32) {
+        let liquid: Vec<Voxel> = voxel_tree.iter_active_regions().voxels().map(|(_, voxel)| voxel).filter(|voxel| voxel.matter_id() == matter_id).collect();
+        (liquid.len(), liquid.iter().map(|voxel| voxel.liquid_level() as u32).sum())
+    }
+
+    /// Steps the sim, waking what changed like [evsys_wake_liquid_sim] does
+    fn step(
+        liquid_sim: &mut LiquidSim,
+        voxel_tree: &VoxelTree,
+        defs: &Res<Defs>,
+    ) {
+        liquid_sim.step(voxel_tree, defs);
+        for change in voxel_tree.drain_changes() {
+            liquid_sim.wake_block(voxel_tree, &change.coord, change.dim, defs);
+        }
+    }
+
+    #[test]
+    fn dense_liquids_flow_every_few_steps() {
+        let mut world = World::new();
+        world.insert_resource(Defs::load(&mut DefsReport::default()));
+        let mut defs_state: SystemState<Res<Defs>> = SystemState::new(&mut world);
+        let defs = defs_state.get(&world);
+
+        let (water, lava) = (defs.matter.id_u8("water"), defs.matter.id_u8("lava"));
+        assert!(!defs.matter.get_u8(water).has_property(VoxelMatterProperty::Dense));
+        assert!(defs.matter.get_u8(lava).has_property(VoxelMatterProperty::Dense));
+
+        let voxel_tree = test_level(&defs);
+        let mut liquid_sim = LiquidSim::default();
+        liquid_sim.wake_block(&voxel_tree, &WATER_SOURCE, 1, &defs);
+        liquid_sim.wake_block(&voxel_tree, &LAVA_SOURCE, 1, &defs);
+        voxel_tree.drain_changes();
+
+        let mut water_spread = vec![];
+        let mut lava_spread = vec![];
+        for _ in 0..(LIQUID_DENSE_STEP_INTERVAL * 2) {
+            step(&mut liquid_sim, &voxel_tree, &defs);
+            water_spread.push(liquid_spread(&voxel_tree, water));
+            lava_spread.push(liquid_spread(&voxel_tree, lava));
+        }
+
+        // Flowing never makes or loses liquid
+        assert!(water_spread.iter().chain(lava_spread.iter()).all(|(_, levels)| *levels == VOXEL_LIQUID_LEVELS as u32));
+
+        // Water flows on every step, lava only on every LIQUID_DENSE_STEP_INTERVAL-th
+        let interval = LIQUID_DENSE_STEP_INTERVAL as usize;
+        assert!(water_spread[0].0 > 1);
+        assert!(lava_spread[..interval - 1].iter().all(|(voxel_count, _)| *voxel_count == 1));
+        assert!(lava_spread[interval - 1].0 > 1);
+        assert!(lava_spread[interval..interval * 2 - 1].iter().all(|spread| *spread == lava_spread[interval - 1]));
+        assert!(water_spread[interval - 1].0 > lava_spread[interval - 1].0);
+    }
+}
@@ -8,8 +8,16 @@ mod editor;
 pub use editor::*;
 mod generator;
 pub use generator::*;
+mod integrity;
+pub use integrity::*;
+mod liquid;
+pub use liquid::*;
+mod sand;
+pub use sand::*;
 mod spawner;
 pub use spawner::*;
+mod stock;
+pub use stock::*;
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // PLUGIN
@@ -19,56 +27,62 @@ impl Plugin for LevelPlugin {
         app.add_plugin(DaytimePlugin)
             .add_plugin(EditorPlugin)
             .add_plugin(GeneratorPlugin)
+            .add_plugin(IntegrityPlugin)
+            .add_plugin(LiquidPlugin)
+            .add_plugin(SandPlugin)
             .add_plugin(SpawnerPlugin)
+            .add_plugin(StockPlugin)
             .add_startup_system(stsys_spawn_level_tree)
             .add_system(onsys_init_level.in_schedule(OnEnter(AppState::MainMenu)));
     }
 }
 
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// CONSTANTS
+const LEVEL_TRUNK_DIRECTORY: &str = "data/level";
+/// Command line flag that deletes the saved trunks in [LEVEL_TRUNK_DIRECTORY] so the level is generated afresh
+pub const NEW_WORLD_ARG: &str = "--new-world";
+
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // STARTUP SYSTEMS
 fn stsys_spawn_level_tree(
     mut commands: Commands,
     mut spawn_events: EventWriter<SpawnEvent>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut atlas_materials: ResMut<Assets<VoxelAtlasMaterial>>,
     asset_loader: Res<AssetLoader>,
     random: Res<Random>,
+    defs: Res<Defs>,
 ) {
-    commands.spawn(LevelTreeBundle::new(Voxel::default(), VoxelMaterials::new_world_materials(&mut materials, &asset_loader)));
+    let voxel_tree = VoxelTree::new(Voxel::default());
+    let streamer = VoxelStreamer::new(&voxel_tree, LEVEL_TRUNK_DIRECTORY, Some(LevelGen::test_level(&random, &defs)));
+    if std::env::args().any(|arg| arg == NEW_WORLD_ARG) {
+        streamer.clear_files();
+    }
+
+    commands.spawn((
+        LevelTreeBundle::from_voxel_tree(voxel_tree, VoxelMaterials::new_world_materials(&mut materials, &mut atlas_materials, &asset_loader)),
+        streamer,
+    ));
 }
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // SCHEDULE SYSTEMS
 fn onsys_init_level(
     mut spawn_events: EventWriter<SpawnEvent>,
-    mut level_tree_query: Query<&mut VoxelTree, With<LevelTree>>,
-    asset_loader: Res<AssetLoader>,
-    random: Res<Random>,
+    mut level_tree_query: Query<(&VoxelTree, &mut VoxelStreamer), With<LevelTree>>,
+    time: Res<Time>,
+    defs: Res<Defs>,
 ) {
-    let mut voxels = level_tree_query.single().get_accessor();
-    
-    let mut rng = random.get_rng();
-    let dirt = Voxel::from_matter_id(1);
-    let grass = Voxel::from_matter_id(2);
-    let tilled_dirt = Voxel::from_matter_id(3);
-    let stone = Voxel::from_matter_id(4);
-
-    let horizontal_dim = 50;
-    for z in -horizontal_dim..=horizontal_dim { for y in -5..=5 { for x in -horizontal_dim..=horizontal_dim {
-        let chance = rng.gen_range(0..4);
-        match chance {
-            0 => { voxels.set_value_on(&IVec3::new(x, y, z), &dirt); },
-            1 => { voxels.set_value_on(&IVec3::new(x, y, z), &grass); },
-            2 => { voxels.set_value_on(&IVec3::new(x, y, z), &tilled_dirt); },
-            3 => { voxels.set_value_on(&IVec3::new(x, y, z), &stone); },
-            _ => { voxels.set_value_on(&IVec3::new(x, y, z), &grass); },
-        }
-    }}}
+    // Terrain comes from the streamer's generator, the trunks units spawn into have to be there first
+    let (voxel_tree, mut streamer) = level_tree_query.single_mut();
+    streamer.preload(voxel_tree, &Vec3::ZERO, time.elapsed_seconds());
+    voxel_tree.prune();
 
     // Unit Test
     let mut rng = thread_rng();
     for z in -4..=4 { for x in -4..=4 {
-        if rng.gen::<f32>() > 0.9 { spawn_events.send(SpawnEvent::Actor(IVec3::new(x, 6, z), 0)); }
+        if rng.gen::<f32>() > 0.9 { spawn_events.send(SpawnEvent::Actor(IVec3::new(x, 6, z), defs.actors.id_u8("demon"))); }
     }}
     
 }
\ No newline at end of file
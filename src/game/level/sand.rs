@@ -0,0 +1,282 @@
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// USE
+use crate::*;
+use bevy::utils::HashSet;
+use rand_pcg::Pcg64;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// PLUGIN
+pub struct SandPlugin;
+impl Plugin for SandPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SandSim::default())
+            .add_startup_system(stsys_seed_sand_sim)
+            .add_system(evsys_wake_sand_sim)
+            .add_system(sys_step_sand_sim.in_schedule(CoreSchedule::FixedUpdate));
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// CONSTANTS
+/// Default for [SandSim::max_updates_per_step]
+pub const SAND_MAX_UPDATES_PER_STEP: usize = 4096;
+/// Voxels fallen per step by `Light` loose matter, `Heavy` matter falls twice as far
+const SAND_FALL_PER_STEP: u32 = 1;
+/// Health taken from a unit that `Heavy` loose matter lands on and can't push aside
+const SAND_CRUSH_DAMAGE: u32 = 5;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// ENUMS
+/// What loose matter did to a unit in its way, the [EntityTree] has already been updated
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SandContact {
+    /// Pushed aside to `to`
+    Displaced { entity: Entity, to: IVec3 },
+    /// Nowhere to go, the matter came to rest on top
+    Crushed { entity: Entity, damage: u32 },
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// RESOURCES
+/// Fixed step cellular simulation of loose matter in the level tree, see [MatterDef::is_loose]
+///
+/// Only voxels woken by a change next to them are stepped, lowest first, so whole columns fall together.
+/// Steps are deterministic for a given [Random] seed & order of changes.
+#[derive(Resource)]
+pub struct SandSim {
+    /// Loose voxels to step next, woken by changes around them
+    awake: HashSet<IVec3>,
+    rng: Pcg64,
+    pub max_updates_per_step: usize,
+}
+
+impl Default for SandSim {
+    fn default() -> Self {
+        Self {
+            awake: HashSet::default(),
+            rng: Random::default().get_rng_for("sand"),
+            max_updates_per_step: SAND_MAX_UPDATES_PER_STEP,
+        }
+    }
+}
+
+impl SandSim {
+    pub fn is_idle(&self) -> bool { self.awake.is_empty() }
+
+    pub fn reseed(
+        &mut self,
+        random: &Random,
+    ) {
+        self.rng = random.get_rng_for("sand");
+    }
+
+    /// Wakes the loose voxels on & around the `dim` sized block at `origin`, its inside is held up by its shell
+    pub fn wake_block(
+        &mut self,
+        voxel_tree: &VoxelTree,
+        origin: &IVec3,
+        dim: usize,
+        defs: &Res<Defs>,
+    ) {
+        let (min, max) = (*origin - IVec3::ONE, *origin + IVec3::splat(dim as i32));
+        let (inner_min, inner_max) = (*origin + IVec3::ONE, *origin + IVec3::splat(dim as i32 - 2));
+        for (coord, voxel) in voxel_tree.iter_active_regions().with_bounds(&min, &max).voxels() {
+            if coord.cmpge(inner_min).all() && coord.cmple(inner_max).all() {
+                continue;
+            }
+
+            if defs.matter.get_u8(voxel.matter_id()).is_loose() {
+                self.awake.insert(coord);
+            }
+        }
+    }
+
+    /// Drops & slides every awake voxel once, the voxels they leave or land next to are woken through [VoxelChangedEvent]
+    pub fn step(
+        &mut self,
+        voxel_tree: &VoxelTree,
+        entity_tree: &EntityTree,
+        defs: &Res<Defs>,
+    ) -> Vec<SandContact> {
+        let mut coords: Vec<IVec3> = self.awake.drain().collect();
+        coords.sort_by_key(|coord| (coord.y, coord.x, coord.z));
+        if coords.len() > self.max_updates_per_step {
+            self.awake.extend(coords.drain(self.max_updates_per_step..));
+        }
+
+        let mut voxels = voxel_tree.get_accessor();
+        let mut entities = entity_tree.get_accessor();
+        let mut contacts = vec![];
+        for coord in coords.iter() {
+            if !voxel_tree.is_resident(coord) {
+                continue;
+            }
+
+            let matter = defs.matter.get_u8(voxels.get_value(coord).matter_id());
+            if !matter.is_loose() {
+                continue;
+            }
+
+            let heavy = matter.has_property(VoxelMatterProperty::Heavy);
+            let fall = if heavy { SAND_FALL_PER_STEP * 2 } else { SAND_FALL_PER_STEP };
+            let mut current = *coord;
+            for _ in 0..fall {
+                let below = current - IVec3::Y;
+                if voxels.get_value(&below).is_solid(defs) || !voxel_tree.is_resident(&below) {
+                    break;
+                }
+
+                if let Some(entity) = entities.get_value(&below) {
+                    match self.find_displacement(voxel_tree, &below, &mut voxels, &mut entities, defs) {
+                        Some(to) => {
+                            entities.move_value(&below, &to);
+                            contacts.push(SandContact::Displaced { entity, to });
+                        },
+                        None => {
+                            if heavy {
+                                contacts.push(SandContact::Crushed { entity, damage: SAND_CRUSH_DAMAGE });
+                            }
+
+                            break;
+                        },
+                    }
+                }
+
+                move_voxel(&current, &below, &mut voxels);
+                current = below;
+            }
+
+            if current == *coord {
+                if let Some(to) = self.find_slide(voxel_tree, coord, heavy, &mut voxels, &mut entities, defs) {
+                    move_voxel(coord, &to, &mut voxels);
+                }
+            }
+        }
+
+        contacts
+    }
+
+    /// An open side of `coord` a unit there can be pushed to, picked at random
+    fn find_displacement(
+        &mut self,
+        voxel_tree: &VoxelTree,
+        coord: &IVec3,
+        voxels: &mut Accessor<Voxel>,
+        entities: &mut EntityAccessor,
+        defs: &Res<Defs>,
+    ) -> Option<IVec3> {
+        let sides: Vec<IVec3> = VOXEL_SIDE_FACE_CHECKS.iter()
+            .map(|offset| *coord + *offset)
+            .filter(|side| is_open(voxel_tree, side, voxels, entities, defs))
+            .collect();
+        sides.choose(&mut self.rng).copied()
+    }
+
+    /// Where `coord` slides to when it rests on a ledge, picked at random
+    ///
+    /// `Light` matter slides off any ledge, `Heavy` matter packs steeper & only slides off drops of 2 or more
+    fn find_slide(
+        &mut self,
+        voxel_tree: &VoxelTree,
+        coord: &IVec3,
+        heavy: bool,
+        voxels: &mut Accessor<Voxel>,
+        entities: &mut EntityAccessor,
+        defs: &Res<Defs>,
+    ) -> Option<IVec3> {
+        let drop = if heavy { 2 } else { 1 };
+        let slides: Vec<IVec3> = VOXEL_SIDE_FACE_CHECKS.iter()
+            .map(|offset| *coord + *offset)
+            .filter(|side| (0..=drop).all(|depth| is_open(voxel_tree, &(*side - IVec3::Y * depth), voxels, entities, defs)))
+            .map(|side| side - IVec3::Y)
+            .collect();
+        slides.choose(&mut self.rng).copied()
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// STARTUP SYSTEMS
+fn stsys_seed_sand_sim(
+    mut sand_sim: ResMut<SandSim>,
+    random: Res<Random>,
+) {
+    sand_sim.reseed(&random);
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// EVENT SYSTEMS
+fn evsys_wake_sand_sim(
+    mut voxel_changed_events: EventReader<VoxelChangedEvent>,
+    mut sand_sim: ResMut<SandSim>,
+    level_tree_query: Query<(Entity, &VoxelTree), With<LevelTree>>,
+    defs: Res<Defs>,
+) {
+    let (level_entity, voxel_tree) = level_tree_query.single();
+    for voxel_changed_event in voxel_changed_events.iter() {
+        if voxel_changed_event.tree != level_entity {
+            continue;
+        }
+
+        for change in voxel_changed_event.changes.iter() {
+            sand_sim.wake_block(voxel_tree, &change.coord, change.dim, &defs);
+        }
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// SYSTEMS
+fn sys_step_sand_sim(
+    mut sand_sim: ResMut<SandSim>,
+    mut health_events: EventWriter<HealthEvent>,
+    mut unit_query: Query<(&mut Unit, Option<&mut UnitMover>)>,
+    level_tree_query: Query<(&VoxelTree, &EntityTree), With<LevelTree>>,
+    game_time: Res<GameTime>,
+    defs: Res<Defs>,
+) {
+    if game_time.delta_steps() == 0 || sand_sim.is_idle() {
+        return;
+    }
+
+    let (voxel_tree, entity_tree) = level_tree_query.single();
+    for contact in sand_sim.step(voxel_tree, entity_tree, &defs) {
+        match contact {
+            SandContact::Displaced { entity, to } => {
+                if let Ok((mut unit, mover)) = unit_query.get_mut(entity) {
+                    unit.set_coord(&to);
+                    if let Some(mut mover) = mover {
+                        mover.clear_path();
+                    }
+                }
+            },
+            SandContact::Crushed { entity, damage } => {
+                health_events.send(HealthEvent::Sub(entity, damage));
+            },
+        }
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// FUNCTIONS
+/// Resident, not solid & no unit in it
+fn is_open(
+    voxel_tree: &VoxelTree,
+    coord: &IVec3,
+    voxels: &mut Accessor<Voxel>,
+    entities: &mut EntityAccessor,
+    defs: &Res<Defs>,
+) -> bool {
+    voxel_tree.is_resident(coord) && !voxels.get_value(coord).is_solid(defs) && entities.is_empty(coord)
+}
+
+/// Moves the voxel at `from` into `to`, whatever gas or liquid was there takes its place
+fn move_voxel(
+    from: &IVec3,
+    to: &IVec3,
+    voxels: &mut Accessor<Voxel>,
+) {
+    let displaced = voxels.get_value(to);
+    voxels.move_value(from, to);
+    if displaced != *voxels.background() {
+        voxels.set_value_on(from, &displaced);
+    }
+}
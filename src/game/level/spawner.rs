@@ -141,7 +141,7 @@ fn try_spawn_unit(
     coord: &IVec3,
     id: u8,
     commands: &mut Commands,
-    entities: &mut Accessor<Option<Entity>>,
+    entities: &mut EntityAccessor,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
     defs: &Res<Defs>,
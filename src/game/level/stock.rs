@@ -0,0 +1,77 @@
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// USE
+use crate::*;
+use bevy::utils::HashMap;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// PLUGIN
+pub struct StockPlugin;
+impl Plugin for StockPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MatterStock::default())
+            .add_event::<MatterDropEvent>()
+            .add_system(evsys_stock_matter_drops);
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// RESOURCES
+/// Matter gathered from the level, by matter id
+#[derive(Resource, Default)]
+pub struct MatterStock {
+    counts: HashMap<u8, u32>,
+}
+
+impl MatterStock {
+    pub fn count(
+        &self,
+        matter_id: u8,
+    ) -> u32 {
+        self.counts.get(&matter_id).copied().unwrap_or(0)
+    }
+
+    pub fn add(
+        &mut self,
+        matter_id: u8,
+        amount: u32,
+    ) {
+        *self.counts.entry(matter_id).or_insert(0) += amount;
+    }
+
+    /// False & nothing taken if there isn't enough
+    pub fn try_take(
+        &mut self,
+        matter_id: u8,
+        amount: u32,
+    ) -> bool {
+        match self.counts.get_mut(&matter_id) {
+            Some(count) if *count >= amount => {
+                *count -= amount;
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// EVENTS
+/// Matter broken out of the level, e.g. a voxel dug by a unit
+pub struct MatterDropEvent {
+    pub coord: IVec3,
+    pub voxel: Voxel,
+    pub source: Option<Entity>,
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// EVENT SYSTEMS
+fn evsys_stock_matter_drops(
+    mut drop_events: EventReader<MatterDropEvent>,
+    mut stock: ResMut<MatterStock>,
+    defs: Res<Defs>,
+) {
+    for drop_event in drop_events.iter() {
+        stock.add(drop_event.voxel.matter_id(), 1);
+        info!("Stocked 1 {} from {}, {} in stock", defs.matter.get_u8(drop_event.voxel.matter_id()).name(), drop_event.coord, stock.count(drop_event.voxel.matter_id()));
+    }
+}
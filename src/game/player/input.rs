@@ -44,6 +44,9 @@ pub enum InputAction {
 
     Z,
 
+    Undo,
+    Redo,
+
     Count,
 }
 
@@ -248,6 +251,9 @@ impl Default for InputBindings {
 
                 InputActionBinding::any(InputAction::Pause).with_keys(vec![KeyCode::Space]),
                 InputActionBinding::any(InputAction::Z).with_keys(vec![KeyCode::Z]),
+
+                InputActionBinding::all(InputAction::Undo).with_keys(vec![KeyCode::LControl, KeyCode::Z]),
+                InputActionBinding::all(InputAction::Redo).with_keys(vec![KeyCode::LControl, KeyCode::Y]),
                 
                 InputActionBinding::any(InputAction::Escape)   .with_keys(vec![KeyCode::Escape]),
                 InputActionBinding::any(InputAction::Inventory).with_keys(vec![KeyCode::Tab]),
@@ -296,6 +302,13 @@ fn sys_update_input(
         if is_flag_on_u8(state, InputStateFlag::JustPressed)  { input_state.just_pressed.set_bit_on(action as usize); }
         if is_flag_on_u8(state, InputStateFlag::Pressed)      { input_state.pressed.set_bit_on(action as usize); }
     }
+
+    // Ctrl+Z is undo, so Z only acts on its own while Ctrl isn't held
+    if input_state.pressed(InputAction::SlowMod) {
+        input_state.just_released.set_bit_off(InputAction::Z as usize);
+        input_state.just_pressed.set_bit_off(InputAction::Z as usize);
+        input_state.pressed.set_bit_off(InputAction::Z as usize);
+    }
 }
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
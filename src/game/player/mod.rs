@@ -98,6 +98,7 @@ impl TacticsPlayerBundle {
         commands: &mut Commands,
         camera: Entity,
         asset_loader: &Res<AssetLoader>,
+        defs: &Res<Defs>,
     ) -> Self {
         let cursor = commands.spawn(SceneBundle {
                 scene: asset_loader.models.get_handle("cursor"),
@@ -116,7 +117,7 @@ impl TacticsPlayerBundle {
             ),
             designator: PlayerDesignator::default(),
             orderer: PlayerOrderer::default(),
-            selector: PlayerSelector::new(cursor),
+            selector: PlayerSelector::new(cursor, defs),
         }
     }
 }
@@ -139,12 +140,13 @@ impl Player {
 fn stsys_spawn_player(
     mut commands: Commands,
     asset_loader: Res<AssetLoader>,
+    defs: Res<Defs>,
 ) {
     let tactice_camera = commands.spawn(PlayerCameraBundle::new(0))
         .insert(OrbitCameraBundle::new(OrbitCam::default(), Vec3::new(16.0, 16.0, 16.0), Vec3::new(0.0, 6.0, 0.0), Vec3::Y))
         .insert(AudioReceiver)
         .id();
-    let tactics_player_bundle = TacticsPlayerBundle::new(&mut commands, tactice_camera, &asset_loader);
+    let tactics_player_bundle = TacticsPlayerBundle::new(&mut commands, tactice_camera, &asset_loader, &defs);
     let player = commands.spawn(tactics_player_bundle).id();
     spawn_quickslots_gui(player, &mut commands, &asset_loader);
 }
@@ -78,9 +78,12 @@ impl OrderEvent {
         }
     }
 
-    pub fn get_voxel_target_order_action(orderer: &PlayerOrderer) -> OrderKind {
+    /// Holding `AltMod` digs the voxel instead of moving to it
+    pub fn get_voxel_target_order_action(orderer: &PlayerOrderer, input_state: &Res<InputState>) -> OrderKind {
         if let Some(order) = orderer.get_order_kind() {
             *order
+        } else if input_state.pressed(InputAction::AltMod) {
+            OrderKind::Action(ActionOrderKind::Dig)
         } else {
             OrderKind::Move
         }
@@ -130,7 +133,7 @@ impl OrderEvent {
                 }
             }
             OrderEventKind::Voxel(select) => {
-                let order = Order::new(OrderEvent::get_voxel_target_order_action(orderer), OrderTarget::Voxel(select));
+                let order = Order::new(OrderEvent::get_voxel_target_order_action(orderer, input_state), OrderTarget::Voxel(select));
                 println!("{:?}", order);
                 if !input_state.pressed(InputAction::MultiMod) {
                     OrderEvent::set_orders(&order, selector, orderable_query);
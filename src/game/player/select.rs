@@ -29,6 +29,62 @@ pub enum AreaShape {
     Cylinder { origin: IVec3, radius: u32, height: u32 },
 }
 
+impl AreaShape {
+    /// Inclusive min & max corners
+    pub fn bounds(&self) -> (IVec3, IVec3) {
+        match *self {
+            Self::Rect { start, end } => (start.min(end), start.max(end)),
+            Self::Sphere { origin, radius } => (origin - IVec3::splat(radius as i32), origin + IVec3::splat(radius as i32)),
+            Self::Cylinder { origin, radius, height } => (
+                origin - IVec3::new(radius as i32, 0, radius as i32),
+                origin + IVec3::new(radius as i32, height as i32 - 1, radius as i32),
+            ),
+        }
+    }
+
+    pub fn translated(&self, offset: &IVec3) -> Self {
+        match *self {
+            Self::Rect { start, end } => Self::Rect { start: start + *offset, end: end + *offset },
+            Self::Sphere { origin, radius } => Self::Sphere { origin: origin + *offset, radius },
+            Self::Cylinder { origin, radius, height } => Self::Cylinder { origin: origin + *offset, radius, height },
+        }
+    }
+
+    pub fn contains(&self, coord: &IVec3) -> bool {
+        self.intersects_block(coord, 1, 0)
+    }
+
+    /// Every shape is convex, so a block is inside if all of its corners are
+    pub fn contains_block(&self, block_origin: &IVec3, dim: usize) -> bool {
+        let far = dim as i32 - 1;
+        (0..8).all(|corner| self.contains(&(*block_origin + IVec3::new(corner & 1, (corner >> 1) & 1, (corner >> 2) & 1) * far)))
+    }
+
+    /// True if the `dim` sized block comes within `margin` voxels of the shape, face steps for `Rect`
+    pub fn intersects_block(&self, block_origin: &IVec3, dim: usize, margin: i32) -> bool {
+        let block_max = *block_origin + IVec3::splat(dim as i32 - 1);
+        let (min, max) = self.bounds();
+        if block_origin.cmpgt(max + margin).any() || block_max.cmplt(min - margin).any() {
+            return false;
+        }
+
+        match *self {
+            Self::Rect { .. } => {
+                let gap = (min - block_max).max(IVec3::ZERO) + (*block_origin - max).max(IVec3::ZERO);
+                gap.x + gap.y + gap.z <= margin
+            },
+            Self::Sphere { origin, radius } => {
+                let closest = origin.clamp(*block_origin, block_max) - origin;
+                length_squared(&closest) <= (radius as i64 + margin as i64).pow(2)
+            },
+            Self::Cylinder { origin, radius, .. } => {
+                let closest = origin.clamp(*block_origin, block_max) - origin;
+                length_squared(&IVec3::new(closest.x, 0, closest.z)) <= (radius as i64 + margin as i64).pow(2)
+            },
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SelectionType {
     Coord  = 0x01,
@@ -91,21 +147,16 @@ impl RaySelection {
     pub fn try_from_screenspace_raycast(
         player: &Player,
         window: &Window,
-        entities: &mut Accessor<Option<Entity>>,
+        voxels: &mut Accessor<Voxel>,
+        entities: &mut EntityAccessor,
         camera_query: &Query<(&Camera, &GlobalTransform), With<Camera3d>>,
-        rapier_context: &Res<RapierContext>,
+        defs: &Res<Defs>,
     ) -> Option<(Self)> {
         if let Ok((camera, camera_global_transform)) = camera_query.get(player.camera) {
             if let Some(cursor_position) = window.cursor_position() {
                 let ray = camera.viewport_to_world(camera_global_transform, cursor_position).unwrap();
-                if let Some((entity, ray_intersection)) = rapier_context.cast_ray_and_get_normal(ray.origin, ray.direction, 200.0, true, QueryFilter::new()) {
-                    let hit_point = ray.origin + ray.direction * ray_intersection.toi;
-
-                    let coord = (hit_point + ray_intersection.normal * -0.1).round().as_ivec3();
-                    let normal = ray_intersection.normal.as_ivec3();
-                    let entity = entities.get_value(&coord);
-
-                    return Some(Self::new(&coord, &normal, &entity));
+                if let Some(hit) = voxels.raycast(entities, ray.origin, ray.direction, 200.0, |voxel| voxel.is_solid(defs)) {
+                    return Some(Self::new(&hit.coord, &hit.normal, &hit.occupant));
                 }
             }
         }
@@ -204,4 +255,10 @@ fn evsys_receive_select_events(
             select_event.select(&mut selection, &mut spawn_events, &mut marker_events, &mut sound_2d_events, &mut voxel_events, &mut selectable_query, &input_state);
         }
     }
-}
\ No newline at end of file
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// FUNCTIONS
+fn length_squared(v: &IVec3) -> i64 {
+    (v.x as i64).pow(2) + (v.y as i64).pow(2) + (v.z as i64).pow(2)
+}
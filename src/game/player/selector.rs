@@ -29,13 +29,14 @@ pub struct PlayerSelector {
 impl PlayerSelector {
     pub fn new(
         cursor: Entity,
+        defs: &Res<Defs>,
     ) -> Self {
         Self {
             cursor,
             selected_voxels: vec![],
             selected_units: vec![],
             select_sound: None,
-            placing_voxel: Voxel::from_matter_id(1),
+            placing_voxel: Voxel::from_matter_id(defs.matter.id_u8("dirt")),
         }
     }
 
@@ -106,15 +107,17 @@ fn sys_update_selection(
     designator_query: Query<&PlayerDesignator>,
     window_query: Query<&Window>,
     camera_query: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    voxel_tree_query: Query<&VoxelTree, With<LevelTree>>,
     entity_tree_query: Query<&EntityTree, With<LevelTree>>,
     input_state: Res<InputState>,
-    rapier_context: Res<RapierContext>,
+    defs: Res<Defs>,
 ) {
     let window = window_query.single();
+    let mut voxels = voxel_tree_query.single().get_accessor();
     let mut entities = entity_tree_query.single().get_accessor();
 
     for (player_entity, player, selection) in player_query.iter() {
-        let ray = if let Some(ray) = RaySelection::try_from_screenspace_raycast(&player, &window, &mut entities, &camera_query, &rapier_context) {
+        let ray = if let Some(ray) = RaySelection::try_from_screenspace_raycast(&player, &window, &mut voxels, &mut entities, &camera_query, &defs) {
                 cursor_events.send(CursorEvent::Move(selection.cursor, ray));
                 ray
             } else {
@@ -130,7 +133,7 @@ fn sys_update_selection(
         }
 
         if input_state.just_released(InputAction::Inventory) {
-            spawn_events.send(SpawnEvent::Actor(ray.coord_selection().coord_plus_normal(), 1));
+            spawn_events.send(SpawnEvent::Actor(ray.coord_selection().coord_plus_normal(), defs.actors.id_u8("demon")));
         } else if input_state.pressed(InputAction::Inventory) && input_state.pressed(InputAction::Z){
 
         }
@@ -218,16 +221,20 @@ pub enum VoxelEvent {
 }
 
 fn sys_voxel_events(
+    mut commands: Commands,
     mut voxel_events: EventReader<VoxelEvent>,
     mut selector_query: Query<&mut PlayerSelector>,
     mut sound_2d_events: EventWriter<Sound2dEvent>,
-    level_tree_query: Query<&VoxelTree, With<LevelTree>>,
+    mut level_tree_query: Query<(&VoxelTree, &mut VoxelHistory), With<LevelTree>>,
+    input_state: Res<InputState>,
     asset_loader: Res<AssetLoader>,
 ) {
-    let mut voxels = level_tree_query.single().get_accessor();
+    let (tree, mut history) = level_tree_query.single_mut();
+    let edit_journal = ValueJournal::default();
+    let mut voxels = tree.get_accessor().with_journal(&edit_journal);
     for voxel_event in voxel_events.iter() {
         match voxel_event {
-            VoxelEvent::Destroy(coord_selection) => { voxels.set_voxel_off(coord_selection.coord()); }
+            VoxelEvent::Destroy(coord_selection) => { voxels.set_value_off(coord_selection.coord()); }
             VoxelEvent::Create(coord_selection, voxel)  => { voxels.set_value_on(&coord_selection.coord_plus_normal(), voxel); }
             VoxelEvent::Set(coord_selection) => {
                 if let Ok(mut selector) = selector_query.get_single_mut() {
@@ -237,4 +244,16 @@ fn sys_voxel_events(
             }
         }
     }
+
+    // A drag keeps adding to the same group until the buttons are let go
+    history.record(std::mem::take(&mut *edit_journal.lock().unwrap()));
+    if !input_state.pressed(InputAction::PrimaryAction) && !input_state.pressed(InputAction::SecondaryAction) {
+        history.end_group();
+    }
+
+    if input_state.just_pressed(InputAction::Undo) {
+        history.undo(&mut tree.get_accessor(), &mut commands);
+    } else if input_state.just_pressed(InputAction::Redo) {
+        history.redo(&mut tree.get_accessor(), &mut commands);
+    }
 }
\ No newline at end of file
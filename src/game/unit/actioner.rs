@@ -15,12 +15,21 @@ impl Plugin for UnitActionerPlugin {
     }
 }
 
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// CONSTANTS
+/// Damage dealt to a voxel by each finished dig action
+const UNIT_DIG_POWER: u16 = 5;
+/// Furthest a unit can dig on any axis
+const UNIT_DIG_REACH: i32 = 1;
+
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // ENUMS
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum UnitActionKind {
     Attack(u8),
     Use(u8),
+    /// Repeats until the voxel breaks, dealing this much damage every cast
+    Dig(u16),
 }
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
@@ -113,10 +122,30 @@ fn sys_queue_actions(
                             // if we're not in ability range && no move orders, assign order
                             println!("[Ability: Unit] Order");
                         }
+                        ActionOrderKind::Dig => {
+                            println!("Abandoned [Dig: Unit] Order");
+                            order_complete = true;
+                        }
                     }
                 }
                 OrderTarget::Voxel(select) => {
-                    
+                    if let ActionOrderKind::Dig = action_order.kind() {
+                        // if we're not in dig range && no move orders, walk next to the face that was picked
+                        let voxel = voxels.get_value(select.coord());
+                        if defs.matter.get_u8(voxel.matter_id()).durability() == 0 {
+                            println!("Abandoned [Dig: Voxel] Order");
+                            order_complete = true;
+                        } else if let Ok(this_unit) = unit_query.get(this_entity) {
+                            if !is_in_dig_reach(this_unit, select.coord()) {
+                                if orderable.next_move_order().is_none() {
+                                    move_order = Some(MoveOrder::new(OrderTarget::Voxel(*select)));
+                                }
+                            } else {
+                                actioner.queue_action(UnitAction::new(UnitActionKind::Dig(UNIT_DIG_POWER), action_order.target()));
+                                order_complete = true;
+                            }
+                        }
+                    }
                 }
             }
         } else {
@@ -136,13 +165,16 @@ fn sys_queue_actions(
 fn sys_update_actions(
     mut health_events: EventWriter<HealthEvent>,
     mut sound_3d_events: EventWriter<Sound3dEvent>,
+    mut drop_events: EventWriter<MatterDropEvent>,
     mut actioner_query: Query<(Entity, &mut UnitActioner)>,
     unit_query: Query<(&Unit)>,
-    level_tree_query: Query<&EntityTree, With<LevelTree>>,
+    level_tree_query: Query<(&VoxelTree, &EntityTree), With<LevelTree>>,
     game_time: Res<GameTime>,
     asset_loader: Res<AssetLoader>,
+    defs: Res<Defs>,
 ) {
-    let mut entities = level_tree_query.single().get_accessor();
+    let (voxel_tree, entity_tree) = level_tree_query.single();
+    let mut entities = entity_tree.get_accessor();
     for (entity, mut actioner) in actioner_query.iter_mut() {
         let mut action_complete = false;
         if let Some(mut action) = actioner.action_queue.front_mut() {
@@ -157,7 +189,24 @@ fn sys_update_actions(
                         health_events.send(HealthEvent::Sub(target_entity, 1));
                     }
                     OrderTarget::Voxel(coord_selection) => {
-
+                        if let UnitActionKind::Dig(power) = action.kind {
+                            let in_reach = unit_query.get(entity).is_ok_and(|unit| is_in_dig_reach(unit, coord_selection.coord()));
+                            if in_reach {
+                                match voxel_tree.damage_voxel(coord_selection.coord(), power, &defs) {
+                                    VoxelDamage::Damaged { .. } => {
+                                        sound_3d_events.send(Sound3dEvent::new(asset_loader.sounds.get_handle("8bit/swipe"), coord_selection.coord_vec3(), 0.5));
+                                        // Keep digging until it breaks
+                                        action.acc = 0;
+                                        action_complete = false;
+                                    }
+                                    VoxelDamage::Destroyed(voxel) => {
+                                        println!("Completed [Dig: Voxel] Order");
+                                        drop_events.send(MatterDropEvent { coord: *coord_selection.coord(), voxel, source: Some(entity) });
+                                    }
+                                    VoxelDamage::Immune => {}
+                                }
+                            }
+                        }
                     }
                 }
             }
@@ -167,4 +216,13 @@ fn sys_update_actions(
             actioner.action_queue.pop_front();
         }
     }
-}
\ No newline at end of file
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// FUNCTIONS
+fn is_in_dig_reach(
+    unit: &Unit,
+    coord: &IVec3,
+) -> bool {
+    (*unit.coord() - *coord).abs().max_element() <= UNIT_DIG_REACH
+}
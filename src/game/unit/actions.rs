@@ -55,7 +55,7 @@ fn sys_execute_actions(
 // pub fn apply_next_coord(
 //     &mut self,
 //     unit_entity: &Option<Entity>,
-//     entities: &mut Accessor<Option<Entity>>,
+//     entities: &mut EntityAccessor,
 // ) {
 //     if let Some(next_coord) = self.desired_coord {
 //         if entities.get_value(&next_coord).is_none() {
@@ -42,7 +42,7 @@ impl Plugin for UnitPlugin {
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // CONSTANTS
-const UNIT_STATE_BUSY_MASK: u16 = UnitState::Moving as u16 | UnitState::Acting as u16;
+const UNIT_STATE_BUSY_MASK: u16 = UnitBusyState::Moving as u16 | UnitBusyState::Acting as u16;
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // BUNDLES
@@ -56,6 +56,7 @@ pub struct UnitBundle {
     rigidbody: RigidBody,
     collider: Collider,
     transform: Transform,
+    stream_focus: StreamFocus,
 }
 
 impl Default for UnitBundle {
@@ -66,6 +67,7 @@ impl Default for UnitBundle {
             rigidbody: RigidBody::Fixed,
             collider: Collider::cuboid(0.5, 0.5, 0.5),
             transform: Transform::from_translation(Vec3::ZERO),
+            stream_focus: StreamFocus,
         }
     }
 }
@@ -98,7 +100,7 @@ impl UnitBundle {
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // ENUMS
 #[derive(Clone, Copy, PartialEq, Eq)]
-pub enum UnitState {
+pub enum UnitBusyState {
     None   = 0x00,
     Moving = 0x01,
     Acting = 0x02,
@@ -145,6 +147,9 @@ impl Unit {
 
     //-------------------------------- -------------------------------- --------------------------------
     // GET SET
+    /// Only moves the unit, its place in the [EntityTree] is left to the caller
+    pub fn set_coord(&mut self, coord: &IVec3) { self.coord = *coord; }
+
     pub fn try_get_unit_entity(
         entity: &Entity,
         unit_query: &Query<&Unit>,
@@ -141,7 +141,7 @@ impl UnitMover {
         unit: &Unit,
         target: &IVec3,
         voxels: &mut Accessor<Voxel>,
-        entities: &mut Accessor<Option<Entity>>,
+        entities: &mut EntityAccessor,
         defs: &Res<Defs>,
     ) -> bool {
         if let Some(path_target) = self.path.first() {
@@ -171,7 +171,7 @@ impl UnitMover {
         unit: &Unit,
         target: &IVec3,
         voxels: &mut Accessor<Voxel>,
-        entities: &mut Accessor<Option<Entity>>,
+        entities: &mut EntityAccessor,
         state_query: &Query<&UnitState>,
         defs: &Res<Defs>,
     ) -> bool {
@@ -277,7 +277,12 @@ fn sys_move_unit(
             if entities.adjacent_value_from_direction(unit.coord(), GridDirection::Bottom).is_some() {
                 mover.clear_path();
             } else {
-                mover.fall_height += 1;
+                // Liquid breaks the fall
+                if voxels.adjacent_value_from_direction(unit.coord(), GridDirection::Bottom).is_liquid(&defs) {
+                    mover.fall_height = 0;
+                } else {
+                    mover.fall_height += 1;
+                }
                 entities.set_value_off(unit.coord());
                 entities.set_value_on(&(*unit.coord() - IVec3::Y), &Some(entity));
                 unit.coord -= IVec3::Y;
@@ -289,8 +294,10 @@ fn sys_move_unit(
             mover.fall_height = 0;
         }
         mover.move_acc += game_time.delta_steps();
-        if mover.move_acc >= mover.steps_per_voxel() {
-            mover.move_acc -= mover.steps_per_voxel();
+        // Wading out of liquid is as slow as wading through it
+        let steps_per_voxel = mover.steps_per_voxel() + voxels.get_value(unit.coord()).move_cost(&defs);
+        if mover.move_acc >= steps_per_voxel {
+            mover.move_acc -= steps_per_voxel;
             if let Some(coord) = mover.path.pop() {
                 if entities.get_value(&coord).is_some() {
                     mover.clear_path();
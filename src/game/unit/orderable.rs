@@ -23,6 +23,8 @@ pub enum ActionOrderKind {
     Use,
     Attack,
     Ability(u32),
+    /// Mine out the targeted voxel
+    Dig,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -31,7 +31,7 @@ impl<'a> Iterator for OnMaskIter<'a> {
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // Bitmask
-#[derive(Default, Clone, Debug)]
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
 pub struct Bitmask {
     pub size: usize,
     pub word_num: usize,
@@ -141,6 +141,14 @@ impl Bitmask {
         0 == (self.words[index >> 6] & ((1 as usize) << (index & 63)))
     }
 
+    pub fn is_on(&self) -> bool {
+        self.words.iter().all(|word| *word == usize::MAX)
+    }
+
+    pub fn is_off(&self) -> bool {
+        self.words.iter().all(|word| *word == 0)
+    }
+
     pub fn set_on(&mut self) {
         for word in self.words.iter_mut() { *word = usize::MAX; }
     }
@@ -160,6 +168,10 @@ impl Bitmask {
     pub fn set_bit_off(&mut self, index: usize) {
         self.words[index >> 6] &= !((1 as usize) << (index & 63));
     }
+
+    pub fn set_union(&mut self, other: &Bitmask) {
+        for (word, other_word) in self.words.iter_mut().zip(other.words.iter()) { *word |= *other_word; }
+    }
 }
 
 pub fn is_flag_on_u8<T: Flag>(mask: u8, flag: T) -> bool {
@@ -38,6 +38,7 @@ pub struct PlayerCameraBundle {
     bloom_settings: BloomSettings,
     player_camera: PlayerCamera,
     sound_3d_listener: Sound3dListener,
+    stream_focus: StreamFocus,
 }
 
 impl PlayerCameraBundle {
@@ -65,6 +66,7 @@ impl PlayerCameraBundle {
             bloom_settings: BloomSettings::default(),
             player_camera: PlayerCamera::new(player_number),
             sound_3d_listener: Sound3dListener,
+            stream_focus: StreamFocus,
         }
     }
 }
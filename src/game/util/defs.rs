@@ -1,6 +1,8 @@
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // USE
 use crate::*;
+use bevy::utils::HashMap;
+use std::time::SystemTime;
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // PLUGIN
@@ -10,10 +12,27 @@ impl Plugin for DefsPlugin {
         &self,
         app: &mut App,
     ) {
-        app.insert_resource(Defs::from_load_or_default());
+        let defs = Defs::from_load_or_default();
+        app.insert_resource(DefsWatcher::new(&defs))
+            .insert_resource(defs)
+            .add_event::<DefsReloaded>()
+            .add_system(sys_reload_changed_defs.in_base_set(CoreSet::First));
     }
 }
 
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// CONSTANTS
+/// Durability of any solid matter, see [MatterDef::durability]
+const MATTER_BASE_DURABILITY: u16 = 10;
+const MATTER_HARD_DURABILITY: u16 = 30;
+const MATTER_DENSE_DURABILITY: u16 = 10;
+const MATTER_HEAVY_DURABILITY: u16 = 10;
+/// Extra steps a unit takes to move through liquid, see [MatterDef::move_cost]
+const MATTER_LIQUID_MOVE_COST: u32 = 2;
+const MATTER_DENSE_LIQUID_MOVE_COST: u32 = 4;
+/// Seconds between checks of the def files for edits, see [DefsWatcher]
+const DEFS_WATCH_INTERVAL: f32 = 0.5;
+
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // RESOURCES
 #[derive(Resource)]
@@ -42,41 +61,184 @@ impl Default for Defs {
 }
 
 impl Defs {
+    /// Name & path of each def type's file
+    fn files(&self) -> [(&str, String); 6] {
+        [
+            (&self.matter.name, self.matter.path()),
+            (&self.levels.name, self.levels.path()),
+            (&self.items.name, self.items.path()),
+            (&self.actors.name, self.actors.path()),
+            (&self.abilities.name, self.abilities.path()),
+            (&self.projectiles.name, self.projectiles.path()),
+        ]
+    }
+
+    /// Logs every issue found loading the defs, then panics if any of them were errors, see [Defs::load]
     pub fn from_load_or_default() -> Self {
+        let mut report = DefsReport::default();
+        let defs = Self::load(&mut report);
+        report.log();
+        if report.has_errors() {
+            panic!("{} def errors, see the log above or run with {}", report.error_count(), DEFS_CHECK_ARG);
+        }
+
+        defs
+    }
+
+    /// Loads, initializes & validates every def type, issues are collected in `report` instead of stopping the load
+    pub fn load(
+        report: &mut DefsReport,
+    ) -> Self {
         let mut defs = Self::default();
 
-        defs.matter.init_load_or_default();
-        defs.levels.init_load_or_default();
-        defs.items.init_load_or_default();
-        defs.actors.init_load_or_default();
-        defs.abilities.init_load_or_default();
-        defs.projectiles.init_load_or_default();
+        defs.matter.init_load_or_default(report);
+        defs.levels.init_load_or_default(report);
+        defs.items.init_load_or_default(report);
+        defs.actors.init_load_or_default(report);
+        defs.abilities.init_load_or_default(report);
+        defs.projectiles.init_load_or_default(report);
 
         for matter in defs.matter.defs.iter_mut() {
             matter.init();
         }
 
+        let projectiles = &defs.projectiles;
+        defs.abilities.for_each_located(|ability, location| ability.init(projectiles, location, report));
+
+        defs.validate(report);
         defs
     }
+
+    fn validate(
+        &self,
+        report: &mut DefsReport,
+    ) {
+        // Voxels keep matter ids in a u8 & the tree background is matter 0
+        if self.matter.defs.len() > u8::MAX as usize + 1 {
+            report.error(&DefLocation::file(&self.matter.path()), format!("{} matter defs, voxels only fit {}", self.matter.defs.len(), u8::MAX as usize + 1));
+        }
+
+        if !self.matter.try_get(0).is_some_and(|matter| matter.is_void()) {
+            report.error(&self.matter.location(0), "the first matter def has to be void, it fills empty space");
+        }
+
+        self.matter.validate(report);
+        self.levels.validate(report);
+        self.items.validate(report);
+        self.actors.validate(report);
+        self.abilities.validate(report);
+        self.projectiles.validate(report);
+    }
+}
+
+/// Polls the def files for edits, so they're reloaded without restarting, see [DefsReloaded]
+#[derive(Resource)]
+pub struct DefsWatcher {
+    /// Last seen modified time of each def type's file, by def type name
+    modified: HashMap<String, SystemTime>,
+    /// Def types edited since the last clean reload, kept through edits with errors
+    changed: Vec<String>,
+    timer: Timer,
+}
+
+impl DefsWatcher {
+    pub fn new(
+        defs: &Defs,
+    ) -> Self {
+        let mut watcher = Self {
+            modified: HashMap::default(),
+            changed: vec![],
+            timer: Timer::from_seconds(DEFS_WATCH_INTERVAL, TimerMode::Repeating),
+        };
+
+        watcher.poll(defs);
+        watcher.changed.clear();
+        watcher
+    }
+
+    /// Adds the def types whose files changed since the last poll to `changed`, true if there were any
+    ///
+    /// Nothing is reported while any file is missing or empty, an editor may be halfway through saving it
+    /// & loading would write a default def over it
+    fn poll(
+        &mut self,
+        defs: &Defs,
+    ) -> bool {
+        let mut modified: Vec<(String, SystemTime)> = vec![];
+        for (name, path) in defs.files() {
+            match std::fs::metadata(&path) {
+                Ok(metadata) if metadata.len() > 0 => {
+                    if let Ok(time) = metadata.modified() {
+                        modified.push((name.to_string(), time));
+                    }
+                },
+                _ => return false,
+            }
+        }
+
+        let mut any_changed = false;
+        for (name, time) in modified.into_iter() {
+            if self.modified.insert(name.clone(), time) != Some(time) {
+                any_changed = true;
+                if !self.changed.contains(&name) {
+                    self.changed.push(name);
+                }
+            }
+        }
+
+        any_changed
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// EVENTS
+/// Sent once edited defs have loaded & validated cleanly, [Defs] has already been swapped for them
+pub struct DefsReloaded {
+    /// Names of the def types whose files were edited, e.g. `"matter"`
+    pub changed: Vec<String>,
+}
+
+impl DefsReloaded {
+    pub fn has_changed(&self, name: &str) -> bool { self.changed.iter().any(|changed| changed == name) }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// TRAITS
+/// Names are unique within a [DefType] & are how other defs reference it, ids are just positions in its file
+pub trait Def: Default + Serialize + for<'a> Deserialize<'a> {
+    fn name(&self) -> &str;
+
+    /// Reports anything wrong with this def alone, runs after every def type is initialized
+    fn validate(
+        &self,
+        _location: &DefLocation,
+        _report: &mut DefsReport,
+    ) {}
 }
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // STRUCTS
-pub struct DefType<T: Default + Serialize + for<'a> Deserialize<'a>> {
+pub struct DefType<T: Def> {
     name: String,
     defs: Vec<T>,
+    /// Every def's id by name, see [DefType::id]
+    ids: HashMap<String, u32>,
+    /// Line & column where each def starts in its file, for [DefLocation]s
+    positions: Vec<Option<(usize, usize)>>,
 }
 
-impl<T: Default + Serialize + for<'a> Deserialize<'a>> Default for DefType<T> {
+impl<T: Def> Default for DefType<T> {
     fn default() -> Self {
         Self {
             name: "NULL".into(),
             defs: vec![],
+            ids: HashMap::default(),
+            positions: vec![],
         }
     }
 }
 
-impl<T: Default + Serialize + for<'a> Deserialize<'a>> DefType<T> {
+impl<T: Def> DefType<T> {
     fn new(
         name: &str,
     ) -> Self {
@@ -86,44 +248,168 @@ impl<T: Default + Serialize + for<'a> Deserialize<'a>> DefType<T> {
         }
     }
 
+    fn path(&self) -> String { "assets/defs/".to_string() + &self.name + ".ron" }
+
+    /// Falls back to a single default def if the file doesn't parse, so the rest can still be checked
     fn init_load_or_default(
         &mut self,
+        report: &mut DefsReport,
     ) {
-        if let Some(contents) = Data::try_read_file_to_string(&("assets/defs/".to_string() + &self.name + ".ron")) {
-            if let Ok(defs) = ron::from_str::<Vec<T>>(&contents) {
-                self.defs = defs;
-                return;
-            } else {
-                panic!("{} defs present but failed to load!", self.name);
+        let path = self.path();
+        if let Some(contents) = Data::try_read_file_to_string(&path) {
+            match ron::from_str::<Vec<T>>(&contents) {
+                Ok(defs) => {
+                    self.defs = defs;
+                    self.find_positions(&contents);
+                },
+                Err(error) => {
+                    report.error(&DefLocation::file(&path).with_position(error.position.line, error.position.col), error.code.to_string());
+                    self.defs = vec![T::default()];
+                },
             }
+
+            self.index_names(report);
+            return;
         }
-        
+
+        report.warning(&DefLocation::file(&path), "missing or empty, wrote a default def");
         self.defs.push(T::default());
+        self.index_names(report);
         if let Ok(contents) = Data::to_ron_string_pretty(&self.defs) {
-            Data::try_write_file(&("assets/defs/".to_string() + &self.name + ".ron"), contents.as_bytes());
+            Data::try_write_file(&path, contents.as_bytes());
         }
     }
 
+    /// Steps over each def with the RON parser to find where it starts, so formatting & comments don't matter
+    /// 
+    /// Defs go without a position if the file isn't a plain list, they were parsed from it some other way
+    fn find_positions(
+        &mut self,
+        contents: &str,
+    ) {
+        // A new deserializer skips whitespace & comments, its remainder starts at the next token
+        let skip = |offset: usize| -> Option<usize> {
+            Some(contents.len() - ron::Deserializer::from_str(&contents[offset..]).ok()?.remainder().len())
+        };
+
+        let find_offsets = || -> Option<Vec<usize>> {
+            let mut offset = skip(0)?;
+            offset = skip(offset + contents[offset..].strip_prefix('[').map(|_| 1)?)?;
+            let mut offsets = vec![];
+            while !contents[offset..].starts_with(']') {
+                offsets.push(offset);
+                let mut deserializer = ron::Deserializer::from_str(&contents[offset..]).ok()?;
+                serde::de::IgnoredAny::deserialize(&mut deserializer).ok()?;
+                offset = skip(contents.len() - deserializer.remainder().len())?;
+                if contents[offset..].starts_with(',') {
+                    offset = skip(offset + 1)?;
+                }
+            }
+
+            Some(offsets)
+        };
+
+        let offsets = find_offsets().filter(|offsets| offsets.len() == self.defs.len()).unwrap_or_default();
+        self.positions = (0..self.defs.len())
+            .map(|id| {
+                let offset = *offsets.get(id)?;
+                let line_start = contents[..offset].rfind('\n').map_or(0, |index| index + 1);
+                Some((contents[..offset].matches('\n').count() + 1, contents[line_start..offset].chars().count() + 1))
+            })
+            .collect();
+    }
+
+    fn index_names(
+        &mut self,
+        report: &mut DefsReport,
+    ) {
+        self.ids.clear();
+        for (id, def) in self.defs.iter().enumerate() {
+            if def.name().is_empty() {
+                report.error(&self.location(id), "has no name");
+            } else if let Some(first_id) = self.ids.get(def.name()) {
+                report.error(&self.location(id), format!("name is already used by {} def {}", self.name, first_id));
+            } else {
+                self.ids.insert(def.name().into(), id as u32);
+            }
+        }
+    }
+
+    /// Where def `id` was loaded from
+    pub fn location(
+        &self,
+        id: usize,
+    ) -> DefLocation {
+        let location = DefLocation::file(&self.path()).with_def(&format!("{} {}", self.name, id));
+        match self.positions.get(id).copied().flatten() {
+            Some((line, column)) => location.with_position(line, column),
+            None => location,
+        }
+    }
+
+    fn for_each_located(
+        &mut self,
+        mut f: impl FnMut(&mut T, &DefLocation),
+    ) {
+        for id in 0..self.defs.len() {
+            let location = self.location(id);
+            f(&mut self.defs[id], &location);
+        }
+    }
+
+    fn validate(
+        &self,
+        report: &mut DefsReport,
+    ) {
+        for (id, def) in self.defs.iter().enumerate() {
+            def.validate(&self.location(id), report);
+        }
+    }
+
+    /// Id of the def called `name`, panics if there's none
+    pub fn id(
+        &self,
+        name: &str,
+    ) -> u32 {
+        self.try_id(name).unwrap_or_else(|| panic!("{} defs have no {}!", self.name, name))
+    }
+
+    /// Like [DefType::id], for defs referenced by `u8` such as matter
+    pub fn id_u8(
+        &self,
+        name: &str,
+    ) -> u8 {
+        u8::try_from(self.id(name)).unwrap_or_else(|_| panic!("{} def {} is out of u8 range!", self.name, name))
+    }
+
+    pub fn try_id(
+        &self,
+        name: &str,
+    ) -> Option<u32> {
+        self.ids.get(name).copied()
+    }
+
+    /// Def with the id `id`, panics if there's none
     pub fn get(
         &self,
         id: u32,
     ) -> &T {
-        if id as usize >= self.defs.len() {
-            &self.defs[0]
-        } else {
-            &self.defs[id as usize]
-        }
+        self.try_get(id).unwrap_or_else(|| panic!("{} defs have no id {}!", self.name, id))
     }
 
+    /// Like [DefType::get], for defs referenced by `u8` such as matter
     pub fn get_u8(
         &self,
         id: u8,
     ) -> &T {
-        if id as usize >= self.defs.len() {
-            &self.defs[0]
-        } else {
-            &self.defs[id as usize]
-        }
+        self.get(id as u32)
+    }
+
+    pub fn try_get(
+        &self,
+        id: u32,
+    ) -> Option<&T> {
+        self.defs.get(id as usize)
     }
 }
 
@@ -137,7 +423,9 @@ pub struct MatterDef {
     state: Option<VoxelMatterState>,
     properties: Option<Vec<VoxelMatterProperty>>,
     texture_id_data: Option<[u32; 6]>,
+    /// Connected face textures, indexed by which same matter neighbours a face joins minus 1, see [MatterDef::connected_texture_id]
     meta_texture_id_data: Option<[u32; 15]>,
+    durability_data: Option<u16>,
     
     #[serde(skip)]
     matter_mask: u16,
@@ -145,6 +433,8 @@ pub struct MatterDef {
     texture_ids: [u32; 6],
     #[serde(skip)]
     meta_texture_ids: [u32; 15],
+    #[serde(skip)]
+    durability: u16,
 }
 
 impl Default for MatterDef {
@@ -155,10 +445,12 @@ impl Default for MatterDef {
             properties: None,
             texture_id_data: None,
             meta_texture_id_data: None,
+            durability_data: None,
             
             matter_mask: 0,
             texture_ids: [0; 6],
             meta_texture_ids: [0; 15],
+            durability: 0,
         }
     }
 }
@@ -184,8 +476,28 @@ impl MatterDef {
         if let Some(meta_texture_id_data) = self.meta_texture_id_data {
             self.meta_texture_ids = meta_texture_id_data;
         }
+
+        self.durability = self.durability_data.unwrap_or_else(|| self.default_durability());
     }
 
+    /// Solids start at [MATTER_BASE_DURABILITY] and are toughened by `Hard`, `Dense` & `Heavy`, nothing else can be dug
+    fn default_durability(
+        &self,
+    ) -> u16 {
+        if !self.is_solid() {
+            return 0;
+        }
+
+        let mut durability = MATTER_BASE_DURABILITY;
+        if self.has_property(VoxelMatterProperty::Hard) { durability += MATTER_HARD_DURABILITY; }
+        if self.has_property(VoxelMatterProperty::Dense) { durability += MATTER_DENSE_DURABILITY; }
+        if self.has_property(VoxelMatterProperty::Heavy) { durability += MATTER_HEAVY_DURABILITY; }
+        durability
+    }
+
+    /// Damage a voxel of this matter takes before it breaks, 0 if it can't be broken at all
+    pub fn durability(&self) -> u16 { self.durability }
+
     pub fn texture_id(
         &self,
         face: u8,
@@ -200,6 +512,22 @@ impl MatterDef {
         self.meta_texture_ids[index as usize]
     }
 
+    pub fn has_connected_textures(&self) -> bool { self.meta_texture_id_data.is_some() }
+
+    /// Texture of `face` given the [ConnectedTextureMask] of the neighbours it joins, a face joining none keeps its
+    /// own texture & every other mask picks meta texture `mask - 1`
+    pub fn connected_texture_id(
+        &self,
+        face: u8,
+        mask: u8,
+    ) -> u32 {
+        if mask == 0 || !self.has_connected_textures() {
+            return self.texture_id(face);
+        }
+
+        self.meta_texture_id(mask - 1)
+    }
+
     pub fn is_opaque(
         &self,
     ) -> bool {
@@ -211,6 +539,102 @@ impl MatterDef {
     ) -> bool {
         self.matter_mask & VoxelMatterState::Solid as u16 == VoxelMatterState::Solid as u16
     }
+
+    pub fn is_liquid(
+        &self,
+    ) -> bool {
+        self.matter_mask & VoxelMatterState::Solid as u16 == VoxelMatterState::Liquid as u16
+    }
+
+    /// No matter at all, unlike gas nothing is displaced by filling it
+    pub fn is_void(
+        &self,
+    ) -> bool {
+        self.matter_mask & VoxelMatterState::Solid as u16 == VoxelMatterState::Void as u16
+    }
+
+    pub fn has_property(
+        &self,
+        property: VoxelMatterProperty,
+    ) -> bool {
+        self.matter_mask & property as u16 != 0
+    }
+
+    /// Solids that aren't `Dense` fall apart when nothing holds them up
+    pub fn is_loose(
+        &self,
+    ) -> bool {
+        self.is_solid() && !self.has_property(VoxelMatterProperty::Dense)
+    }
+
+    /// `Hard` solids that hold together connect into structures, which fall once nothing holds them up, see [IntegritySim]
+    pub fn is_structural(
+        &self,
+    ) -> bool {
+        self.is_solid() && self.has_property(VoxelMatterProperty::Hard) && !self.is_loose()
+    }
+
+    /// Only liquids slow units down, `Dense` liquids more so
+    pub fn move_cost(
+        &self,
+    ) -> u32 {
+        if !self.is_liquid() {
+            return 0;
+        }
+
+        if self.has_property(VoxelMatterProperty::Dense) { MATTER_LIQUID_MOVE_COST + MATTER_DENSE_LIQUID_MOVE_COST } else { MATTER_LIQUID_MOVE_COST }
+    }
+}
+
+impl Def for MatterDef {
+    fn name(&self) -> &str { &self.name }
+
+    fn validate(
+        &self,
+        location: &DefLocation,
+        report: &mut DefsReport,
+    ) {
+        let atlas_size = (TEX_ATLAS_DIM * TEX_ATLAS_DIM) as u32;
+        let texture_ids = self.texture_id_data.iter().flatten().chain(self.meta_texture_id_data.iter().flatten());
+        for texture_id in texture_ids {
+            if *texture_id >= atlas_size {
+                report.error(location, format!("texture id {} is outside the {} cell atlas", texture_id, atlas_size));
+            }
+        }
+
+        let properties = self.properties.clone().unwrap_or_default();
+        for (index, property) in properties.iter().enumerate() {
+            if properties[..index].contains(property) {
+                report.warning(location, format!("{:?} is listed more than once", property));
+            }
+        }
+
+        if properties.contains(&VoxelMatterProperty::Undefined01) || properties.contains(&VoxelMatterProperty::Undefined02) {
+            report.warning(location, "undefined properties do nothing yet");
+        }
+
+        if self.is_void() {
+            if self.texture_id_data.is_some() || !properties.is_empty() {
+                report.warning(location, "void matter is never drawn, its textures & properties do nothing");
+            }
+
+            return;
+        }
+
+        if self.texture_id_data.is_none() {
+            report.error(location, "has no texture_id_data, every face would show atlas cell 0");
+        }
+
+        if !self.is_solid() {
+            if self.has_property(VoxelMatterProperty::Hard) {
+                report.warning(location, "Hard only means something for solids");
+            }
+
+            if self.durability_data.is_some() {
+                report.warning(location, "durability_data lets units dig matter that isn't solid");
+            }
+        }
+    }
 }
 
 //================================-================================-================================ 
@@ -229,6 +653,10 @@ impl Default for LevelDef {
     }
 }
 
+impl Def for LevelDef {
+    fn name(&self) -> &str { &self.name }
+}
+
 //================================-================================-================================ 
 // Item
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -245,6 +673,10 @@ impl Default for ItemDef {
     }
 }
 
+impl Def for ItemDef {
+    fn name(&self) -> &str { &self.name }
+}
+
 //================================-================================-================================ 
 // Actor
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -262,6 +694,10 @@ impl Default for ActorDef {
     }
 }
 
+impl Def for ActorDef {
+    fn name(&self) -> &str { &self.name }
+}
+
 //================================-================================-================================ 
 // Ability
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -285,9 +721,12 @@ pub struct AbilityCost(AbilityResource, AbilityResourceDrain);
 pub struct AbilityDef {
     pub name: String,
     costs: Vec<AbilityCost>,
-    projectiles: Vec<u32>,
+    /// [ProjectileDef] names, resolved at load, see [AbilityDef::init]
+    projectiles: Vec<String>,
     valid_targets: Vec<SelectionType>,
 
+    #[serde(skip)]
+    projectile_ids: Vec<u32>,
     #[serde(skip)]
     valid_targets_mask: u8,
 }
@@ -297,14 +736,50 @@ impl Default for AbilityDef {
         Self {
             name: "Throw Hammer".into(),
             costs: vec![AbilityCost(AbilityResource::Stamina, AbilityResourceDrain::Instant(5))],
-            projectiles: vec![0],
+            projectiles: vec!["Hammer".into()],
             valid_targets: vec![SelectionType::Coord, SelectionType::Unit],
+            projectile_ids: vec![0],
             valid_targets_mask: SelectionType::Coord as u8 | SelectionType::Unit as u8,
         }
     }
 }
 
+impl Def for AbilityDef {
+    fn name(&self) -> &str { &self.name }
+
+    fn validate(
+        &self,
+        location: &DefLocation,
+        report: &mut DefsReport,
+    ) {
+        if self.valid_targets.is_empty() {
+            report.warning(location, "has no valid_targets, it can never be used");
+        }
+    }
+}
+
 impl AbilityDef {
+    /// Projectiles that don't exist are reported & left out
+    pub fn init(
+        &mut self,
+        projectiles: &DefType<ProjectileDef>,
+        location: &DefLocation,
+        report: &mut DefsReport,
+    ) {
+        self.projectile_ids = self.projectiles.iter()
+            .filter_map(|projectile| {
+                let id = projectiles.try_id(projectile);
+                if id.is_none() {
+                    report.error(location, format!("references unknown projectile {}", projectile));
+                }
+
+                id
+            })
+            .collect();
+    }
+
+    pub fn projectile_ids(&self) -> &Vec<u32> { &self.projectile_ids }
+
     pub fn is_selection_valid_target(&self, selection: &Selection) -> bool {
         let mut mask = 0;
         match *selection {
@@ -341,6 +816,110 @@ impl Default for ProjectileDef {
     }
 }
 
+impl Def for ProjectileDef {
+    fn name(&self) -> &str { &self.name }
+
+    fn validate(
+        &self,
+        location: &DefLocation,
+        report: &mut DefsReport,
+    ) {
+        if self.scale <= 0.0 {
+            report.error(location, format!("scale {} has to be above 0", self.scale));
+        }
+
+        if self.steps_per_voxel == 0 {
+            report.error(location, "steps_per_voxel has to be at least 1");
+        }
+    }
+}
+
 impl ProjectileDef {
 
-}
\ No newline at end of file
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// SYSTEMS
+/// Reloads & validates the defs when their files change, edits with errors are logged & the old defs kept
+fn sys_reload_changed_defs(
+    mut defs: ResMut<Defs>,
+    mut watcher: ResMut<DefsWatcher>,
+    mut defs_reloaded_events: EventWriter<DefsReloaded>,
+    time: Res<Time>,
+) {
+    if !watcher.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    if !watcher.poll(&defs) {
+        return;
+    }
+
+    let mut report = DefsReport::default();
+    let reloaded = Defs::load(&mut report);
+    report.log();
+    if report.has_errors() {
+        warn!("Kept the old defs, {} errors after editing {}", report.error_count(), watcher.changed.join(", "));
+        return;
+    }
+
+    let changed = std::mem::take(&mut watcher.changed);
+    info!("Reloaded defs: {}", changed.join(", "));
+    *defs = reloaded;
+    defs_reloaded_events.send(DefsReloaded { changed });
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions_of(
+        contents: &str,
+    ) -> Vec<Option<(usize, usize)>> {
+        let mut levels = DefType::<LevelDef>::new("levels");
+        levels.defs = ron::from_str(contents).unwrap();
+        levels.find_positions(contents);
+        levels.positions
+    }
+
+    #[test]
+    fn positions_come_from_the_parser_not_the_names() {
+        let contents = r#"// (name: "cave") is commented out
+[
+    (
+        name: "cave",
+    ),
+    /* (name: "tower"), */ (name : "tower"), (name: r"keep"),
+    (
+        // name: "ruin"
+        name: "ruin"
+    )
+]"#;
+
+        assert_eq!(positions_of(contents), vec![Some((3, 5)), Some((6, 28)), Some((6, 46)), Some((7, 5))]);
+    }
+
+    #[test]
+    fn positions_allow_trailing_commas_and_empty_lists() {
+        assert_eq!(positions_of("[(name: \"cave\"),]"), vec![Some((1, 2))]);
+        assert!(positions_of("[]").is_empty());
+    }
+
+    #[test]
+    fn missing_ids_are_none() {
+        let levels = DefType::<LevelDef>::new("levels");
+        assert!(levels.try_get(0).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "levels defs have no id 3!")]
+    fn getting_missing_ids_panics() {
+        let mut levels = DefType::<LevelDef>::new("levels");
+        levels.defs = ron::from_str("[(name: \"cave\")]").unwrap();
+        assert_eq!(levels.get(0).name(), "cave");
+        levels.get_u8(3);
+    }
+}
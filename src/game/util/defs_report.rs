@@ -0,0 +1,149 @@
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// USE
+use crate::*;
+use std::fmt;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// CONSTANTS
+/// Command line flag that lints `assets/defs` & exits instead of starting the game, see [check_defs]
+pub const DEFS_CHECK_ARG: &str = "--check-defs";
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// ENUMS
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DefSeverity {
+    /// Loads, but probably isn't what was meant
+    Warning,
+    /// Defs can't be used as they are
+    Error,
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// STRUCTS
+//================================-================================-================================
+// DefLocation
+/// Where in `assets/defs` an issue was found, as precise as the check allows
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DefLocation {
+    pub file: String,
+    /// Line & column, both from 1
+    pub position: Option<(usize, usize)>,
+    pub def: Option<String>,
+}
+
+impl DefLocation {
+    pub fn file(
+        file: &str,
+    ) -> Self {
+        Self {
+            file: file.into(),
+            position: None,
+            def: None,
+        }
+    }
+
+    pub fn with_position(mut self, line: usize, column: usize) -> Self { self.position = Some((line, column)); self }
+    pub fn with_def(mut self, def: &str) -> Self { self.def = Some(def.into()); self }
+}
+
+impl fmt::Display for DefLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some((line, column)) = self.position {
+            write!(f, ":{}:{}", line, column)?;
+        }
+
+        if let Some(def) = &self.def {
+            write!(f, " ({})", def)?;
+        }
+
+        Ok(())
+    }
+}
+
+//================================-================================-================================
+// DefIssue
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DefIssue {
+    pub severity: DefSeverity,
+    pub location: DefLocation,
+    pub message: String,
+}
+
+impl fmt::Display for DefIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+                DefSeverity::Warning => "warning",
+                DefSeverity::Error => "error",
+            };
+
+        write!(f, "{}: {}: {}", severity, self.location, self.message)
+    }
+}
+
+//================================-================================-================================
+// DefsReport
+/// Everything wrong with the defs found while loading & validating them, see [Defs::load]
+#[derive(Default)]
+pub struct DefsReport {
+    issues: Vec<DefIssue>,
+}
+
+impl DefsReport {
+    pub fn issues(&self) -> &Vec<DefIssue> { &self.issues }
+    pub fn error_count(&self) -> usize { self.count(DefSeverity::Error) }
+    pub fn warning_count(&self) -> usize { self.count(DefSeverity::Warning) }
+    pub fn has_errors(&self) -> bool { self.error_count() > 0 }
+
+    fn count(
+        &self,
+        severity: DefSeverity,
+    ) -> usize {
+        self.issues.iter().filter(|issue| issue.severity == severity).count()
+    }
+
+    pub fn error(
+        &mut self,
+        location: &DefLocation,
+        message: impl Into<String>,
+    ) {
+        self.issues.push(DefIssue { severity: DefSeverity::Error, location: location.clone(), message: message.into() });
+    }
+
+    pub fn warning(
+        &mut self,
+        location: &DefLocation,
+        message: impl Into<String>,
+    ) {
+        self.issues.push(DefIssue { severity: DefSeverity::Warning, location: location.clone(), message: message.into() });
+    }
+
+    /// Every issue through the game log
+    pub fn log(
+        &self,
+    ) {
+        for issue in self.issues.iter() {
+            match issue.severity {
+                DefSeverity::Warning => warn!("{}", issue),
+                DefSeverity::Error => error!("{}", issue),
+            }
+        }
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// FUNCTIONS
+/// Loads & validates the defs without starting the game, printing every issue to stderr
+///
+/// Returns the process exit code, 1 if there were any errors
+pub fn check_defs() -> i32 {
+    let mut report = DefsReport::default();
+    Defs::load(&mut report);
+
+    for issue in report.issues().iter() {
+        eprintln!("{}", issue);
+    }
+
+    eprintln!("{} errors, {} warnings", report.error_count(), report.warning_count());
+    if report.has_errors() { 1 } else { 0 }
+}
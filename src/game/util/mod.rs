@@ -18,6 +18,8 @@ mod data;
 pub use data::*;
 mod defs;
 pub use defs::*;
+mod defs_report;
+pub use defs_report::*;
 mod path;
 pub use path::*;
 mod random;
@@ -20,7 +20,7 @@ impl AStarNode {
         unit: &Unit,
         successors: &mut Vec<(IVec3, u32)>,
         voxels: &mut Accessor<Voxel>,
-        entities: &mut Accessor<Option<Entity>>,
+        entities: &mut EntityAccessor,
         state_query: &Query<&UnitState>,
         defs: &Res<Defs>,
     ) {
@@ -28,7 +28,7 @@ impl AStarNode {
         for (side_face, side_voxel) in side_neighbors.iter().enumerate() {
             let side_coord = *node + VOXEL_SIDE_FACE_CHECKS[side_face];
             if side_voxel.is_walkable(&side_coord, voxels, defs) && entities.is_empty(&side_coord) {
-                successors.push((side_coord, 1));
+                successors.push((side_coord, 1 + side_voxel.move_cost(defs)));
             }
         }
     }
@@ -39,17 +39,18 @@ impl AStarNode {
         max_jump: u8,
         successors: &mut Vec<(IVec3, u32)>,
         voxels: &mut Accessor<Voxel>,
-        entities: &mut Accessor<Option<Entity>>,
+        entities: &mut EntityAccessor,
         state_query: &Query<&UnitState>,
         defs: &Res<Defs>,
     ) {
         for jump in 0..max_jump {
             let jump_coord = *node + (IVec3::Y + IVec3::Y * jump as i32);
 
-            if voxels.get_value(&jump_coord).is_blocked(defs) { break; }
+            let jump_voxel = voxels.get_value(&jump_coord);
+            if jump_voxel.is_blocked(defs) { break; }
             if !entities.is_empty(&jump_coord) { break; }
 
-            successors.push((jump_coord, 2));
+            successors.push((jump_coord, 2 + jump_voxel.move_cost(defs)));
             AStarNode::append_walk_successors(&jump_coord, unit, successors, voxels, entities, state_query, defs);
         }
     }
@@ -60,7 +61,7 @@ impl AStarNode {
         max_fall: u8,
         successors: &mut Vec<(IVec3, u32)>,
         voxels: &mut Accessor<Voxel>,
-        entities: &mut Accessor<Option<Entity>>,
+        entities: &mut EntityAccessor,
         state_query: &Query<&UnitState>,
         defs: &Res<Defs>,
     ) {
@@ -68,12 +69,13 @@ impl AStarNode {
         for (side_face, side_neighbor) in side_neighbors.iter().enumerate() {
             let side_coord = *node + VOXEL_SIDE_FACE_CHECKS[side_face];
             if !side_neighbor.is_blocked(defs) && entities.is_empty(&side_coord) {
-                successors.push((side_coord, 1));
+                successors.push((side_coord, 1 + side_neighbor.move_cost(defs)));
 
                 for fall in 0..max_fall {
                     let fall_coord = side_coord - (IVec3::Y + IVec3::Y * fall as i32);
-                    if !voxels.get_value(&fall_coord).is_blocked(defs) && entities.is_empty(&fall_coord) {
-                        successors.push((fall_coord, 2));
+                    let fall_voxel = voxels.get_value(&fall_coord);
+                    if !fall_voxel.is_blocked(defs) && entities.is_empty(&fall_coord) {
+                        successors.push((fall_coord, 2 + fall_voxel.move_cost(defs)));
                     }
                 }
             }
@@ -86,7 +88,7 @@ impl AStarNode {
         max_jump: u8,
         max_fall: u8,
         voxels: &mut Accessor<Voxel>,
-        entities: &mut Accessor<Option<Entity>>,
+        entities: &mut EntityAccessor,
         state_query: &Query<&UnitState>,
         defs: &Res<Defs>,
     ) -> Vec<(IVec3, u32)> {
@@ -115,7 +117,7 @@ impl AStar {
         max_fall: u8,
         target: &IVec3,
         voxels: &mut Accessor<Voxel>,
-        entities: &mut Accessor<Option<Entity>>,
+        entities: &mut EntityAccessor,
         state_query: &Query<&UnitState>,
         defs: &Res<Defs>,
     ) -> Option<(Vec<IVec3>, u32)> {
@@ -19,7 +19,7 @@ impl Plugin for RandomPlugin {
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // RESOURCES
-#[derive(Resource)]
+#[derive(Resource, Clone)]
 pub struct Random {
     seed: String,
 }
@@ -46,4 +46,20 @@ impl Random {
     ) -> Pcg64 {
         Seeder::from(&self.seed).make_rng()
     }
+
+    /// The same rng for `coord` every time, for generating parts of the world in any order
+    pub fn get_rng_at(
+        &self,
+        coord: &IVec3,
+    ) -> Pcg64 {
+        Seeder::from(format!("{}{}", self.seed, coord)).make_rng()
+    }
+
+    /// The same rng for `name` every time, so each simulation draws from a stream of its own
+    pub fn get_rng_for(
+        &self,
+        name: &str,
+    ) -> Pcg64 {
+        Seeder::from(format!("{}{}", self.seed, name)).make_rng()
+    }
 }
\ No newline at end of file
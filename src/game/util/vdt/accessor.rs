@@ -2,61 +2,76 @@
 // USE
 use crate::*;
 use super::*;
+use bevy::utils::HashSet;
+use std::sync::Mutex;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// ENUMS
+/// How writes through an [Accessor] mark meshes for rebuilding
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum RemeshMode {
+    /// For trees that are never meshed
+    Off,
+    /// Every write marks its leaf & the neighbouring leaves it borders
+    #[default]
+    Immediate,
+    /// Leaves are collected and only marked on [Accessor::flush_remesh], for many writes at once
+    Batched,
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// TRAITS
+/// Records that live & die with the values of another tree, see [Accessor::with_link]
+pub trait LinkedTable<ValueType>: Send + Sync {
+    /// True if a write from `old` to `new` drops the record
+    fn clears(&self, old: &ValueType, new: &ValueType) -> bool;
+    /// Drops every record in the `dim` sized block around `coord`
+    fn clear_block(&self, coord: &IVec3, dim: usize);
+    /// Moves the record at `from` over whatever is at `to`
+    fn move_record(&self, from: &IVec3, to: &IVec3);
+}
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // STRUCTS
-pub struct Accessor<ValueType: Clone + Copy + PartialEq + Eq> {
-    root_node: Arc<RwLock<RootNode<ValueType>>>,
+/// One write recorded in an [Accessor] journal, if `dim` is above 1 the whole block at `coord` went from `old` to `new`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueChange<ValueType> {
+    pub coord: IVec3,
+    pub dim: usize,
+    pub old: ValueType,
+    pub new: ValueType,
+}
+
+pub type ValueJournal<ValueType> = Arc<Mutex<Vec<ValueChange<ValueType>>>>;
+
+/// Side table of records kept beside a tree, written through an [Accessor] of that tree
+pub type ValueLink<ValueType> = Arc<dyn LinkedTable<ValueType>>;
+
+pub struct Accessor<ValueType: Clone + Copy + PartialEq + Eq, const L: usize = { LEAF_LOG2DIM }, const B: usize = { BRANCH_LOG2DIM }, const T: usize = { TRUNK_LOG2DIM }> {
+    root_node: Arc<RwLock<RootNode<ValueType, L, B, T>>>,
     background: ValueType,
+    /// Shared with the root, the cached nodes below are only valid while it matches `cache_generation`
+    generation: Arc<AtomicU64>,
+    cache_generation: u64,
 
     trunk_key: IVec3,
-    trunk_node: Option<Arc<RwLock<TrunkNode<ValueType>>>>,
+    trunk_node: Option<Arc<RwLock<TrunkNode<ValueType, L, B, T>>>>,
     branch_key: IVec3,
-    branch_node: Option<Arc<RwLock<BranchNode<ValueType>>>>,
+    branch_node: Option<Arc<RwLock<BranchNode<ValueType, L, B, T>>>>,
     leaf_key: IVec3,
-    leaf_node: Option<Arc<RwLock<LeafNode<ValueType>>>>,
-}
+    leaf_node: Option<Arc<RwLock<LeafNode<ValueType, L, B, T>>>>,
 
-impl Accessor<Voxel> {
-    pub fn set_remesh(
-        &mut self,
-        coord: &IVec3,
-    ) {
-        self.eval_leaf_cache(coord);
-        
-        let (branch_node, trunk_node) = self.root_node.write().unwrap().set_redraw_and_cache(coord);
-        self.insert_branch(coord, &branch_node);
-        self.insert_trunk(coord, &trunk_node);
-    }
+    auto_prune: bool,
+    prune_report: PruneReport,
 
-    pub fn set_voxel_off(
-        &mut self,
-        coord: &IVec3,
-    ) {
-        if let Some(leaf_node) = self.eval_leaf_cache(coord) {
-            leaf_node.write().unwrap().set_value_at_coord(coord, &self.background, false);
-        } else if let Some(branch_node) = self.eval_branch_cache(coord) {
-            let leaf_node = branch_node.write().unwrap().set_value_and_cache(coord, &self.background, false);
-            self.insert_leaf(coord, &leaf_node);
-        } else if let Some(trunk_node) = self.eval_trunk_cache(coord) {
-            let (leaf_node, branch_node) = trunk_node.write().unwrap().set_value_and_cache(coord, &self.background, false);
-            self.insert_leaf(coord, &leaf_node);
-            self.insert_branch(coord, &branch_node);
-        } else {
-            let (leaf_node, branch_node, trunk_node) = self.root_node.write().unwrap().set_value_and_cache(coord, &self.background, false);
-            self.insert_leaf(coord, &leaf_node);
-            self.insert_branch(coord, &branch_node);
-            self.insert_trunk(coord, &trunk_node);
-        }
+    remesh_mode: RemeshMode,
+    remesh_batch: HashSet<IVec3>,
 
-        let neighbors = self.get_adjacent_values(coord);
-        for face_coord in GRID_DIRECTIONS.iter().map(|face_coord| *face_coord + *coord) {
-            self.set_remesh(&face_coord);
-        }
-    }
+    journals: Vec<ValueJournal<ValueType>>,
+    links: Vec<ValueLink<ValueType>>,
 }
 
-impl Accessor<Option<Entity>> {
+impl<const L: usize, const B: usize, const T: usize> Accessor<Option<Entity>, L, B, T> {
     pub fn is_empty(
         &mut self,
         coord: &IVec3,
@@ -65,15 +80,17 @@ impl Accessor<Option<Entity>> {
     }
 }
 
-impl<ValueType: Clone + Copy + PartialEq + Eq> Accessor<ValueType> {
+impl<ValueType: Clone + Copy + PartialEq + Eq, const L: usize, const B: usize, const T: usize> Accessor<ValueType, L, B, T> {
     pub fn new(
-        root_node: &Arc<RwLock<RootNode<ValueType>>>,
+        root_node: &Arc<RwLock<RootNode<ValueType, L, B, T>>>,
     ) -> Self {
         let root_read_lock = root_node.read().unwrap();
 
         Self {
             root_node: root_node.clone(),
             background: root_read_lock.background,
+            generation: root_read_lock.generation.clone(),
+            cache_generation: root_read_lock.generation(),
 
             trunk_key: IVec3::new(i32::MAX, i32::MAX, i32::MAX),
             trunk_node: None,
@@ -81,9 +98,58 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> Accessor<ValueType> {
             branch_node: None,
             leaf_key: IVec3::new(i32::MAX, i32::MAX, i32::MAX),
             leaf_node: None,
+
+            auto_prune: false,
+            prune_report: PruneReport::default(),
+
+            remesh_mode: RemeshMode::default(),
+            remesh_batch: HashSet::default(),
+
+            journals: vec![],
+            links: vec![],
         }
     }
 
+    /// After every write, fold the written leaf back into a tile if it has become uniform
+    /// 
+    /// Other accessors into the same tree drop their cached nodes on their next access, see [RootNode::generation]
+    pub fn with_auto_prune(
+        mut self,
+    ) -> Self {
+        self.auto_prune = true;
+        self
+    }
+
+    pub fn with_remesh_mode(
+        mut self,
+        remesh_mode: RemeshMode,
+    ) -> Self {
+        self.remesh_mode = remesh_mode;
+        self
+    }
+
+    /// Records every write that changes a value into `journal`, on top of any journals already attached
+    pub fn with_journal(
+        mut self,
+        journal: &ValueJournal<ValueType>,
+    ) -> Self {
+        self.journals.push(journal.clone());
+        self
+    }
+
+    /// Clears records in `link` wherever a write replaces their value, see [Accessor::move_value]
+    pub fn with_link(
+        mut self,
+        link: &ValueLink<ValueType>,
+    ) -> Self {
+        self.links.push(link.clone());
+        self
+    }
+
+    pub fn prune_report(&self) -> &PruneReport { &self.prune_report }
+    pub fn remesh_mode(&self) -> RemeshMode { self.remesh_mode }
+    pub fn background(&self) -> &ValueType { &self.background }
+
 
 
     pub fn adjacent_value_from_index(
@@ -164,12 +230,47 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> Accessor<ValueType> {
         }
     }
 
+    /// Bypasses the cache, blocks bigger than a voxel are never cached anyway
+    pub fn get_block_value(
+        &self,
+        coord: &IVec3,
+        dim: usize,
+    ) -> Option<ValueType> {
+        if dim <= 1 {
+            if let Some(leaf_node) = &self.leaf_node {
+                if *coord & TreeDims::<L, B, T>::LEAF_ORIGIN_MASK == self.leaf_key {
+                    return Some(leaf_node.read().unwrap().get_value_at_coord(coord));
+                }
+            }
+        }
+
+        self.root_node.read().unwrap().get_block_value(coord, dim)
+    }
+
+    /// Like `get_value`, but also returns the dim of the uniform block around `coord`, 1 inside a leaf
+    pub fn get_value_and_extent(
+        &mut self,
+        coord: &IVec3,
+    ) -> (ValueType, usize) {
+        if self.eval_leaf_cache(coord).is_some() {
+            return (self.get_value(coord), 1);
+        }
+
+        let (value, dim) = self.root_node.read().unwrap().get_value_and_extent(coord);
+        if dim == 1 {
+            return (self.get_value(coord), 1);
+        }
+
+        (value, dim)
+    }
+
     pub fn set_value(
         &mut self,
         coord: &IVec3,
         value: &ValueType,
         active: bool,
     ) {
+        let old = self.get_journal_value(coord);
         if let Some(leaf_node) = self.eval_leaf_cache(coord) {
             leaf_node.write().unwrap().set_value_at_coord(coord, value, active);
         } else if let Some(branch_node) = self.eval_branch_cache(coord) {
@@ -179,12 +280,52 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> Accessor<ValueType> {
             let (leaf_node, branch_node) = trunk_node.write().unwrap().set_value_and_cache(coord, value, active);
             self.insert_leaf(coord, &leaf_node);
             self.insert_branch(coord, &branch_node);
+        } else if self.try_defer(PendingWriteKind::Value { coord: *coord, value: *value, active }) {
+            return;
         } else {
             let (leaf_node, branch_node, trunk_node) = self.root_node.write().unwrap().set_value_and_cache(coord, value, active);
             self.insert_leaf(coord, &leaf_node);
             self.insert_branch(coord, &branch_node);
             self.insert_trunk(coord, &trunk_node);
         }
+
+        self.record_change(coord, 1, old, value);
+        self.try_auto_prune(coord);
+        self.mark_remesh_around(coord);
+        self.mark_remesh_diagonals(coord);
+    }
+
+    /// Writes the `dim` sized block around `coord` as a single tile, dropping whatever nodes were below it
+    /// 
+    /// `None` if a root tile can't hold `active`, otherwise the meshes of dropped trunks & branches, these must be despawned
+    /// 
+    /// Mixed blocks are journaled as each of their active regions, then the whole block from the background,
+    /// so applying the changes in reverse restores them exactly
+    pub fn set_tile(
+        &mut self,
+        coord: &IVec3,
+        dim: usize,
+        value: &ValueType,
+        active: bool,
+    ) -> Option<Vec<Entity>> {
+        if dim >= TreeDims::<L, B, T>::TRUNK_DIM && active != (*value != self.background) {
+            return None;
+        }
+
+        if self.try_defer(PendingWriteKind::Tile { coord: *coord, dim, value: *value, active }) {
+            return Some(vec![]);
+        }
+
+        if !self.journals.is_empty() || !self.links.is_empty() {
+            let changes = tile_changes(&self.root_node.read().unwrap(), coord, dim, value);
+            for change in changes.iter() {
+                record_value_change(&self.journals, &self.links, change);
+            }
+        }
+
+        let mesh_entities = self.root_node.write().unwrap().set_tile(coord, dim, value, active);
+        self.clear_cache();
+        Some(mesh_entities)
     }
 
     pub fn set_value_on(
@@ -192,6 +333,7 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> Accessor<ValueType> {
         coord: &IVec3,
         value: &ValueType,
     ) {
+        let old = self.get_journal_value(coord);
         if let Some(leaf_node) = self.eval_leaf_cache(coord) {
             leaf_node.write().unwrap().set_value_at_coord(coord, value, true);
         } else if let Some(branch_node) = self.eval_branch_cache(coord) {
@@ -201,19 +343,42 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> Accessor<ValueType> {
             let (leaf_node, branch_node) = trunk_node.write().unwrap().set_value_and_cache(coord, value, true);
             self.insert_leaf(coord, &leaf_node);
             self.insert_branch(coord, &branch_node);
+        } else if self.try_defer(PendingWriteKind::Value { coord: *coord, value: *value, active: true }) {
+            return;
         } else {
             let (leaf_node, branch_node, trunk_node) = self.root_node.write().unwrap().set_value_and_cache(coord, value, true);
             self.insert_leaf(coord, &leaf_node);
             self.insert_branch(coord, &branch_node);
             self.insert_trunk(coord, &trunk_node);
         }
+
+        self.record_change(coord, 1, old, value);
+        self.try_auto_prune(coord);
+        self.mark_remesh_around(coord);
+        self.mark_remesh_diagonals(coord);
+    }
+
+    /// Moves the value at `from` to `to` along with its linked records, `from` is left as background
+    pub fn move_value(
+        &mut self,
+        from: &IVec3,
+        to: &IVec3,
+    ) {
+        let value = self.get_value(from);
+        let active = value != self.background;
+        self.set_value(to, &value, active);
+        for link in self.links.iter() {
+            link.move_record(from, to);
+        }
+
+        self.set_value_off(from);
     }
 
-    /// If `ValueType` is [Voxel], use `set_voxel_off` instead
     pub fn set_value_off(
         &mut self,
         coord: &IVec3,
     ) {
+        let old = self.get_journal_value(coord);
         if let Some(leaf_node) = self.eval_leaf_cache(coord) {
             leaf_node.write().unwrap().set_value_at_coord(coord, &self.background, false);
         } else if let Some(branch_node) = self.eval_branch_cache(coord) {
@@ -223,21 +388,184 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> Accessor<ValueType> {
             let (leaf_node, branch_node) = trunk_node.write().unwrap().set_value_and_cache(coord, &self.background, false);
             self.insert_leaf(coord, &leaf_node);
             self.insert_branch(coord, &branch_node);
+        } else if self.try_defer(PendingWriteKind::Value { coord: *coord, value: self.background, active: false }) {
+            return;
         } else {
             let (leaf_node, branch_node, trunk_node) = self.root_node.write().unwrap().set_value_and_cache(coord, &self.background, false);
             self.insert_leaf(coord, &leaf_node);
             self.insert_branch(coord, &branch_node);
             self.insert_trunk(coord, &trunk_node);
         }
+
+        let background = self.background;
+        self.record_change(coord, 1, old, &background);
+        self.try_auto_prune(coord);
+        self.mark_remesh_around(coord);
+        self.mark_remesh_diagonals(coord);
+    }
+
+
+
+    /// Leaving [RemeshMode::Batched] flushes whatever was collected
+    pub fn set_remesh_mode(
+        &mut self,
+        remesh_mode: RemeshMode,
+    ) {
+        self.remesh_mode = remesh_mode;
+        self.flush_remesh();
+    }
+
+    /// Marks every leaf collected while batched for redraw
+    pub fn flush_remesh(
+        &mut self,
+    ) {
+        let remesh_batch: Vec<IVec3> = self.remesh_batch.drain().collect();
+        for leaf_key in remesh_batch.iter() {
+            self.set_remesh(leaf_key);
+        }
+    }
+
+    /// Marks the leaf or tile around `coord` for redraw, ignores the remesh mode
+    pub fn set_remesh(
+        &mut self,
+        coord: &IVec3,
+    ) {
+        self.eval_leaf_cache(coord);
+        
+        let (branch_node, trunk_node) = self.root_node.write().unwrap().set_redraw_and_cache(coord);
+        self.insert_branch(coord, &branch_node);
+        self.insert_trunk(coord, &trunk_node);
+    }
+
+    /// Marks the leaf or tile around `coord` following the remesh mode
+    pub fn mark_remesh(
+        &mut self,
+        coord: &IVec3,
+    ) {
+        match self.remesh_mode {
+            RemeshMode::Off => {},
+            RemeshMode::Immediate => self.set_remesh(coord),
+            RemeshMode::Batched => { self.remesh_batch.insert(*coord & TreeDims::<L, B, T>::LEAF_ORIGIN_MASK); },
+        }
+    }
+
+    /// Marks the leaf around `coord` & the neighbouring leaves whose faces border it
+    pub fn mark_remesh_around(
+        &mut self,
+        coord: &IVec3,
+    ) {
+        if self.remesh_mode == RemeshMode::Off { return; }
+
+        self.mark_remesh(coord);
+
+        let local_coord = *coord & (TreeDims::<L, B, T>::LEAF_DIM as i32 - 1);
+        for direction in GRID_DIRECTIONS.iter() {
+            let local_neighbour = local_coord + *direction;
+            if local_neighbour.cmplt(IVec3::ZERO).any() || local_neighbour.cmpge(IVec3::splat(TreeDims::<L, B, T>::LEAF_DIM as i32)).any() {
+                self.mark_remesh(&(*coord + *direction));
+            }
+        }
+    }
+
+    /// Marks the leaves only touching `coord` along an edge or corner
+    ///
+    /// Baked ambient occlusion of a face samples its edge & corner neighbours, see [MeshVoxel::bake_ambient_occlusion],
+    /// so a write shades faces in leaves diagonal to it
+    pub fn mark_remesh_diagonals(
+        &mut self,
+        coord: &IVec3,
+    ) {
+        if self.remesh_mode == RemeshMode::Off { return; }
+
+        let local_coord = *coord & (TreeDims::<L, B, T>::LEAF_DIM as i32 - 1);
+        for x in -1..=1 { for y in -1..=1 { for z in -1..=1 {
+            let direction = IVec3::new(x, y, z);
+            if direction.abs().dot(IVec3::ONE) < 2 {
+                continue;
+            }
+
+            let local_neighbour = local_coord + direction;
+            if local_neighbour.cmplt(IVec3::ZERO).any() || local_neighbour.cmpge(IVec3::splat(TreeDims::<L, B, T>::LEAF_DIM as i32)).any() {
+                self.mark_remesh(&(*coord + direction));
+            }
+        }}}
+    }
+
+
+
+    /// The current value at `coord` if there is a journal or link to pass the write on to
+    fn get_journal_value(
+        &mut self,
+        coord: &IVec3,
+    ) -> Option<ValueType> {
+        if self.journals.is_empty() && self.links.is_empty() { None } else { Some(self.get_value(coord)) }
+    }
+
+    fn record_change(
+        &mut self,
+        coord: &IVec3,
+        dim: usize,
+        old: Option<ValueType>,
+        new: &ValueType,
+    ) {
+        if let Some(old) = old {
+            record_value_change(&self.journals, &self.links, &ValueChange { coord: *coord, dim, old, new: *new });
+        }
+    }
+
+    /// Holds back a write to a streamed trunk that isn't resident, it's recorded in this accessor's journals once replayed
+    fn try_defer(
+        &self,
+        kind: PendingWriteKind<ValueType>,
+    ) -> bool {
+        let write = PendingWrite { kind, journals: self.journals.clone(), links: self.links.clone() };
+        self.root_node.read().unwrap().defer_write(write)
+    }
+
+    fn try_auto_prune(
+        &mut self,
+        coord: &IVec3,
+    ) {
+        if !self.auto_prune || self.eval_leaf_cache(coord).is_none() { return; }
+
+        if let Some(branch_node) = self.eval_branch_cache(coord) {
+            if branch_node.write().unwrap().try_prune_child_at_coord(coord, &mut self.prune_report) {
+                self.generation.fetch_add(1, Ordering::AcqRel);
+                self.insert_leaf(coord, &None);
+            }
+        }
     }
 
 
 
+    /// Forget every cached node, needed after nodes have been dropped from the tree
+    pub fn clear_cache(
+        &mut self,
+    ) {
+        self.cache_generation = self.generation.load(Ordering::Acquire);
+        self.trunk_key = IVec3::new(i32::MAX, i32::MAX, i32::MAX);
+        self.trunk_node = None;
+        self.branch_key = IVec3::new(i32::MAX, i32::MAX, i32::MAX);
+        self.branch_node = None;
+        self.leaf_key = IVec3::new(i32::MAX, i32::MAX, i32::MAX);
+        self.leaf_node = None;
+    }
+
+    /// Forget every cached node if any may have been dropped since they were cached
+    fn check_generation(
+        &mut self,
+    ) {
+        if self.generation.load(Ordering::Acquire) != self.cache_generation {
+            self.clear_cache();
+        }
+    }
+
     pub fn eval_trunk_cache(
         &mut self,
         coord: &IVec3,
-    ) -> Option<Arc<RwLock<TrunkNode<ValueType>>>> {
-        let coord_trunk_key = *coord & TRUNK_ORIGIN_MASK;
+    ) -> Option<Arc<RwLock<TrunkNode<ValueType, L, B, T>>>> {
+        self.check_generation();
+        let coord_trunk_key = *coord & TreeDims::<L, B, T>::TRUNK_ORIGIN_MASK;
         if coord_trunk_key == self.trunk_key {
             self.trunk_node.clone()
         } else {
@@ -249,8 +577,9 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> Accessor<ValueType> {
     pub fn eval_branch_cache(
         &mut self,
         coord: &IVec3,
-    ) -> Option<Arc<RwLock<BranchNode<ValueType>>>> {
-        let coord_branch_key = *coord & BRANCH_ORIGIN_MASK;
+    ) -> Option<Arc<RwLock<BranchNode<ValueType, L, B, T>>>> {
+        self.check_generation();
+        let coord_branch_key = *coord & TreeDims::<L, B, T>::BRANCH_ORIGIN_MASK;
         if coord_branch_key == self.branch_key {
             self.branch_node.clone()
         } else {
@@ -262,8 +591,9 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> Accessor<ValueType> {
     pub fn eval_leaf_cache(
         &mut self,
         coord: &IVec3,
-    ) -> Option<Arc<RwLock<LeafNode<ValueType>>>> {
-        let coord_leaf_key = *coord & LEAF_ORIGIN_MASK;
+    ) -> Option<Arc<RwLock<LeafNode<ValueType, L, B, T>>>> {
+        self.check_generation();
+        let coord_leaf_key = *coord & TreeDims::<L, B, T>::LEAF_ORIGIN_MASK;
         if coord_leaf_key == self.leaf_key {
             self.leaf_node.clone()
         } else {
@@ -277,27 +607,112 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> Accessor<ValueType> {
     pub fn insert_trunk(
         &mut self,
         coord: &IVec3,
-        trunk: &Option<Arc<RwLock<TrunkNode<ValueType>>>>,
+        trunk: &Option<Arc<RwLock<TrunkNode<ValueType, L, B, T>>>>,
     ) {
-        self.trunk_key = *coord & TRUNK_ORIGIN_MASK;
+        self.trunk_key = *coord & TreeDims::<L, B, T>::TRUNK_ORIGIN_MASK;
         self.trunk_node = trunk.clone();
     }
 
     pub fn insert_branch(
         &mut self,
         coord: &IVec3,
-        branch: &Option<Arc<RwLock<BranchNode<ValueType>>>>,
+        branch: &Option<Arc<RwLock<BranchNode<ValueType, L, B, T>>>>,
     ) {
-        self.branch_key = *coord & BRANCH_ORIGIN_MASK;
+        self.branch_key = *coord & TreeDims::<L, B, T>::BRANCH_ORIGIN_MASK;
         self.branch_node = branch.clone();
     }
 
     pub fn insert_leaf(
         &mut self,
         coord: &IVec3,
-        leaf: &Option<Arc<RwLock<LeafNode<ValueType>>>>,
+        leaf: &Option<Arc<RwLock<LeafNode<ValueType, L, B, T>>>>,
     ) {
-        self.leaf_key = *coord & LEAF_ORIGIN_MASK;
+        self.leaf_key = *coord & TreeDims::<L, B, T>::LEAF_ORIGIN_MASK;
         self.leaf_node = leaf.clone();
     }
-}
\ No newline at end of file
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// FUNCTIONS
+/// Passes a change on to journals & links, unless nothing changed
+pub(super) fn record_value_change<ValueType: Clone + Copy + PartialEq + Eq>(
+    journals: &[ValueJournal<ValueType>],
+    links: &[ValueLink<ValueType>],
+    change: &ValueChange<ValueType>,
+) {
+    if change.old == change.new {
+        return;
+    }
+
+    for journal in journals.iter() {
+        journal.lock().unwrap().push(*change);
+    }
+
+    for link in links.iter() {
+        if link.clears(&change.old, &change.new) {
+            link.clear_block(&change.coord, change.dim);
+        }
+    }
+}
+
+/// What writing the `dim` sized block around `coord` as a tile of `value` changes, in the order [Accessor::set_tile] journals them
+pub(super) fn tile_changes<ValueType: Clone + Copy + PartialEq + Eq, const L: usize, const B: usize, const T: usize>(
+    root_node: &RootNode<ValueType, L, B, T>,
+    coord: &IVec3,
+    dim: usize,
+    value: &ValueType,
+) -> Vec<ValueChange<ValueType>> {
+    let block_origin = *coord & !(dim as i32 - 1);
+    let (old, extent) = root_node.get_value_and_extent(&block_origin);
+    if extent >= dim {
+        return vec![ValueChange { coord: block_origin, dim, old, new: *value }];
+    }
+
+    let block_max = block_origin + IVec3::splat(dim as i32 - 1);
+    let mut changes: Vec<ValueChange<ValueType>> = root_node.iter_active_regions().with_bounds(&block_origin, &block_max)
+        // Bounds are block aligned, so every region is a whole tile or voxel
+        .map(|region| ValueChange { coord: region.min, dim: (region.max.x - region.min.x + 1) as usize, old: region.value, new: *value })
+        .collect();
+
+    changes.push(ValueChange { coord: block_origin, dim, old: root_node.background, new: *value });
+    changes
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestRoot = RootNode<u8, 2, 2, 2>;
+
+    #[test]
+    fn pruning_drops_the_nodes_other_accessors_cached() {
+        let root_node = Arc::new(RwLock::new(TestRoot::new(0)));
+        let mut pruning = Accessor::new(&root_node).with_remesh_mode(RemeshMode::Off).with_auto_prune();
+        let mut other = Accessor::new(&root_node).with_remesh_mode(RemeshMode::Off);
+
+        // Both cache the leaf at the origin, then the pruning writer folds it back into a tile
+        pruning.set_value_on(&IVec3::ZERO, &1);
+        assert_eq!(other.get_value(&IVec3::ZERO), 1);
+        pruning.set_value_off(&IVec3::ZERO);
+        assert_eq!(pruning.prune_report().leaves, 1);
+
+        // A write through the stale leaf would never reach the tree
+        other.set_value_on(&IVec3::ONE, &2);
+        assert_eq!(Accessor::new(&root_node).get_value(&IVec3::ONE), 2);
+    }
+
+    #[test]
+    fn tree_prunes_drop_cached_nodes() {
+        let root_node = Arc::new(RwLock::new(TestRoot::new(0)));
+        let mut values = Accessor::new(&root_node).with_remesh_mode(RemeshMode::Off);
+        values.set_value_on(&IVec3::ZERO, &1);
+        values.set_value_off(&IVec3::ZERO);
+
+        assert_eq!(root_node.write().unwrap().prune().trunks, 1);
+        values.set_value_on(&IVec3::ONE, &2);
+        assert_eq!(Accessor::new(&root_node).get_value(&IVec3::ONE), 2);
+    }
+}
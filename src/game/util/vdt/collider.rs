@@ -0,0 +1,67 @@
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// USE
+use crate::*;
+use super::*;
+use block_mesh::ndshape::ConstShape;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// FUNCTIONS
+/// Solid voxels of a leaf merged into as few boxes as a greedy sweep finds, see [MeshVoxel::solid]
+///
+/// Built from voxel data alone, so it doesn't follow the render mesh. Boxes grow along x, then z, then y.
+/// `voxels` is laid out as `PaddedLeafShape`, the padding is ignored
+pub fn build_leaf_collider(
+    voxels: &[MeshVoxel],
+) -> Option<Collider> {
+    let dim = LEAF_DIM as i32;
+    let padded_index = |coord: IVec3| PaddedLeafShape::linearize((coord + IVec3::ONE).as_uvec3().to_array()) as usize;
+    let mut taken = vec![false; voxels.len()];
+    let is_free = |taken: &[bool], coord: IVec3| !taken[padded_index(coord)] && voxels[padded_index(coord)].solid;
+
+    let mut shapes: Vec<(Vec3, Quat, Collider)> = vec![];
+    for y in 0..dim { for z in 0..dim { for x in 0..dim {
+        let min = IVec3::new(x, y, z);
+        if !is_free(&taken, min) {
+            continue;
+        }
+
+        let mut max = min;
+        while max.x + 1 < dim && is_free(&taken, IVec3::new(max.x + 1, y, z)) {
+            max.x += 1;
+        }
+
+        while max.z + 1 < dim && (min.x..=max.x).all(|x| is_free(&taken, IVec3::new(x, y, max.z + 1))) {
+            max.z += 1;
+        }
+
+        while max.y + 1 < dim && (min.x..=max.x).all(|x| (min.z..=max.z).all(|z| is_free(&taken, IVec3::new(x, max.y + 1, z)))) {
+            max.y += 1;
+        }
+
+        for y in min.y..=max.y { for z in min.z..=max.z { for x in min.x..=max.x {
+            taken[padded_index(IVec3::new(x, y, z))] = true;
+        }}}
+
+        // Voxel centers sit on integer coords
+        let half_extents = (max - min + IVec3::ONE).as_vec3() * CUBE_HALF_DIM;
+        shapes.push((
+            (min + max).as_vec3() * 0.5,
+            Quat::IDENTITY,
+            Collider::cuboid(half_extents.x, half_extents.y, half_extents.z),
+        ));
+    }}}
+
+    if shapes.is_empty() { None } else { Some(Collider::compound(shapes)) }
+}
+
+/// One box filling a solid tile of `dim` voxels across
+pub fn build_tile_collider(
+    dim: usize,
+) -> Collider {
+    let half_extent = dim as f32 * CUBE_HALF_DIM;
+    Collider::compound(vec![(
+        Vec3::splat(half_extent - CUBE_HALF_DIM),
+        Quat::IDENTITY,
+        Collider::cuboid(half_extent, half_extent, half_extent),
+    )])
+}
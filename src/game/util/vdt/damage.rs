@@ -0,0 +1,50 @@
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// USE
+use crate::*;
+use super::*;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// ENUMS
+/// What [VoxelTree::damage_voxel] did to a voxel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoxelDamage {
+    /// The matter can't be broken, see [MatterDef::durability]
+    Immune,
+    /// Damage dealt so far out of the matter's durability
+    Damaged { damage: u16, durability: u16 },
+    /// The voxel was removed, this is what it held
+    Destroyed(Voxel),
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// COMPONENTS
+//================================-================================-================================
+// VoxelTree
+impl VoxelTree {
+    /// Adds `amount` to the damage kept in the voxel's [VoxelMeta], the voxel is removed once that reaches its durability
+    pub fn damage_voxel(
+        &self,
+        coord: &IVec3,
+        amount: u16,
+        defs: &Res<Defs>,
+    ) -> VoxelDamage {
+        let mut voxels = self.get_accessor();
+        let voxel = voxels.get_value(coord);
+        let durability = defs.matter.get_u8(voxel.matter_id()).durability();
+        if durability == 0 {
+            return VoxelDamage::Immune;
+        }
+
+        let mut metas = self.get_meta_accessor();
+        let meta = metas.get_value(coord);
+        let damage = meta.damage.saturating_add(amount);
+        if damage >= durability {
+            // The link clears the damage with the voxel
+            voxels.set_value_off(coord);
+            return VoxelDamage::Destroyed(voxel);
+        }
+
+        metas.set_value_on(coord, &VoxelMeta { damage, ..meta });
+        VoxelDamage::Damaged { damage, durability }
+    }
+}
@@ -0,0 +1,99 @@
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// USE
+use crate::*;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// STRUCTS
+/// Sizes, masks & index math of one tree layout, shared by every node in it
+///
+/// `L`, `B` & `T` are the log2 dims of leaves in voxels, branches in leaves & trunks in branches, each at least 2 so masks fill whole words
+pub struct TreeDims<const L: usize, const B: usize, const T: usize>;
+
+impl<const L: usize, const B: usize, const T: usize> TreeDims<L, B, T> {
+    pub const LEAF_TOTAL: usize =     L;
+    pub const LEAF_DIM: usize =       1 << Self::LEAF_TOTAL;
+    pub const LEAF_SIZE: usize =      1 << (L*3);
+    pub const LEAF_MASK_SIZE: usize = Self::LEAF_SIZE >> 6;
+    pub const LEAF_ORIGIN_MASK: i32 = !(Self::LEAF_DIM as i32 - 1);
+
+    pub const BRANCH_TOTAL: usize =     B + Self::LEAF_TOTAL;
+    pub const BRANCH_DIM: usize =       1 << Self::BRANCH_TOTAL;
+    pub const BRANCH_SIZE: usize =      1 << (B*3);
+    pub const BRANCH_MASK_SIZE: usize = Self::BRANCH_SIZE >> 6;
+    pub const BRANCH_ORIGIN_MASK: i32 = !(Self::BRANCH_DIM as i32 - 1);
+
+    pub const TRUNK_TOTAL: usize =     T + Self::BRANCH_TOTAL;
+    pub const TRUNK_DIM: usize =       1 << Self::TRUNK_TOTAL;
+    pub const TRUNK_SIZE: usize =      1 << (T*3);
+    pub const TRUNK_MASK_SIZE: usize = Self::TRUNK_SIZE >> 6;
+    pub const TRUNK_ORIGIN_MASK: i32 = !(Self::TRUNK_DIM as i32 - 1);
+
+    pub const LOG2DIMS: [u8; 3] = [L as u8, B as u8, T as u8];
+
+    /// Fails the build for any layout that uses it with a log2 dim below 2 or trunks too big for `i32` coords
+    pub const CHECK: () = assert!(L >= 2 && B >= 2 && T >= 2 && Self::TRUNK_TOTAL < 31, "invalid voxel tree layout");
+
+    pub fn leaf_index_from_coord(
+        coord: &IVec3,
+    ) -> usize {
+        index_from_coord(coord, L, 0)
+    }
+
+    pub fn branch_index_from_coord(
+        coord: &IVec3,
+    ) -> usize {
+        index_from_coord(coord, B, Self::LEAF_TOTAL)
+    }
+
+    pub fn trunk_index_from_coord(
+        coord: &IVec3,
+    ) -> usize {
+        index_from_coord(coord, T, Self::BRANCH_TOTAL)
+    }
+
+    /// Voxel offset of the index'th voxel from its leaf origin
+    pub fn leaf_offset_from_index(
+        index: usize,
+    ) -> IVec3 {
+        offset_from_index(index, L, 0)
+    }
+
+    /// Voxel offset of the index'th leaf from its branch origin
+    pub fn branch_offset_from_index(
+        index: usize,
+    ) -> IVec3 {
+        offset_from_index(index, B, Self::LEAF_TOTAL)
+    }
+
+    /// Voxel offset of the index'th branch from its trunk origin
+    pub fn trunk_offset_from_index(
+        index: usize,
+    ) -> IVec3 {
+        offset_from_index(index, T, Self::BRANCH_TOTAL)
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// FUNCTIONS
+/// Index of the child around `coord` in a node `1 << log2dim` children across, with children `1 << child_total` voxels across
+fn index_from_coord(
+    coord: &IVec3,
+    log2dim: usize,
+    child_total: usize,
+) -> usize {
+    let local = (*coord & ((1 << (log2dim + child_total)) - 1)) >> child_total as i32;
+    ((local.x << (log2dim*2)) + (local.y << log2dim) + local.z) as usize
+}
+
+fn offset_from_index(
+    index: usize,
+    log2dim: usize,
+    child_total: usize,
+) -> IVec3 {
+    let x = (index >> (log2dim*2)) as i32;
+    let n = index & ((1 << (log2dim*2)) - 1);
+    let y = (n >> log2dim) as i32;
+    let z = (n & ((1 << log2dim) - 1)) as i32;
+
+    IVec3::new(x, y, z) << child_total as i32
+}
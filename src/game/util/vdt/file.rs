@@ -0,0 +1,166 @@
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// USE
+use crate::*;
+use super::*;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// CONSTANTS
+pub const VDT_FILE_MAGIC: [u8; 4] = *b"SVDT";
+/// Bump whenever the layout of any `*File` struct below changes
+pub const VDT_FILE_VERSION: u32 = 1;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// STRUCTS
+/// On-disk form of a [RootNode]. Only allocated nodes are written, every other region is stored as its tile value.
+#[derive(Serialize, Deserialize)]
+pub struct RootFile<ValueType> {
+    pub(super) magic: [u8; 4],
+    pub(super) version: u32,
+    pub(super) log2dims: [u8; 3],
+    pub(super) background: ValueType,
+    pub(super) entries: Vec<RootDataFile<ValueType>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RootDataFile<ValueType> {
+    pub(super) key: IVec3,
+    pub(super) tile: ValueType,
+    pub(super) child: Option<TrunkFile<ValueType>>,
+}
+
+/// `tiles` holds one value per index with `child_mask` off, `children` one node per index with it on, both in index order
+#[derive(Serialize, Deserialize)]
+pub struct TrunkFile<ValueType> {
+    pub(super) origin: IVec3,
+    pub(super) child_mask: Bitmask,
+    pub(super) value_mask: Bitmask,
+    pub(super) tiles: Vec<ValueType>,
+    pub(super) children: Vec<BranchFile<ValueType>>,
+}
+
+/// Same layout as [TrunkFile]
+#[derive(Serialize, Deserialize)]
+pub struct BranchFile<ValueType> {
+    pub(super) origin: IVec3,
+    pub(super) child_mask: Bitmask,
+    pub(super) value_mask: Bitmask,
+    pub(super) tiles: Vec<ValueType>,
+    pub(super) children: Vec<LeafFile<ValueType>>,
+}
+
+/// Inactive values are kept, so a sparse leaf round-trips exactly
+#[derive(Serialize, Deserialize)]
+pub struct LeafFile<ValueType> {
+    pub(super) origin: IVec3,
+    pub(super) value_mask: Bitmask,
+    pub(super) data: Vec<ValueType>,
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// FUNCTIONS
+pub fn vdt_file_error(
+    message: &str,
+) -> bincode::Error {
+    Box::new(bincode::ErrorKind::Custom(message.to_string()))
+}
+
+pub fn check_file_mask(
+    mask: &Bitmask,
+    size: usize,
+    name: &str,
+) -> bincode::Result<()> {
+    if mask.size != size || mask.words.len() != mask.word_num || mask.word_num != size >> 6 {
+        return Err(vdt_file_error(&format!("{} has size {} but expected {}", name, mask.size, size)));
+    }
+
+    Ok(())
+}
+
+pub fn try_save_root_node<ValueType: Clone + Copy + PartialEq + Eq + Serialize + for<'a> Deserialize<'a>, const L: usize, const B: usize, const T: usize>(
+    root_node: &Arc<RwLock<RootNode<ValueType, L, B, T>>>,
+    path: &str,
+) -> bool {
+    match root_node.read().unwrap().to_bytes() {
+        Ok(bytes) => Data::try_write_file(path, &bytes),
+        Err(error) => {
+            error!("failed to save tree to {}: {}", path, error);
+            false
+        }
+    }
+}
+
+pub fn try_load_root_node<ValueType: Clone + Copy + PartialEq + Eq + Serialize + for<'a> Deserialize<'a>, const L: usize, const B: usize, const T: usize>(
+    path: &str,
+) -> Option<RootNode<ValueType, L, B, T>> {
+    let bytes = Data::try_read_file_to_bytes(path)?;
+    match RootNode::<ValueType, L, B, T>::from_bytes(&bytes) {
+        Ok(root_node) => Some(root_node),
+        Err(error) => {
+            error!("failed to load tree from {}: {}", path, error);
+            None
+        }
+    }
+}
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestRoot = RootNode<u8, 2, 2, 2>;
+
+    /// Loose voxels in two trunks, an inactive value, a branch tile & a root tile
+    fn test_root() -> Arc<RwLock<TestRoot>> {
+        let root_node = Arc::new(RwLock::new(TestRoot::new(0)));
+        let mut values = Accessor::new(&root_node).with_remesh_mode(RemeshMode::Off);
+        values.set_value_on(&IVec3::new(1, 2, 3), &7);
+        values.set_value_on(&IVec3::new(-5, 70, -130), &9);
+        values.set_value(&IVec3::new(2, 2, 3), &4, false);
+        values.set_tile(&IVec3::new(16, 0, 0), 16, &3, true);
+        values.set_tile(&IVec3::new(-64, -64, -64), 64, &5, true);
+        root_node
+    }
+
+    fn active_values(
+        root_node: &Arc<RwLock<TestRoot>>,
+    ) -> Vec<(IVec3, u8)> {
+        let mut voxels: Vec<(IVec3, u8)> = root_node.read().unwrap().iter_active_regions().voxels().collect();
+        voxels.sort_by_key(|(coord, _)| coord.to_array());
+        voxels
+    }
+
+    fn test_bytes() -> Vec<u8> {
+        test_root().read().unwrap().to_bytes().unwrap()
+    }
+
+    #[test]
+    fn round_trip_keeps_values_and_tiles() {
+        let root_node = test_root();
+        let loaded = Arc::new(RwLock::new(TestRoot::from_bytes(&root_node.read().unwrap().to_bytes().unwrap()).unwrap()));
+        assert_eq!(active_values(&loaded), active_values(&root_node));
+
+        let mut values = Accessor::new(&loaded);
+        assert_eq!(values.get_value(&IVec3::new(2, 2, 3)), 4);
+        assert_eq!(values.get_value_and_extent(&IVec3::new(20, 4, 4)), (3, 16));
+        assert_eq!(values.get_value_and_extent(&IVec3::new(-1, -1, -1)), (5, 64));
+        assert_eq!(values.get_value(&IVec3::new(100, 100, 100)), 0);
+    }
+
+    #[test]
+    fn rejects_other_dims() {
+        let bytes = test_bytes();
+        assert!(RootNode::<u8, 2, 2, 3>::from_bytes(&bytes).is_err());
+        assert!(RootNode::<u8, 3, 2, 2>::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_other_magic_and_versions() {
+        let mut bytes = test_bytes();
+        bytes[0] = b'X';
+        assert!(TestRoot::from_bytes(&bytes).is_err());
+
+        let mut bytes = test_bytes();
+        bytes[4..8].copy_from_slice(&(VDT_FILE_VERSION + 1).to_le_bytes());
+        assert!(TestRoot::from_bytes(&bytes).is_err());
+    }
+}
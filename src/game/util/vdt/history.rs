@@ -0,0 +1,130 @@
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// USE
+use crate::*;
+use super::*;
+use std::collections::VecDeque;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// CONSTANTS
+/// Default for [VoxelHistory::capacity], in groups
+pub const VOXEL_HISTORY_CAPACITY: usize = 64;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// COMPONENTS
+//================================-================================-================================
+// VoxelHistory
+/// Bounded undo & redo stacks of voxel diffs
+///
+/// Changes are collected into an open group until [VoxelHistory::end_group], so a click, a drag or a region edit is undone in one step
+#[derive(Component)]
+pub struct VoxelHistory {
+    undo_stack: VecDeque<Vec<VoxelChange>>,
+    redo_stack: Vec<Vec<VoxelChange>>,
+    open_group: Vec<VoxelChange>,
+    /// Most groups kept, the oldest are dropped first
+    pub capacity: usize,
+}
+
+impl Default for VoxelHistory {
+    fn default() -> Self {
+        Self {
+            undo_stack: VecDeque::new(),
+            redo_stack: vec![],
+            open_group: vec![],
+            capacity: VOXEL_HISTORY_CAPACITY,
+        }
+    }
+}
+
+impl VoxelHistory {
+    pub fn can_undo(&self) -> bool { !self.open_group.is_empty() || !self.undo_stack.is_empty() }
+    pub fn can_redo(&self) -> bool { !self.redo_stack.is_empty() }
+
+    /// Adds `changes` to the open group, in write order
+    pub fn record(
+        &mut self,
+        changes: Vec<VoxelChange>,
+    ) {
+        self.open_group.extend(changes);
+    }
+
+    /// Closes the open group, a new edit drops everything that could be redone
+    pub fn end_group(
+        &mut self,
+    ) {
+        if self.open_group.is_empty() {
+            return;
+        }
+
+        let group = std::mem::take(&mut self.open_group);
+        self.redo_stack.clear();
+        self.push_undo(group);
+    }
+
+    /// Reverts the last group, false if there was nothing to undo
+    pub fn undo(
+        &mut self,
+        voxels: &mut Accessor<Voxel>,
+        commands: &mut Commands,
+    ) -> bool {
+        self.end_group();
+        if let Some(group) = self.undo_stack.pop_back() {
+            apply_changes(voxels, group.iter().rev().map(|change| (change, &change.old)), commands);
+            self.redo_stack.push(group);
+            return true;
+        }
+
+        false
+    }
+
+    /// Reapplies the last undone group, false if there was nothing to redo
+    pub fn redo(
+        &mut self,
+        voxels: &mut Accessor<Voxel>,
+        commands: &mut Commands,
+    ) -> bool {
+        self.end_group();
+        if let Some(group) = self.redo_stack.pop() {
+            apply_changes(voxels, group.iter().map(|change| (change, &change.new)), commands);
+            self.push_undo(group);
+            return true;
+        }
+
+        false
+    }
+
+    fn push_undo(
+        &mut self,
+        group: Vec<VoxelChange>,
+    ) {
+        self.undo_stack.push_back(group);
+        while self.undo_stack.len() > self.capacity {
+            self.undo_stack.pop_front();
+        }
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// FUNCTIONS
+/// Writes each change's block back to `value`, remeshing is batched so touched meshes are rebuilt once
+fn apply_changes<'a>(
+    voxels: &mut Accessor<Voxel>,
+    changes: impl Iterator<Item = (&'a VoxelChange, &'a Voxel)>,
+    commands: &mut Commands,
+) {
+    let remesh_mode = voxels.remesh_mode();
+    voxels.set_remesh_mode(RemeshMode::Batched);
+
+    for (change, value) in changes {
+        let active = value != voxels.background();
+        if change.dim == 1 {
+            voxels.set_value(&change.coord, value, active);
+        } else {
+            let shape = AreaShape::Rect { start: change.coord, end: change.coord + IVec3::splat(change.dim as i32 - 1) };
+            let report = if active { voxels.fill(&shape, value) } else { voxels.clear(&shape) };
+            report.despawn_meshes(commands);
+        }
+    }
+
+    voxels.set_remesh_mode(remesh_mode);
+}
@@ -5,58 +5,85 @@ use super::*;
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // STRUCTS
+/// A node handed out for an [Accessor] to cache, `None` where the tree holds a tile instead
+pub type LeafRef<ValueType, const L: usize, const B: usize, const T: usize> = Option<Arc<RwLock<LeafNode<ValueType, L, B, T>>>>;
+pub type BranchRef<ValueType, const L: usize, const B: usize, const T: usize> = Option<Arc<RwLock<BranchNode<ValueType, L, B, T>>>>;
+pub type TrunkRef<ValueType, const L: usize, const B: usize, const T: usize> = Option<Arc<RwLock<TrunkNode<ValueType, L, B, T>>>>;
+
 //================================-================================-================================ 
 // TrunkNode
-pub struct TrunkNode<ValueType: Clone + Copy + PartialEq + Eq> {
+pub struct TrunkNode<ValueType: Clone + Copy + PartialEq + Eq, const L: usize = { LEAF_LOG2DIM }, const B: usize = { BRANCH_LOG2DIM }, const T: usize = { TRUNK_LOG2DIM }> {
     origin: IVec3,
-    nodes: Vec<TrunkData<ValueType>>,
+    nodes: Vec<TrunkData<ValueType, L, B, T>>,
     child_mask: Bitmask,
     value_mask: Bitmask,
     redraw_mask: Bitmask,
 }
 
 impl TrunkNode<Voxel> {
-    pub fn get_meshes(
+    pub fn get_mesh_jobs(
         &self,
         defs: &Res<Defs>,
         accessor: &mut Accessor<Voxel>,
-    ) -> Vec<(IVec3, Option<Mesh>)>  {
-        let mut key_mesh_pairs: Vec<(IVec3, Option<Mesh>)> = vec![];
+        mesh_mode: VoxelMeshMode,
+    ) -> Vec<VoxelMeshJob>  {
+        let mut jobs: Vec<VoxelMeshJob> = vec![];
 
         for index in OnMaskIter::new(0, &self.redraw_mask) {
             if self.child_mask.is_bit_on(index) {
                 let branch_read_lock = self.nodes[index].child.as_ref().unwrap().read().unwrap();
-                key_mesh_pairs.extend(branch_read_lock.get_meshes(defs, accessor));
+                jobs.extend(branch_read_lock.get_mesh_jobs(defs, accessor, mesh_mode));
             } else {
-                error!("needed BRANCH tile mesh but developer is lazy!");
+                let tile_origin = self.global_coord_from_index(index);
+                let active = self.value_mask.is_bit_on(index);
+                jobs.push(VoxelMeshJob::new_tile(&tile_origin, BRANCH_DIM, LEAF_DIM, &self.nodes[index].tile, active, defs, accessor));
             }
         }
 
-        key_mesh_pairs
+        jobs
     }
 }
 
-impl<ValueType: Clone + Copy + PartialEq + Eq> TrunkNode<ValueType> {
+impl<ValueType: Clone + Copy + PartialEq + Eq, const L: usize, const B: usize, const T: usize> TrunkNode<ValueType, L, B, T> {
     pub fn new(
         coord: &IVec3,
         background: &ValueType,
         active: bool,
     ) -> Self {
         Self {
-            origin: *coord & TRUNK_ORIGIN_MASK,
-            nodes: vec![TrunkData::new_tile(background); TRUNK_SIZE],
-            child_mask: Bitmask::from_cube_log2dim(TRUNK_LOG2DIM, false),
-            value_mask: Bitmask::from_cube_log2dim(TRUNK_LOG2DIM, active),
-            redraw_mask: Bitmask::from_cube_log2dim(TRUNK_LOG2DIM, active),
+            origin: *coord & TreeDims::<L, B, T>::TRUNK_ORIGIN_MASK,
+            nodes: vec![TrunkData::new_tile(background); TreeDims::<L, B, T>::TRUNK_SIZE],
+            child_mask: Bitmask::from_cube_log2dim(T, false),
+            value_mask: Bitmask::from_cube_log2dim(T, active),
+            redraw_mask: Bitmask::from_cube_log2dim(T, active),
         }
     }
 
     pub fn index_from_coord(
         coord: &IVec3
     ) -> usize {
-        ((((coord.x & (TRUNK_DIM as i32 - 1)) >> BRANCH_TOTAL) << TRUNK_LOG2DIM*2) +
-         (((coord.y & (TRUNK_DIM as i32 - 1)) >> BRANCH_TOTAL) << TRUNK_LOG2DIM  ) +
-         (((coord.z & (TRUNK_DIM as i32 - 1)) >> BRANCH_TOTAL))) as usize
+        TreeDims::<L, B, T>::trunk_index_from_coord(coord)
+    }
+
+    pub fn global_coord_from_index(
+        &self,
+        index: usize,
+    ) -> IVec3 {
+        TreeDims::<L, B, T>::trunk_offset_from_index(index) + self.origin
+    }
+
+    /// Origin & value of every active tile, in index order, children are left out
+    pub fn iter_active_tiles(
+        &self,
+    ) -> impl Iterator<Item = (IVec3, ValueType)> + '_ {
+        OnMaskIter::new(0, &self.value_mask).map(|index| (self.global_coord_from_index(index), self.nodes[index].tile))
+    }
+
+    /// Origin of every child, in index order
+    pub fn iter_children(
+        &self,
+    ) -> impl Iterator<Item = (IVec3, &Arc<RwLock<BranchNode<ValueType, L, B, T>>>)> + '_ {
+        OnMaskIter::new(0, &self.child_mask).map(|index| (self.global_coord_from_index(index), self.nodes[index].child.as_ref().unwrap()))
     }
 
 
@@ -65,29 +92,73 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> TrunkNode<ValueType> {
         &mut self,
         coord: &IVec3,
         new_mesh_entity: &Option<Entity>,
-    ) -> Option<Entity> {
-        let mut despawn_entity = None;
+    ) -> Vec<Entity> {
+        let mut despawn: Vec<Entity> = vec![];
 
-        let index = TrunkNode::<ValueType>::index_from_coord(coord);
+        let index = Self::index_from_coord(coord);
 
+        despawn.append(&mut self.nodes[index].retired);
         if self.child_mask.is_bit_on(index) {
-            let mut branch_write_lock = self.nodes[index].child.as_ref().unwrap().write().unwrap();
-            despawn_entity = branch_write_lock.assign_mesh_entity(coord, new_mesh_entity);
+            despawn.extend(self.nodes[index].child.as_ref().unwrap().write().unwrap().assign_mesh_entity(coord, new_mesh_entity));
+            // Shell of the tile this branch replaced
+            despawn.extend(self.nodes[index].mesh.take());
         } else {
-            despawn_entity = self.nodes[index].mesh;
+            despawn.extend(self.nodes[index].mesh);
             self.nodes[index].mesh = *new_mesh_entity;
         }
 
-        self.redraw_mask.set_bit_off(index);
+        despawn
+    }
 
-        despawn_entity
+    pub fn get_mesh_entities(
+        &self,
+    ) -> Vec<Entity> {
+        let mut mesh_entities: Vec<Entity> = vec![];
+
+        for trunk_data in self.nodes.iter() {
+            if let Some(branch_node) = &trunk_data.child {
+                mesh_entities.extend(branch_node.read().unwrap().get_mesh_entities());
+            }
+
+            if let Some(mesh_entity) = trunk_data.mesh {
+                mesh_entities.push(mesh_entity);
+            }
+
+            mesh_entities.extend(trunk_data.retired.iter());
+        }
+
+        mesh_entities
+    }
+
+    /// Called once every redraw has been turned into a mesh job
+    pub fn clear_redraw(
+        &mut self,
+    ) {
+        for index in OnMaskIter::new(0, &self.redraw_mask) {
+            if let Some(branch_node) = &self.nodes[index].child {
+                branch_node.write().unwrap().clear_redraw();
+            }
+        }
+
+        self.redraw_mask.set_off();
+    }
+
+    /// Marks every branch & active tile for redraw, down to the leaves
+    pub fn set_redraw_all(
+        &mut self,
+    ) {
+        self.redraw_mask = self.child_mask.clone();
+        self.redraw_mask.set_union(&self.value_mask);
+        for index in OnMaskIter::new(0, &self.child_mask) {
+            self.nodes[index].child.as_ref().unwrap().write().unwrap().set_redraw_all();
+        }
     }
 
     pub fn set_redraw_and_cache(
         &mut self,
         coord: &IVec3,
-    ) -> Option<Arc<RwLock<BranchNode<ValueType>>>> {
-        let index = TrunkNode::<ValueType>::index_from_coord(coord);
+    ) -> Option<Arc<RwLock<BranchNode<ValueType, L, B, T>>>> {
+        let index = Self::index_from_coord(coord);
         self.redraw_mask.set_bit_on(index);
         
         if self.child_mask.is_bit_on(index) {
@@ -103,8 +174,8 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> TrunkNode<ValueType> {
     pub fn get_value_and_cache(
         &self,
         coord: &IVec3,
-    ) -> (ValueType, Option<Arc<RwLock<LeafNode<ValueType>>>>, Option<Arc<RwLock<BranchNode<ValueType>>>>) {
-        let index = TrunkNode::<ValueType>::index_from_coord(coord);
+    ) -> (ValueType, LeafRef<ValueType, L, B, T>, BranchRef<ValueType, L, B, T>) {
+        let index = Self::index_from_coord(coord);
         if self.child_mask.is_bit_on(index) {
             let (value, leaf_node) = self.nodes[index].child.as_ref().unwrap().read().unwrap().get_value_and_cache(coord);
             let branch_node = Some(self.nodes[index].child.as_ref().unwrap().clone());
@@ -114,13 +185,41 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> TrunkNode<ValueType> {
         }
     }
 
+    /// `Some` if the `dim` sized block around `coord` is a single tile
+    pub fn get_block_value(
+        &self,
+        coord: &IVec3,
+        dim: usize,
+    ) -> Option<ValueType> {
+        let index = Self::index_from_coord(coord);
+        if self.child_mask.is_bit_off(index) {
+            Some(self.nodes[index].tile)
+        } else if dim >= TreeDims::<L, B, T>::BRANCH_DIM {
+            None
+        } else {
+            self.nodes[index].child.as_ref().unwrap().read().unwrap().get_block_value(coord, dim)
+        }
+    }
+
+    pub fn get_value_and_extent(
+        &self,
+        coord: &IVec3,
+    ) -> (ValueType, usize) {
+        let index = Self::index_from_coord(coord);
+        if self.child_mask.is_bit_off(index) {
+            (self.nodes[index].tile, TreeDims::<L, B, T>::BRANCH_DIM)
+        } else {
+            self.nodes[index].child.as_ref().unwrap().read().unwrap().get_value_and_extent(coord)
+        }
+    }
+
     pub fn set_value_and_cache(
         &mut self,
         coord: &IVec3,
         value: &ValueType,
         active: bool,
-    ) -> (Option<Arc<RwLock<LeafNode<ValueType>>>>, Option<Arc<RwLock<BranchNode<ValueType>>>>) {
-        let index = TrunkNode::<ValueType>::index_from_coord(coord);
+    ) -> (LeafRef<ValueType, L, B, T>, BranchRef<ValueType, L, B, T>) {
+        let index = Self::index_from_coord(coord);
         let mut has_child = self.child_mask.is_bit_on(index);
 
         if !has_child {
@@ -142,6 +241,44 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> TrunkNode<ValueType> {
     }
 
 
+    /// See [RootNode::set_tile]
+    pub fn set_tile(
+        &mut self,
+        coord: &IVec3,
+        dim: usize,
+        value: &ValueType,
+        active: bool,
+    ) -> Vec<Entity> {
+        let index = Self::index_from_coord(coord);
+
+        if dim >= TreeDims::<L, B, T>::BRANCH_DIM {
+            let mut despawn: Vec<Entity> = vec![];
+            if let Some(branch_node) = self.nodes[index].child.take() {
+                despawn.extend(branch_node.read().unwrap().get_mesh_entities());
+            }
+
+            self.nodes[index].tile = *value;
+            self.child_mask.set_bit_off(index);
+            self.value_mask.set_bit(index, active);
+            self.redraw_mask.set_bit_on(index);
+            return despawn;
+        }
+
+        if self.child_mask.is_bit_off(index) {
+            let tile = self.nodes[index].tile;
+            let tile_active = self.value_mask.is_bit_on(index);
+            if tile == *value && tile_active == active {
+                return vec![];
+            }
+
+            self.set_child_node(index, coord, &tile, tile_active);
+        }
+
+        self.redraw_mask.set_bit_on(index);
+        self.nodes[index].child.as_ref().unwrap().write().unwrap().set_tile(coord, value, active);
+        vec![]
+    }
+
 
     pub fn set_child_node(
         &mut self,
@@ -155,61 +292,203 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> TrunkNode<ValueType> {
         self.redraw_mask.set_bit_on(index);
         self.nodes[index].child = Some(Arc::new(RwLock::new(BranchNode::new(coord, &self.nodes[index].tile, active))));
     }
+
+
+
+    /// Size of one trunk in memory, not counting its children
+    pub fn mem_size() -> usize {
+        std::mem::size_of::<Self>() + TreeDims::<L, B, T>::TRUNK_SIZE * std::mem::size_of::<TrunkData<ValueType, L, B, T>>() + 3 * TreeDims::<L, B, T>::TRUNK_MASK_SIZE * std::mem::size_of::<usize>()
+    }
+
+    /// `Some` if this trunk has no children and every tile & active state is the same
+    pub fn get_uniform_value(
+        &self,
+    ) -> Option<(ValueType, bool)> {
+        if !self.child_mask.is_off() { return None; }
+
+        let active = if self.value_mask.is_on() {
+                true
+            } else if self.value_mask.is_off() {
+                false
+            } else {
+                return None;
+            };
+
+        let value = self.nodes[0].tile;
+        if self.nodes.iter().all(|trunk_data| trunk_data.tile == value) {
+            Some((value, active))
+        } else {
+            None
+        }
+    }
+
+    /// Prunes every branch, folding the uniform ones back into tiles
+    /// 
+    /// Returns the value of this trunk if it is now uniform itself
+    pub fn prune(
+        &mut self,
+        report: &mut PruneReport,
+    ) -> Option<(ValueType, bool)> {
+        let child_indices: Vec<usize> = OnMaskIter::new(0, &self.child_mask).collect();
+        for index in child_indices {
+            let uniform = self.nodes[index].child.as_ref().unwrap().write().unwrap().prune(report);
+            if let Some((value, active)) = uniform {
+                let branch_node = self.nodes[index].child.take().unwrap();
+                let retired = branch_node.read().unwrap().get_mesh_entities();
+                self.nodes[index].retired.extend(retired);
+                report.branches += 1;
+                report.bytes += BranchNode::<ValueType, L, B, T>::mem_size();
+
+                self.nodes[index].tile = value;
+                self.child_mask.set_bit_off(index);
+                self.value_mask.set_bit(index, active);
+                self.redraw_mask.set_bit_on(index);
+            }
+        }
+
+        self.get_uniform_value()
+    }
+}
+
+impl<ValueType: Clone + Copy + PartialEq + Eq + Serialize + for<'a> Deserialize<'a>, const L: usize, const B: usize, const T: usize> TrunkNode<ValueType, L, B, T> {
+    pub fn to_file(
+        &self,
+    ) -> TrunkFile<ValueType> {
+        let mut tiles: Vec<ValueType> = vec![];
+        let mut children: Vec<BranchFile<ValueType>> = vec![];
+
+        for (index, trunk_data) in self.nodes.iter().enumerate() {
+            if self.child_mask.is_bit_on(index) {
+                children.push(trunk_data.child.as_ref().unwrap().read().unwrap().to_file());
+            } else {
+                tiles.push(trunk_data.tile);
+            }
+        }
+
+        TrunkFile {
+            origin: self.origin,
+            child_mask: self.child_mask.clone(),
+            value_mask: self.value_mask.clone(),
+            tiles,
+            children,
+        }
+    }
+
+    /// Every allocated branch & leaf and every active tile is marked for redraw
+    pub fn from_file(
+        file: TrunkFile<ValueType>,
+        background: &ValueType,
+    ) -> bincode::Result<Self> {
+        check_file_mask(&file.child_mask, TreeDims::<L, B, T>::TRUNK_SIZE, "trunk child_mask")?;
+        check_file_mask(&file.value_mask, TreeDims::<L, B, T>::TRUNK_SIZE, "trunk value_mask")?;
+
+        let mut nodes: Vec<TrunkData<ValueType, L, B, T>> = Vec::with_capacity(TreeDims::<L, B, T>::TRUNK_SIZE);
+        let mut tiles = file.tiles.into_iter();
+        let mut children = file.children.into_iter();
+
+        for index in 0..TreeDims::<L, B, T>::TRUNK_SIZE {
+            if file.child_mask.is_bit_on(index) {
+                let branch_file = children.next().ok_or_else(|| vdt_file_error("trunk is missing a branch"))?;
+                let mut trunk_data = TrunkData::new_tile(background);
+                trunk_data.child = Some(Arc::new(RwLock::new(BranchNode::from_file(branch_file, background)?)));
+                nodes.push(trunk_data);
+            } else {
+                let tile = tiles.next().ok_or_else(|| vdt_file_error("trunk is missing a tile"))?;
+                nodes.push(TrunkData::new_tile(&tile));
+            }
+        }
+
+        if tiles.next().is_some() || children.next().is_some() {
+            return Err(vdt_file_error("trunk has more tiles or branches than its child_mask allows"));
+        }
+
+        let mut redraw_mask = file.child_mask.clone();
+        redraw_mask.set_union(&file.value_mask);
+
+        Ok(Self {
+            origin: file.origin & TreeDims::<L, B, T>::TRUNK_ORIGIN_MASK,
+            nodes,
+            redraw_mask,
+            child_mask: file.child_mask,
+            value_mask: file.value_mask,
+        })
+    }
 }
 
 //================================-================================-================================ 
 // BranchNode
-pub struct BranchNode<ValueType: Clone + Copy + PartialEq + Eq> {
+pub struct BranchNode<ValueType: Clone + Copy + PartialEq + Eq, const L: usize = { LEAF_LOG2DIM }, const B: usize = { BRANCH_LOG2DIM }, const T: usize = { TRUNK_LOG2DIM }> {
     origin: IVec3,
-    nodes: Vec<BranchData<ValueType>>,
+    nodes: Vec<BranchData<ValueType, L, B, T>>,
     child_mask: Bitmask,
     value_mask: Bitmask,
     redraw_mask: Bitmask,
 }
 
 impl BranchNode<Voxel> {
-    pub fn get_meshes(
+    pub fn get_mesh_jobs(
         &self,
         defs: &Res<Defs>,
         accessor: &mut Accessor<Voxel>,
-    ) -> Vec<(IVec3, Option<Mesh>)> {
-        let mut key_mesh_pairs: Vec<(IVec3, Option<Mesh>)> = vec![];
+        mesh_mode: VoxelMeshMode,
+    ) -> Vec<VoxelMeshJob> {
+        let mut jobs: Vec<VoxelMeshJob> = vec![];
 
         for index in OnMaskIter::new(0, &self.redraw_mask) {
             if self.child_mask.is_bit_on(index) {
                 let leaf_read_lock = self.nodes[index].child.as_ref().unwrap().read().unwrap();
-                let key_mesh_pair = leaf_read_lock.get_mesh(defs, accessor);
-                key_mesh_pairs.push(key_mesh_pair);
+                jobs.push(leaf_read_lock.get_mesh_job(defs, accessor, mesh_mode));
             } else {
-                error!("needed LEAF tile mesh but developer is lazy!");
+                let tile_origin = self.global_coord_from_index(index);
+                let active = self.value_mask.is_bit_on(index);
+                jobs.push(VoxelMeshJob::new_tile(&tile_origin, LEAF_DIM, 1, &self.nodes[index].tile, active, defs, accessor));
             }
         }
 
-        key_mesh_pairs
+        jobs
     }
 }
 
-impl<ValueType: Clone + Copy + PartialEq + Eq> BranchNode<ValueType> {
+impl<ValueType: Clone + Copy + PartialEq + Eq, const L: usize, const B: usize, const T: usize> BranchNode<ValueType, L, B, T> {
     pub fn new(
         coord: &IVec3,
         background: &ValueType,
         active: bool,
     ) -> Self {
         Self {
-            origin: *coord & BRANCH_ORIGIN_MASK,
-            nodes: vec![BranchData::new_tile(background); BRANCH_SIZE],
-            child_mask: Bitmask::from_cube_log2dim(BRANCH_LOG2DIM, false),
-            value_mask: Bitmask::from_cube_log2dim(BRANCH_LOG2DIM, active),
-            redraw_mask: Bitmask::from_cube_log2dim(BRANCH_LOG2DIM, active),
+            origin: *coord & TreeDims::<L, B, T>::BRANCH_ORIGIN_MASK,
+            nodes: vec![BranchData::new_tile(background); TreeDims::<L, B, T>::BRANCH_SIZE],
+            child_mask: Bitmask::from_cube_log2dim(B, false),
+            value_mask: Bitmask::from_cube_log2dim(B, active),
+            redraw_mask: Bitmask::from_cube_log2dim(B, active),
         }
     }
 
     pub fn index_from_coord(
         coord: &IVec3
     ) -> usize {
-        ((((coord.x & (BRANCH_DIM as i32 - 1)) >> LEAF_TOTAL) << BRANCH_LOG2DIM*2) +
-         (((coord.y & (BRANCH_DIM as i32 - 1)) >> LEAF_TOTAL) << BRANCH_LOG2DIM  ) +
-         (((coord.z & (BRANCH_DIM as i32 - 1)) >> LEAF_TOTAL))) as usize
+        TreeDims::<L, B, T>::branch_index_from_coord(coord)
+    }
+
+    pub fn global_coord_from_index(
+        &self,
+        index: usize,
+    ) -> IVec3 {
+        TreeDims::<L, B, T>::branch_offset_from_index(index) + self.origin
+    }
+
+    /// Origin & value of every active tile, in index order, children are left out
+    pub fn iter_active_tiles(
+        &self,
+    ) -> impl Iterator<Item = (IVec3, ValueType)> + '_ {
+        OnMaskIter::new(0, &self.value_mask).map(|index| (self.global_coord_from_index(index), self.nodes[index].tile))
+    }
+
+    /// Origin of every child, in index order
+    pub fn iter_children(
+        &self,
+    ) -> impl Iterator<Item = (IVec3, &Arc<RwLock<LeafNode<ValueType, L, B, T>>>)> + '_ {
+        OnMaskIter::new(0, &self.child_mask).map(|index| (self.global_coord_from_index(index), self.nodes[index].child.as_ref().unwrap()))
     }
 
 
@@ -219,20 +498,39 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> BranchNode<ValueType> {
         coord: &IVec3,
         new_mesh_entity: &Option<Entity>,
     ) -> Option<Entity> {
-        let index = BranchNode::<ValueType>::index_from_coord(coord);
+        let index = Self::index_from_coord(coord);
         
         let despawn_entity = self.nodes[index].mesh;
         self.nodes[index].mesh = *new_mesh_entity;
-        self.redraw_mask.set_bit_off(index);
 
         despawn_entity
     }
 
+    pub fn get_mesh_entities(
+        &self,
+    ) -> Vec<Entity> {
+        self.nodes.iter().filter_map(|branch_data| branch_data.mesh).collect()
+    }
+
+    pub fn clear_redraw(
+        &mut self,
+    ) {
+        self.redraw_mask.set_off();
+    }
+
+    /// Marks every leaf & active tile for redraw
+    pub fn set_redraw_all(
+        &mut self,
+    ) {
+        self.redraw_mask = self.child_mask.clone();
+        self.redraw_mask.set_union(&self.value_mask);
+    }
+
     pub fn set_redraw(
         &mut self,
         coord: &IVec3,
     ) {
-        let index = BranchNode::<ValueType>::index_from_coord(coord);
+        let index = Self::index_from_coord(coord);
         self.redraw_mask.set_bit_on(index);
     }
 
@@ -241,8 +539,8 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> BranchNode<ValueType> {
     pub fn get_value_and_cache(
         &self,
         coord: &IVec3,
-    ) -> (ValueType, Option<Arc<RwLock<LeafNode<ValueType>>>>) {
-        let index = BranchNode::<ValueType>::index_from_coord(coord);
+    ) -> (ValueType, LeafRef<ValueType, L, B, T>) {
+        let index = Self::index_from_coord(coord);
         if self.child_mask.is_bit_on(index) {
             let value = self.nodes[index].child.as_ref().unwrap().read().unwrap().get_value_at_coord(coord);
             let leaf_node = Some(self.nodes[index].child.as_ref().unwrap().clone());
@@ -252,13 +550,41 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> BranchNode<ValueType> {
         }
     }
 
+    /// `Some` if the `dim` sized block around `coord` is a single tile or voxel
+    pub fn get_block_value(
+        &self,
+        coord: &IVec3,
+        dim: usize,
+    ) -> Option<ValueType> {
+        let index = Self::index_from_coord(coord);
+        if self.child_mask.is_bit_off(index) {
+            Some(self.nodes[index].tile)
+        } else if dim > 1 {
+            None
+        } else {
+            Some(self.nodes[index].child.as_ref().unwrap().read().unwrap().get_value_at_coord(coord))
+        }
+    }
+
+    pub fn get_value_and_extent(
+        &self,
+        coord: &IVec3,
+    ) -> (ValueType, usize) {
+        let index = Self::index_from_coord(coord);
+        if self.child_mask.is_bit_off(index) {
+            (self.nodes[index].tile, TreeDims::<L, B, T>::LEAF_DIM)
+        } else {
+            (self.nodes[index].child.as_ref().unwrap().read().unwrap().get_value_at_coord(coord), 1)
+        }
+    }
+
     pub fn set_value_and_cache(
         &mut self,
         coord: &IVec3,
         value: &ValueType,
         active: bool,
-    ) -> Option<Arc<RwLock<LeafNode<ValueType>>>> {
-        let index = BranchNode::<ValueType>::index_from_coord(coord);
+    ) -> Option<Arc<RwLock<LeafNode<ValueType, L, B, T>>>> {
+        let index = Self::index_from_coord(coord);
         let mut has_child = self.child_mask.is_bit_on(index);
 
         if !has_child {
@@ -280,6 +606,21 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> BranchNode<ValueType> {
     }
 
 
+    /// Replaces the leaf around `coord` with a tile, the leaf mesh stays in place until the tile is remeshed
+    pub fn set_tile(
+        &mut self,
+        coord: &IVec3,
+        value: &ValueType,
+        active: bool,
+    ) {
+        let index = Self::index_from_coord(coord);
+        self.nodes[index].child = None;
+        self.nodes[index].tile = *value;
+        self.child_mask.set_bit_off(index);
+        self.value_mask.set_bit(index, active);
+        self.redraw_mask.set_bit_on(index);
+    }
+
 
     pub fn set_child_node(
         &mut self,
@@ -293,6 +634,144 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> BranchNode<ValueType> {
         self.redraw_mask.set_bit_on(index);
         self.nodes[index].child = Some(Arc::new(RwLock::new(LeafNode::new(coord, &self.nodes[index].tile, active))));
     }
+
+
+
+    /// Size of one branch in memory, not counting its children
+    pub fn mem_size() -> usize {
+        std::mem::size_of::<Self>() + TreeDims::<L, B, T>::BRANCH_SIZE * std::mem::size_of::<BranchData<ValueType, L, B, T>>() + 3 * TreeDims::<L, B, T>::BRANCH_MASK_SIZE * std::mem::size_of::<usize>()
+    }
+
+    /// `Some` if this branch has no children and every tile & active state is the same
+    pub fn get_uniform_value(
+        &self,
+    ) -> Option<(ValueType, bool)> {
+        if !self.child_mask.is_off() { return None; }
+
+        let active = if self.value_mask.is_on() {
+                true
+            } else if self.value_mask.is_off() {
+                false
+            } else {
+                return None;
+            };
+
+        let value = self.nodes[0].tile;
+        if self.nodes.iter().all(|branch_data| branch_data.tile == value) {
+            Some((value, active))
+        } else {
+            None
+        }
+    }
+
+    /// Folds every uniform leaf back into a tile
+    /// 
+    /// Returns the value of this branch if it is now uniform itself
+    pub fn prune(
+        &mut self,
+        report: &mut PruneReport,
+    ) -> Option<(ValueType, bool)> {
+        let child_indices: Vec<usize> = OnMaskIter::new(0, &self.child_mask).collect();
+        for index in child_indices {
+            self.try_prune_child(index, report);
+        }
+
+        self.get_uniform_value()
+    }
+
+    pub fn try_prune_child_at_coord(
+        &mut self,
+        coord: &IVec3,
+        report: &mut PruneReport,
+    ) -> bool {
+        let index = Self::index_from_coord(coord);
+        self.child_mask.is_bit_on(index) && self.try_prune_child(index, report)
+    }
+
+    /// The leaf mesh stays in place, it is still valid for the tile
+    fn try_prune_child(
+        &mut self,
+        index: usize,
+        report: &mut PruneReport,
+    ) -> bool {
+        let uniform = self.nodes[index].child.as_ref().unwrap().read().unwrap().get_uniform_value();
+        if let Some((value, active)) = uniform {
+            self.nodes[index].child = None;
+            self.nodes[index].tile = value;
+            self.child_mask.set_bit_off(index);
+            self.value_mask.set_bit(index, active);
+            report.leaves += 1;
+            report.bytes += LeafNode::<ValueType, L, B, T>::mem_size();
+            return true;
+        }
+
+        false
+    }
+}
+
+impl<ValueType: Clone + Copy + PartialEq + Eq + Serialize + for<'a> Deserialize<'a>, const L: usize, const B: usize, const T: usize> BranchNode<ValueType, L, B, T> {
+    pub fn to_file(
+        &self,
+    ) -> BranchFile<ValueType> {
+        let mut tiles: Vec<ValueType> = vec![];
+        let mut children: Vec<LeafFile<ValueType>> = vec![];
+
+        for (index, branch_data) in self.nodes.iter().enumerate() {
+            if self.child_mask.is_bit_on(index) {
+                children.push(branch_data.child.as_ref().unwrap().read().unwrap().to_file());
+            } else {
+                tiles.push(branch_data.tile);
+            }
+        }
+
+        BranchFile {
+            origin: self.origin,
+            child_mask: self.child_mask.clone(),
+            value_mask: self.value_mask.clone(),
+            tiles,
+            children,
+        }
+    }
+
+    /// Every allocated leaf and every active tile is marked for redraw
+    pub fn from_file(
+        file: BranchFile<ValueType>,
+        background: &ValueType,
+    ) -> bincode::Result<Self> {
+        check_file_mask(&file.child_mask, TreeDims::<L, B, T>::BRANCH_SIZE, "branch child_mask")?;
+        check_file_mask(&file.value_mask, TreeDims::<L, B, T>::BRANCH_SIZE, "branch value_mask")?;
+
+        let mut nodes: Vec<BranchData<ValueType, L, B, T>> = Vec::with_capacity(TreeDims::<L, B, T>::BRANCH_SIZE);
+        let mut tiles = file.tiles.into_iter();
+        let mut children = file.children.into_iter();
+
+        for index in 0..TreeDims::<L, B, T>::BRANCH_SIZE {
+            if file.child_mask.is_bit_on(index) {
+                let leaf_file = children.next().ok_or_else(|| vdt_file_error("branch is missing a leaf"))?;
+                let mut branch_data = BranchData::new_tile(background);
+                branch_data.child = Some(Arc::new(RwLock::new(LeafNode::from_file(leaf_file)?)));
+                nodes.push(branch_data);
+            } else {
+                let tile = tiles.next().ok_or_else(|| vdt_file_error("branch is missing a tile"))?;
+                nodes.push(BranchData::new_tile(&tile));
+            }
+        }
+
+        if tiles.next().is_some() || children.next().is_some() {
+            return Err(vdt_file_error("branch has more tiles or leaves than its child_mask allows"));
+        }
+
+        let mut redraw_mask = file.child_mask.clone();
+        redraw_mask.set_union(&file.value_mask);
+
+        Ok(Self {
+            origin: file.origin & TreeDims::<L, B, T>::BRANCH_ORIGIN_MASK,
+            nodes,
+            redraw_mask,
+            child_mask: file.child_mask,
+            value_mask: file.value_mask,
+        })
+    }
 }
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
@@ -300,14 +779,16 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> BranchNode<ValueType> {
 //================================-================================-================================ 
 // TrunkData
 #[derive(Clone)]
-struct TrunkData<ValueType: Clone + Copy + PartialEq + Eq> {
-    child: Option<Arc<RwLock<BranchNode<ValueType>>>>,
+struct TrunkData<ValueType: Clone + Copy + PartialEq + Eq, const L: usize, const B: usize, const T: usize> {
+    child: Option<Arc<RwLock<BranchNode<ValueType, L, B, T>>>>,
     tile: ValueType,
 
     mesh: Option<Entity>,
+    /// Meshes of a branch pruned into this tile, shown until the tile's own mesh replaces them
+    retired: Vec<Entity>,
 }
 
-impl<ValueType: Clone + Copy + PartialEq + Eq> TrunkData<ValueType> {
+impl<ValueType: Clone + Copy + PartialEq + Eq, const L: usize, const B: usize, const T: usize> TrunkData<ValueType, L, B, T> {
     pub fn new_tile(
         background: &ValueType,
     ) -> Self {
@@ -316,6 +797,7 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> TrunkData<ValueType> {
             tile: *background,
 
             mesh: None,
+            retired: vec![],
         }
     }
 }
@@ -323,14 +805,14 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> TrunkData<ValueType> {
 //================================-================================-================================ 
 // BranchData
 #[derive(Clone)]
-struct BranchData<ValueType: Clone + Copy + PartialEq + Eq> {
-    child: Option<Arc<RwLock<LeafNode<ValueType>>>>,
+struct BranchData<ValueType: Clone + Copy + PartialEq + Eq, const L: usize, const B: usize, const T: usize> {
+    child: Option<Arc<RwLock<LeafNode<ValueType, L, B, T>>>>,
     tile: ValueType,
 
     mesh: Option<Entity>,
 }
 
-impl<ValueType: Clone + Copy + PartialEq + Eq> BranchData<ValueType> {
+impl<ValueType: Clone + Copy + PartialEq + Eq, const L: usize, const B: usize, const T: usize> BranchData<ValueType, L, B, T> {
     pub fn new_tile(
         background: &ValueType,
     ) -> Self {
@@ -0,0 +1,354 @@
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// USE
+use crate::*;
+use super::*;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// ENUMS
+/// A node still to be walked, or a cube of one value still to be tested against the shape
+enum ActiveWork<ValueType: Clone + Copy + PartialEq + Eq, const L: usize, const B: usize, const T: usize> {
+    Trunk(Arc<RwLock<TrunkNode<ValueType, L, B, T>>>),
+    Branch(Arc<RwLock<BranchNode<ValueType, L, B, T>>>),
+    Leaf(Arc<RwLock<LeafNode<ValueType, L, B, T>>>),
+    Block { origin: IVec3, dim: usize, value: ValueType },
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// STRUCTS
+//================================-================================-================================
+// ActiveRegion
+/// Active voxels from `min` to `max` inclusive, all holding `value`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ActiveRegion<ValueType> {
+    pub min: IVec3,
+    pub max: IVec3,
+    pub value: ValueType,
+}
+
+impl<ValueType: Clone + Copy> ActiveRegion<ValueType> {
+    pub fn is_voxel(&self) -> bool { self.min == self.max }
+
+    /// Number of voxels covered
+    pub fn volume(
+        &self,
+    ) -> u64 {
+        let size = self.max - self.min + IVec3::ONE;
+        size.x as u64 * size.y as u64 * size.z as u64
+    }
+
+    /// Every covered coord, x then y then z
+    pub fn coords(
+        &self,
+    ) -> impl Iterator<Item = IVec3> {
+        let (min, max) = (self.min, self.max);
+        (min.x..=max.x).flat_map(move |x| (min.y..=max.y).flat_map(move |y| (min.z..=max.z).map(move |z| IVec3::new(x, y, z))))
+    }
+}
+
+//================================-================================-================================
+// ActiveRegionIter
+/// Walks the active values of a tree through its masks, tiles come out whole as one [ActiveRegion]
+///
+/// Tiles cut by the bounds are clipped, tiles cut by the shape are split into the largest cubes inside it.
+/// Only resident trunks of a streamed tree are walked, nothing is loaded.
+/// Nodes are read locked one at a time, so writes in between steps may or may not be seen.
+pub struct ActiveRegionIter<ValueType: Clone + Copy + PartialEq + Eq, const L: usize = { LEAF_LOG2DIM }, const B: usize = { BRANCH_LOG2DIM }, const T: usize = { TRUNK_LOG2DIM }> {
+    work: Vec<ActiveWork<ValueType, L, B, T>>,
+    bounds: Option<(IVec3, IVec3)>,
+    shape: Option<AreaShape>,
+}
+
+impl<ValueType: Clone + Copy + PartialEq + Eq, const L: usize, const B: usize, const T: usize> ActiveRegionIter<ValueType, L, B, T> {
+    /// Only yield voxels from `min` to `max` inclusive
+    pub fn with_bounds(
+        mut self,
+        min: &IVec3,
+        max: &IVec3,
+    ) -> Self {
+        self.bounds = Some((min.min(*max), min.max(*max)));
+        self
+    }
+
+    /// Only yield voxels inside `shape`
+    pub fn with_shape(
+        mut self,
+        shape: &AreaShape,
+    ) -> Self {
+        self.shape = Some(*shape);
+        self
+    }
+
+    /// Every active voxel on its own, tiles are expanded
+    pub fn voxels(
+        self,
+    ) -> impl Iterator<Item = (IVec3, ValueType)> {
+        self.flat_map(|region| region.coords().map(move |coord| (coord, region.value)))
+    }
+
+    /// False if none of the `dim` sized block at `origin` can be yielded
+    fn overlaps(
+        &self,
+        origin: &IVec3,
+        dim: usize,
+    ) -> bool {
+        let max = *origin + IVec3::splat(dim as i32 - 1);
+        if let Some((bounds_min, bounds_max)) = &self.bounds {
+            if origin.cmpgt(*bounds_max).any() || max.cmplt(*bounds_min).any() {
+                return false;
+            }
+        }
+
+        match &self.shape {
+            Some(shape) => shape.intersects_block(origin, dim, 0),
+            None => true,
+        }
+    }
+
+    fn push_block(
+        &mut self,
+        origin: &IVec3,
+        dim: usize,
+        value: &ValueType,
+    ) {
+        if self.overlaps(origin, dim) {
+            self.work.push(ActiveWork::Block { origin: *origin, dim, value: *value });
+        }
+    }
+
+    /// The block clipped to the bounds if all of it is inside the shape, otherwise its 8 octants are queued
+    fn take_block(
+        &mut self,
+        origin: &IVec3,
+        dim: usize,
+        value: &ValueType,
+    ) -> Option<ActiveRegion<ValueType>> {
+        if !self.overlaps(origin, dim) {
+            return None;
+        }
+
+        if dim > 1 && self.shape.as_ref().is_some_and(|shape| !shape.contains_block(origin, dim)) {
+            let half = dim >> 1;
+            for octant in (0..8).rev() {
+                let octant_origin = *origin + IVec3::new(octant & 1, (octant >> 1) & 1, (octant >> 2) & 1) * half as i32;
+                self.push_block(&octant_origin, half, value);
+            }
+
+            return None;
+        }
+
+        if dim == 1 && self.shape.as_ref().is_some_and(|shape| !shape.contains(origin)) {
+            return None;
+        }
+
+        let (mut min, mut max) = (*origin, *origin + IVec3::splat(dim as i32 - 1));
+        if let Some((bounds_min, bounds_max)) = &self.bounds {
+            min = min.max(*bounds_min);
+            max = max.min(*bounds_max);
+        }
+
+        Some(ActiveRegion { min, max, value: *value })
+    }
+}
+
+impl<ValueType: Clone + Copy + PartialEq + Eq, const L: usize, const B: usize, const T: usize> Iterator for ActiveRegionIter<ValueType, L, B, T> {
+    type Item = ActiveRegion<ValueType>;
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(work) = self.work.pop() {
+            match work {
+                ActiveWork::Trunk(trunk_node) => {
+                    let trunk_read_lock = trunk_node.read().unwrap();
+                    let mut children: Vec<ActiveWork<ValueType, L, B, T>> = vec![];
+                    for (origin, branch_node) in trunk_read_lock.iter_children() {
+                        if self.overlaps(&origin, TreeDims::<L, B, T>::BRANCH_DIM) {
+                            children.push(ActiveWork::Branch(branch_node.clone()));
+                        }
+                    }
+
+                    self.work.extend(children.into_iter().rev());
+                    let tiles: Vec<(IVec3, ValueType)> = trunk_read_lock.iter_active_tiles().collect();
+                    for (origin, value) in tiles.iter().rev() {
+                        self.push_block(origin, TreeDims::<L, B, T>::BRANCH_DIM, value);
+                    }
+                },
+                ActiveWork::Branch(branch_node) => {
+                    let branch_read_lock = branch_node.read().unwrap();
+                    let mut children: Vec<ActiveWork<ValueType, L, B, T>> = vec![];
+                    for (origin, leaf_node) in branch_read_lock.iter_children() {
+                        if self.overlaps(&origin, TreeDims::<L, B, T>::LEAF_DIM) {
+                            children.push(ActiveWork::Leaf(leaf_node.clone()));
+                        }
+                    }
+
+                    self.work.extend(children.into_iter().rev());
+                    let tiles: Vec<(IVec3, ValueType)> = branch_read_lock.iter_active_tiles().collect();
+                    for (origin, value) in tiles.iter().rev() {
+                        self.push_block(origin, TreeDims::<L, B, T>::LEAF_DIM, value);
+                    }
+                },
+                ActiveWork::Leaf(leaf_node) => {
+                    let voxels: Vec<(IVec3, ValueType)> = leaf_node.read().unwrap().iter_active().collect();
+                    for (coord, value) in voxels.iter().rev() {
+                        self.push_block(coord, 1, value);
+                    }
+                },
+                ActiveWork::Block { origin, dim, value } => {
+                    if let Some(region) = self.take_block(&origin, dim, &value) {
+                        return Some(region);
+                    }
+                },
+            }
+        }
+
+        None
+    }
+}
+
+//================================-================================-================================
+// RootNode
+impl<ValueType: Clone + Copy + PartialEq + Eq, const L: usize, const B: usize, const T: usize> RootNode<ValueType, L, B, T> {
+    /// Every active value, trunks in key order then tiles before children within each node
+    pub fn iter_active_regions(
+        &self,
+    ) -> ActiveRegionIter<ValueType, L, B, T> {
+        let mut work: Vec<(IVec3, ActiveWork<ValueType, L, B, T>)> = vec![];
+        for (key, value) in self.iter_active_tiles() {
+            work.push((key, ActiveWork::Block { origin: key, dim: TreeDims::<L, B, T>::TRUNK_DIM, value }));
+        }
+
+        for (key, trunk_node) in self.iter_children() {
+            work.push((key, ActiveWork::Trunk(trunk_node.clone())));
+        }
+
+        work.sort_by_key(|(key, _)| std::cmp::Reverse(key.to_array()));
+
+        ActiveRegionIter {
+            work: work.into_iter().map(|(_, work)| work).collect(),
+            bounds: None,
+            shape: None,
+        }
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestRoot = RootNode<u8, 2, 2, 2>;
+
+    /// A root tile, a branch tile, a leaf tile, a loose voxel & an inactive value
+    fn test_root() -> Arc<RwLock<TestRoot>> {
+        let root_node = Arc::new(RwLock::new(TestRoot::new(0)));
+        let mut values = Accessor::new(&root_node).with_remesh_mode(RemeshMode::Off);
+        values.set_tile(&IVec3::splat(-64), 64, &5, true);
+        values.set_tile(&IVec3::new(16, 0, 0), 16, &3, true);
+        values.set_tile(&IVec3::new(0, 4, 0), 4, &6, true);
+        values.set_value_on(&IVec3::new(1, 2, 3), &7);
+        values.set_value(&IVec3::new(2, 2, 3), &4, false);
+        root_node
+    }
+
+    fn sorted_regions(
+        regions: impl Iterator<Item = ActiveRegion<u8>>,
+    ) -> Vec<ActiveRegion<u8>> {
+        let mut regions: Vec<ActiveRegion<u8>> = regions.collect();
+        regions.sort_by_key(|region| region.min.to_array());
+        regions
+    }
+
+    #[test]
+    fn empty_tree_yields_nothing() {
+        let root_node = Arc::new(RwLock::new(TestRoot::new(0)));
+        assert!(root_node.read().unwrap().iter_active_regions().next().is_none());
+
+        // Inactive values are stored but never yielded
+        Accessor::new(&root_node).with_remesh_mode(RemeshMode::Off).set_value(&IVec3::ONE, &4, false);
+        assert!(root_node.read().unwrap().iter_active_regions().next().is_none());
+        assert!(root_node.read().unwrap().iter_active_regions().voxels().next().is_none());
+    }
+
+    #[test]
+    fn tiles_come_out_whole_and_leaves_as_voxels() {
+        let root_node = test_root();
+        let regions = sorted_regions(root_node.read().unwrap().iter_active_regions());
+        assert_eq!(regions, vec![
+            ActiveRegion { min: IVec3::splat(-64), max: IVec3::splat(-1), value: 5 },
+            ActiveRegion { min: IVec3::new(0, 4, 0), max: IVec3::new(3, 7, 3), value: 6 },
+            ActiveRegion { min: IVec3::new(1, 2, 3), max: IVec3::new(1, 2, 3), value: 7 },
+            ActiveRegion { min: IVec3::new(16, 0, 0), max: IVec3::new(31, 15, 15), value: 3 },
+        ]);
+        assert!(regions[2].is_voxel());
+        assert_eq!(regions[3].volume(), 16 * 16 * 16);
+    }
+
+    #[test]
+    fn regions_come_out_in_key_order_tiles_first() {
+        let root_node = test_root();
+        let regions: Vec<ActiveRegion<u8>> = root_node.read().unwrap().iter_active_regions().collect();
+        let mins: Vec<IVec3> = regions.iter().map(|region| region.min).collect();
+        assert_eq!(mins, vec![IVec3::splat(-64), IVec3::new(16, 0, 0), IVec3::new(0, 4, 0), IVec3::new(1, 2, 3)]);
+    }
+
+    #[test]
+    fn voxels_expand_every_region() {
+        let root_node = test_root();
+        let voxels: Vec<(IVec3, u8)> = root_node.read().unwrap().iter_active_regions().voxels().collect();
+        assert_eq!(voxels.len(), 64 * 64 * 64 + 16 * 16 * 16 + 4 * 4 * 4 + 1);
+        assert!(voxels.contains(&(IVec3::new(1, 2, 3), 7)));
+        assert!(voxels.contains(&(IVec3::new(31, 15, 15), 3)));
+        assert!(voxels.contains(&(IVec3::splat(-64), 5)));
+        assert!(!voxels.iter().any(|(coord, _)| *coord == IVec3::new(2, 2, 3)));
+
+        let leaf_voxels: Vec<(IVec3, u8)> = ActiveRegion { min: IVec3::ZERO, max: IVec3::new(0, 1, 1), value: 2 }.coords().map(|coord| (coord, 2)).collect();
+        assert_eq!(leaf_voxels, vec![(IVec3::ZERO, 2), (IVec3::Z, 2), (IVec3::Y, 2), (IVec3::new(0, 1, 1), 2)]);
+    }
+
+    #[test]
+    fn bounds_clip_tiles_and_skip_whats_outside() {
+        let root_node = test_root();
+        let expected = vec![
+            ActiveRegion { min: IVec3::splat(-2), max: IVec3::splat(-1), value: 5 },
+            ActiveRegion { min: IVec3::new(16, 0, 0), max: IVec3::new(17, 1, 1), value: 3 },
+        ];
+
+        let regions = sorted_regions(root_node.read().unwrap().iter_active_regions().with_bounds(&IVec3::splat(-2), &IVec3::new(17, 1, 1)));
+        assert_eq!(regions, expected);
+
+        // Corners may come in either order
+        let regions = sorted_regions(root_node.read().unwrap().iter_active_regions().with_bounds(&IVec3::new(17, 1, 1), &IVec3::splat(-2)));
+        assert_eq!(regions, expected);
+    }
+
+    #[test]
+    fn shapes_split_tiles_into_cubes_inside_them() {
+        let root_node = test_root();
+        let shape = AreaShape::Sphere { origin: IVec3::new(24, 8, 8), radius: 5 };
+        let regions: Vec<ActiveRegion<u8>> = root_node.read().unwrap().iter_active_regions().with_shape(&shape).collect();
+
+        for region in regions.iter() {
+            let size = region.max - region.min;
+            assert!(size.x == size.y && size.y == size.z);
+            assert!(shape.contains_block(&region.min, size.x as usize + 1));
+            assert_eq!(region.value, 3);
+        }
+        assert!(regions.iter().any(|region| !region.is_voxel()));
+
+        let mut voxels: Vec<IVec3> = regions.iter().flat_map(|region| region.coords()).collect();
+        voxels.sort_by_key(|coord| coord.to_array());
+        let mut expected: Vec<IVec3> = ActiveRegion { min: IVec3::new(16, 0, 0), max: IVec3::new(31, 15, 15), value: 3 }.coords().filter(|coord| shape.contains(coord)).collect();
+        expected.sort_by_key(|coord| coord.to_array());
+        assert_eq!(voxels, expected);
+    }
+
+    #[test]
+    fn bounds_and_shape_combine() {
+        let root_node = test_root();
+        let shape = AreaShape::Rect { start: IVec3::new(0, 0, 0), end: IVec3::new(3, 7, 3) };
+        let voxels: Vec<(IVec3, u8)> = root_node.read().unwrap().iter_active_regions().with_shape(&shape).with_bounds(&IVec3::new(0, 2, 0), &IVec3::new(3, 4, 3)).voxels().collect();
+        assert_eq!(voxels.len(), 1 + 4 * 4);
+        assert!(voxels.contains(&(IVec3::new(1, 2, 3), 7)));
+        assert!(voxels.iter().all(|(coord, value)| *value == 7 || (coord.y == 4 && *value == 6)));
+    }
+}
@@ -2,45 +2,66 @@
 // USE
 use crate::*;
 use super::*;
+use block_mesh::ndshape::ConstShape;
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // STRUCTS
-pub struct LeafNode<ValueType> {
+pub struct LeafNode<ValueType, const L: usize = { LEAF_LOG2DIM }, const B: usize = { BRANCH_LOG2DIM }, const T: usize = { TRUNK_LOG2DIM }> {
     origin: IVec3,
-    data: [ValueType; LEAF_SIZE],
+    data: Box<[ValueType]>,
     value_mask: Bitmask,
 }
 
 impl LeafNode<Voxel> {
-    pub fn get_mesh(
+    pub fn get_mesh_job(
         &self,
         defs: &Res<Defs>,
         accessor: &mut Accessor<Voxel>,
-    ) -> (IVec3, Option<Mesh>) {
-        let mut mesh_data = MeshData::default();
-
-        for index in OnMaskIter::new(0, &self.value_mask) {
-            mesh_data.add_cube_voxel(&self.origin, index, &self.data[index], defs, accessor);
+        mesh_mode: VoxelMeshMode,
+    ) -> VoxelMeshJob {
+        VoxelMeshJob::Leaf {
+            origin: self.origin,
+            voxels: self.get_padded_voxels(defs, accessor),
+            mode: mesh_mode,
         }
+    }
 
-        if mesh_data.is_empty() {
-            (self.origin, None)
-        } else {
-            (self.origin, Some(mesh_data.get_mesh()))
+    /// This leaf plus a one voxel border read through `accessor`, laid out as `PaddedLeafShape`
+    fn get_padded_voxels(
+        &self,
+        defs: &Res<Defs>,
+        accessor: &mut Accessor<Voxel>,
+    ) -> Vec<MeshVoxel> {
+        let mut voxels = vec![MeshVoxel::default(); PaddedLeafShape::SIZE as usize];
+
+        for (padded_index, voxel) in voxels.iter_mut().enumerate() {
+            let padded_coord = PaddedLeafShape::delinearize(padded_index as u32);
+            let local_coord = IVec3::new(padded_coord[0] as i32, padded_coord[1] as i32, padded_coord[2] as i32) - IVec3::ONE;
+
+            if local_coord.cmpge(IVec3::ZERO).all() && local_coord.cmplt(IVec3::splat(LEAF_DIM as i32)).all() {
+                let index = LeafNode::<Voxel>::index_from_coord(&local_coord);
+                *voxel = MeshVoxel::new(&self.data[index], self.value_mask.is_bit_on(index), defs);
+            } else {
+                *voxel = MeshVoxel::new_padding(&accessor.get_value(&(local_coord + self.origin)), defs);
+            }
         }
+
+        MeshVoxel::connect_face_textures(&mut voxels, defs);
+        MeshVoxel::bake_ambient_occlusion(&mut voxels);
+        voxels
     }
 }
 
-impl<ValueType: Clone + Copy + PartialEq + Eq> LeafNode<ValueType> {
+impl<ValueType: Clone + Copy + PartialEq + Eq, const L: usize, const B: usize, const T: usize> LeafNode<ValueType, L, B, T> {
     pub fn new(
         coord: &IVec3,
         background: &ValueType,
         active: bool,
     ) -> Self {
         Self {
-            origin: *coord & LEAF_ORIGIN_MASK,
-            data: [*background; LEAF_SIZE],
-            value_mask: Bitmask::from_cube_log2dim(LEAF_LOG2DIM, active),
+            origin: *coord & TreeDims::<L, B, T>::LEAF_ORIGIN_MASK,
+            data: vec![*background; TreeDims::<L, B, T>::LEAF_SIZE].into_boxed_slice(),
+            value_mask: Bitmask::from_cube_log2dim(L, active),
         }
     }
 
@@ -49,27 +70,54 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> LeafNode<ValueType> {
     pub fn index_from_coord(
         coord: &IVec3
     ) -> usize {
-        (((coord.x & (LEAF_DIM as i32 - 1)) << LEAF_LOG2DIM*2) +
-         ((coord.y & (LEAF_DIM as i32 - 1)) << LEAF_LOG2DIM  ) +
-          (coord.z & (LEAF_DIM as i32 - 1))) as usize
+        TreeDims::<L, B, T>::leaf_index_from_coord(coord)
     }
 
     pub fn local_coord_from_index(
         index: usize,
     ) -> IVec3 {
-        let x = (index >> (LEAF_LOG2DIM*2)) as i32;
-        let n = index & ((1 << LEAF_LOG2DIM*2) - 1);
-        let y = (n >> LEAF_LOG2DIM) as i32;
-        let z = (n & ((1 << LEAF_LOG2DIM) - 1)) as i32;
-        
-        IVec3::new(x, y, z)
+        TreeDims::<L, B, T>::leaf_offset_from_index(index)
     }
 
     pub fn global_coord_from_index(
         &self,
         index: usize,
     ) -> IVec3 {
-        LeafNode::<ValueType>::local_coord_from_index(index) + self.origin
+        Self::local_coord_from_index(index) + self.origin
+    }
+
+    /// Global coord & value of every active voxel, in index order
+    pub fn iter_active(
+        &self,
+    ) -> impl Iterator<Item = (IVec3, ValueType)> + '_ {
+        OnMaskIter::new(0, &self.value_mask).map(|index| (self.global_coord_from_index(index), self.data[index]))
+    }
+
+
+
+    /// Size of one leaf in memory
+    pub fn mem_size() -> usize {
+        std::mem::size_of::<Self>() + TreeDims::<L, B, T>::LEAF_SIZE * std::mem::size_of::<ValueType>() + TreeDims::<L, B, T>::LEAF_MASK_SIZE * std::mem::size_of::<usize>()
+    }
+
+    /// `Some` if every value & active state in this leaf is the same
+    pub fn get_uniform_value(
+        &self,
+    ) -> Option<(ValueType, bool)> {
+        let active = if self.value_mask.is_on() {
+                true
+            } else if self.value_mask.is_off() {
+                false
+            } else {
+                return None;
+            };
+
+        let value = self.data[0];
+        if self.data.iter().all(|other| *other == value) {
+            Some((value, active))
+        } else {
+            None
+        }
     }
 
 
@@ -85,7 +133,7 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> LeafNode<ValueType> {
         &self,
         coord: &IVec3,
     ) -> ValueType {
-        self.get_value_at_index(LeafNode::<ValueType>::index_from_coord(coord))
+        self.get_value_at_index(Self::index_from_coord(coord))
     }
 
 
@@ -106,6 +154,33 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> LeafNode<ValueType> {
         value: &ValueType,
         active: bool,
     ) {
-        self.set_value_at_index(LeafNode::<ValueType>::index_from_coord(coord), value, active);
+        self.set_value_at_index(Self::index_from_coord(coord), value, active);
+    }
+}
+
+impl<ValueType: Clone + Copy + PartialEq + Eq + Serialize + for<'a> Deserialize<'a>, const L: usize, const B: usize, const T: usize> LeafNode<ValueType, L, B, T> {
+    pub fn to_file(
+        &self,
+    ) -> LeafFile<ValueType> {
+        LeafFile {
+            origin: self.origin,
+            value_mask: self.value_mask.clone(),
+            data: self.data.to_vec(),
+        }
+    }
+
+    pub fn from_file(
+        file: LeafFile<ValueType>,
+    ) -> bincode::Result<Self> {
+        check_file_mask(&file.value_mask, TreeDims::<L, B, T>::LEAF_SIZE, "leaf value_mask")?;
+        if file.data.len() != TreeDims::<L, B, T>::LEAF_SIZE {
+            return Err(vdt_file_error(&format!("leaf has {} values but expected {}", file.data.len(), TreeDims::<L, B, T>::LEAF_SIZE)));
+        }
+
+        Ok(Self {
+            origin: file.origin & TreeDims::<L, B, T>::LEAF_ORIGIN_MASK,
+            data: file.data.into_boxed_slice(),
+            value_mask: file.value_mask,
+        })
     }
 }
\ No newline at end of file
@@ -2,9 +2,24 @@
 // USE
 use crate::*;
 use super::*;
-use bevy::render::{
-    render_resource::PrimitiveTopology,
-    mesh::Indices
+use std::result::Result; // AsBindGroup expects the std Result, not the io one glob imported above
+use bevy::{
+    ecs::system::EntityCommands,
+    reflect::TypeUuid,
+    render::{
+        render_resource::{AsBindGroup, PrimitiveTopology, ShaderRef},
+        mesh::Indices
+    },
+};
+use block_mesh::{
+    ndshape::{ConstShape, ConstShape3u32},
+    greedy_quads_with_merge_strategy,
+    AxisPermutation,
+    FaceStrides,
+    GreedyQuadsBuffer,
+    MergeStrategy,
+    OrientedBlockFace,
+    VoxelVisibility,
 };
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
@@ -12,6 +27,10 @@ use bevy::render::{
 pub const TEX_ATLAS_DIM: usize = 32;
 pub const TEX_ATLAS_UV_DIM: f32 = 1.0 / TEX_ATLAS_DIM as f32;
 pub const CUBE_HALF_DIM: f32 = 0.5;
+/// Opacity of `VoxelMaterials::liquid_material`
+pub const VOXEL_LIQUID_ALPHA: f32 = 0.7;
+/// Opacity of `VoxelMaterials::translucent_material`
+pub const VOXEL_TRANSLUCENT_ALPHA: f32 = 0.6;
 
 pub const CUBE_VERTS: [[f32; 3]; 8] = [
     [-CUBE_HALF_DIM, -CUBE_HALF_DIM, -CUBE_HALF_DIM], // 0 Left,  Bottom, Back
@@ -51,6 +70,90 @@ pub const CUBE_QUAD_VERTS: [[usize; 4]; 6] = [ // Read as: 0, 1, 2, 2, 1, 3
 
 pub const CUBE_QUAD_INDICES: [u32; 6] = [0, 2, 1, 1, 2, 3];
 
+/// Vertex colour of each ambient occlusion level, 0 is a corner closed in on all sides
+pub const AMBIENT_OCCLUSION_CURVE: [f32; 4] = [0.45, 0.65, 0.85, 1.0];
+/// Every vertex of a face fully open, see [MeshVoxel::ambient_occlusion]
+pub const AMBIENT_OCCLUSION_OPEN: u8 = 0xFF;
+
+/// Greedy quads store `atlas cell * stride + voxels across the quad` in their UVs, see `assets/shaders/voxel_atlas.wgsl`.
+/// Must stay larger than the widest quad, a root tile shell cell is `BRANCH_DIM` across
+pub const GREEDY_UV_CELL_STRIDE: f32 = 256.0;
+/// Leaves are meshed from a copy padded with one voxel of their neighbours on every side
+pub const PADDED_LEAF_DIM: u32 = LEAF_DIM as u32 + 2;
+pub type PaddedLeafShape = ConstShape3u32<PADDED_LEAF_DIM, PADDED_LEAF_DIM, PADDED_LEAF_DIM>;
+
+/// Neighbours in the plane of each face, as [ConnectedTextureMask] bits & in the face order of `CUBE_NORMALS`.
+/// These follow the atlas cell as both meshers lay it on the face
+pub const CONNECTED_TEXTURE_DIRECTIONS: [[IVec3; 4]; 6] = [
+    [IVec3::new( 0,  1,  0), IVec3::new( 0,  0,  1), IVec3::new( 0, -1,  0), IVec3::new( 0,  0, -1)], // Left face
+    [IVec3::new( 0,  1,  0), IVec3::new( 0,  0, -1), IVec3::new( 0, -1,  0), IVec3::new( 0,  0,  1)], // Right face
+    [IVec3::new( 0,  0, -1), IVec3::new(-1,  0,  0), IVec3::new( 0,  0,  1), IVec3::new( 1,  0,  0)], // Bottom face
+    [IVec3::new( 0,  0,  1), IVec3::new(-1,  0,  0), IVec3::new( 0,  0, -1), IVec3::new( 1,  0,  0)], // Top face
+    [IVec3::new( 0,  1,  0), IVec3::new(-1,  0,  0), IVec3::new( 0, -1,  0), IVec3::new( 1,  0,  0)], // Back face
+    [IVec3::new( 0,  1,  0), IVec3::new( 1,  0,  0), IVec3::new( 0, -1,  0), IVec3::new(-1,  0,  0)], // Front face
+];
+
+/// Same face order as `CUBE_NORMALS`
+pub const GREEDY_FACES: [OrientedBlockFace; 6] = [
+    OrientedBlockFace::new(-1, AxisPermutation::Xzy), // Left face
+    OrientedBlockFace::new( 1, AxisPermutation::Xzy), // Right face
+    OrientedBlockFace::new(-1, AxisPermutation::Yzx), // Bottom face
+    OrientedBlockFace::new( 1, AxisPermutation::Yzx), // Top face
+    OrientedBlockFace::new(-1, AxisPermutation::Zxy), // Back face
+    OrientedBlockFace::new( 1, AxisPermutation::Zxy), // Front face
+];
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// ENUMS
+/// Same matter neighbours a face joins, picks one of the `meta_texture_ids` of its [MatterDef]
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ConnectedTextureMask {
+    Up    = 0x1,
+    Right = 0x2,
+    Down  = 0x4,
+    Left  = 0x8,
+    /// Joined on every side
+    Center = 0xF,
+}
+
+/// Which sub-mesh & material a voxel is drawn with
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VoxelMeshLayer {
+    Opaque,
+    /// Matter without the `Opaque` property, see [MeshData::add_translucent_voxels]
+    Translucent,
+    /// See [MeshData::add_liquid_voxels]
+    Liquid,
+}
+
+impl VoxelMeshLayer {
+    pub fn new(
+        voxel: &Voxel,
+        defs: &Res<Defs>,
+    ) -> Self {
+        if voxel.is_liquid(defs) {
+            Self::Liquid
+        } else if voxel.is_opaque(defs) {
+            Self::Opaque
+        } else {
+            Self::Translucent
+        }
+    }
+}
+
+/// Which mesher `LeafNode<Voxel>::get_mesh` uses
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum VoxelMeshMode {
+    /// One quad per visible face, drawn with `VoxelMaterials::base_material`
+    PerFace,
+    /// Coplanar faces with the same texture merged into one quad, drawn with `VoxelMaterials::atlas_material`
+    /// 
+    /// Tile shells are always meshed like this. Only opaque voxels are meshed by either mode
+    #[default]
+    Greedy,
+}
+
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // STRUCTS
 pub struct MeshData {
@@ -58,6 +161,7 @@ pub struct MeshData {
     indices: Vec<u32>,
     uvs: Vec<[f32; 2]>,
     normals: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
 }
 
 impl Default for MeshData {
@@ -67,6 +171,7 @@ impl Default for MeshData {
             indices: vec![],
             uvs: vec![],
             normals: vec![],
+            colors: vec![],
         }
     }
 }
@@ -87,53 +192,752 @@ impl MeshData {
         mesh.set_indices(Some(Indices::U32(self.indices)));
         mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
         mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, self.colors);
 
         mesh
     }
 
-    pub fn add_cube_voxel(
+    /// Shade of a vertex from its [MeshVoxel::ambient_occlusion] level
+    fn push_ambient_occlusion(
         &mut self,
-        leaf_origin: &IVec3,
-        index: usize,
+        level: u8,
+    ) {
+        let shade = AMBIENT_OCCLUSION_CURVE[level as usize];
+        self.colors.push([shade, shade, shade, 1.0]);
+    }
+
+    pub fn add_cube_voxels(
+        &mut self,
+        voxels: &[MeshVoxel],
+    ) {
+        for index in 0..LEAF_SIZE {
+            let local_coord = LeafNode::<Voxel>::local_coord_from_index(index);
+            let padded_index = PaddedLeafShape::linearize((local_coord + IVec3::ONE).as_uvec3().to_array()) as usize;
+            let voxel = &voxels[padded_index];
+            if voxel.visibility != VoxelVisibility::Opaque {
+                continue;
+            }
+
+            for face in 0..6 {
+                let neighbor_coord = local_coord + IVec3::ONE + GRID_DIRECTIONS[face];
+                if voxels[PaddedLeafShape::linearize(neighbor_coord.as_uvec3().to_array()) as usize].visibility == VoxelVisibility::Opaque {
+                    continue;
+                }
+
+                let vert_count = self.verts.len();
+
+                for vert_index in 0..4 {
+                    let vert = CUBE_VERTS[CUBE_QUAD_VERTS[face][vert_index]];
+                    self.verts.push([
+                        vert[0] + local_coord.x as f32,
+                        vert[1] + local_coord.y as f32,
+                        vert[2] + local_coord.z as f32,
+                    ]);
+
+                    let uv = CUBE_UVS[vert_index];
+                    let uv_offset = TEX_ATLAS_UV_DIM * voxel.texture_ids[face] as f32;
+                    let uv_offset_floor = uv_offset.floor();
+                    self.uvs.push([
+                        uv[0] + uv_offset - uv_offset_floor,
+                        uv[1] + uv_offset_floor * TEX_ATLAS_UV_DIM,
+                    ]);
+                    self.push_ambient_occlusion(voxel.vertex_ambient_occlusion(face, vert_index));
+                }
+
+                self.normals.extend(vec![CUBE_NORMALS[face]; 4]);
+                self.indices.extend(CUBE_QUAD_INDICES.iter().map(|index| index + vert_count as u32));
+            }
+        }
+    }
+
+    pub fn add_greedy_quads(
+        &mut self,
+        voxels: &[MeshVoxel],
+        buffer: &mut GreedyQuadsBuffer,
+    ) {
+        // Translucent voxels get a mesh of their own, opaque faces behind them still need drawing
+        let voxels: Vec<MeshVoxel> = voxels.iter()
+            .map(|voxel| match voxel.visibility {
+                VoxelVisibility::Translucent => MeshVoxel { visibility: VoxelVisibility::Empty, ..*voxel },
+                _ => *voxel,
+            })
+            .collect();
+
+        greedy_quads_with_merge_strategy::<_, _, FaceTextureMerger>(
+            &voxels,
+            &PaddedLeafShape {},
+            [0; 3],
+            [PADDED_LEAF_DIM - 1; 3],
+            &GREEDY_FACES,
+            buffer,
+        );
+
+        for (face, quads) in buffer.quads.groups.iter().enumerate() {
+            let quad_verts = CUBE_QUAD_VERTS[face];
+            let u_dir = Vec3::from(CUBE_VERTS[quad_verts[0]]) - Vec3::from(CUBE_VERTS[quad_verts[1]]);
+            let v_dir = Vec3::from(CUBE_VERTS[quad_verts[0]]) - Vec3::from(CUBE_VERTS[quad_verts[2]]);
+
+            for quad in quads.iter() {
+                let voxel = &voxels[PaddedLeafShape::linearize(quad.minimum) as usize];
+                let texture_id = voxel.texture_ids[face];
+                let center = Vec3::from(quad.minimum.map(|axis| axis as f32)) - Vec3::ONE;
+                let cell = Vec2::new((texture_id % TEX_ATLAS_DIM as u32) as f32, (texture_id / TEX_ATLAS_DIM as u32) as f32);
+
+                // Padding is one voxel & voxel centers sit on integer coords
+                let corners = GREEDY_FACES[face].quad_corners(quad)
+                    .map(|corner| Vec3::from(corner.as_vec3().to_array()) - Vec3::splat(1.0 + CUBE_HALF_DIM));
+                let min_u = corners.iter().map(|corner| corner.dot(u_dir)).fold(f32::MAX, f32::min);
+                let min_v = corners.iter().map(|corner| corner.dot(v_dir)).fold(f32::MAX, f32::min);
+
+                let vert_count = self.verts.len() as u32;
+
+                for corner in corners.iter() {
+                    let local = Vec2::new(corner.dot(u_dir) - min_u, corner.dot(v_dir) - min_v);
+                    self.verts.push(corner.to_array());
+                    self.uvs.push((cell * GREEDY_UV_CELL_STRIDE + local).to_array());
+                    // Only faces with one level on every vertex get merged, so any corner of the first voxel will do
+                    let vert_index = quad_verts.iter()
+                        .position(|vert| Vec3::from(CUBE_VERTS[*vert]) == (*corner - center).clamp(Vec3::splat(-CUBE_HALF_DIM), Vec3::splat(CUBE_HALF_DIM)))
+                        .unwrap_or(0);
+                    self.push_ambient_occlusion(voxel.vertex_ambient_occlusion(face, vert_index));
+                }
+
+                self.normals.extend(vec![CUBE_NORMALS[face]; 4]);
+                self.indices.extend(GREEDY_FACES[face].quad_mesh_indices(vert_count));
+            }
+        }
+    }
+
+    /// Faces of translucent voxels, left out against opaque voxels & the same matter so a block of it reads as one volume
+    /// 
+    /// Blending doesn't write depth, so faces are drawn lowest first & bottom, side then top faces at each height.
+    /// That's back to front for the camera looking down on the level. UVs are laid out like [MeshData::add_greedy_quads],
+    /// drawn with `VoxelMaterials::translucent_material`
+    pub fn add_translucent_voxels(
+        &mut self,
+        voxels: &[MeshVoxel],
+    ) {
+        let mut faces: Vec<(IVec3, usize)> = vec![];
+        for index in 0..LEAF_SIZE {
+            let padded_coord = LeafNode::<Voxel>::local_coord_from_index(index) + IVec3::ONE;
+            let voxel = &voxels[PaddedLeafShape::linearize(padded_coord.as_uvec3().to_array()) as usize];
+            if voxel.visibility != VoxelVisibility::Translucent {
+                continue;
+            }
+
+            for face in 0..6 {
+                let neighbor = &voxels[PaddedLeafShape::linearize((padded_coord + GRID_DIRECTIONS[face]).as_uvec3().to_array()) as usize];
+                match neighbor.visibility {
+                    VoxelVisibility::Opaque => continue,
+                    VoxelVisibility::Translucent if neighbor.matter_id == voxel.matter_id => continue,
+                    _ => faces.push((padded_coord, face)),
+                }
+            }
+        }
+
+        // Half voxel steps, the top face of a voxel sits level with the bottom face of the one above
+        faces.sort_by_key(|(padded_coord, face)| {
+            let order = match *face {
+                    face if face == GridDirection::Bottom as usize => 0,
+                    face if face == GridDirection::Top as usize => 2,
+                    _ => 1,
+                };
+            (padded_coord.y * 2 + GRID_DIRECTIONS[*face].y, order)
+        });
+
+        for (padded_coord, face) in faces.into_iter() {
+            let voxel = &voxels[PaddedLeafShape::linearize(padded_coord.as_uvec3().to_array()) as usize];
+            let texture_id = voxel.texture_ids[face];
+            let cell = Vec2::new((texture_id % TEX_ATLAS_DIM as u32) as f32, (texture_id / TEX_ATLAS_DIM as u32) as f32);
+            let vert_count = self.verts.len();
+
+            for vert_index in 0..4 {
+                let vert = Vec3::from(CUBE_VERTS[CUBE_QUAD_VERTS[face][vert_index]]) + (padded_coord - IVec3::ONE).as_vec3();
+                self.verts.push(vert.to_array());
+
+                let local = (Vec2::from(CUBE_UVS[vert_index]) / TEX_ATLAS_UV_DIM).round();
+                self.uvs.push((cell * GREEDY_UV_CELL_STRIDE + local).to_array());
+                self.push_ambient_occlusion(voxel.vertex_ambient_occlusion(face, vert_index));
+            }
+
+            self.normals.extend(vec![CUBE_NORMALS[face]; 4]);
+            self.indices.extend(CUBE_QUAD_INDICES.iter().map(|index| index + vert_count as u32));
+        }
+    }
+
+    /// Liquid voxels as boxes as high as their fill level, faces against opaque voxels & fuller liquid are left out
+    /// 
+    /// UVs are laid out like [MeshData::add_greedy_quads], drawn with `VoxelMaterials::liquid_material`
+    pub fn add_liquid_voxels(
+        &mut self,
+        voxels: &[MeshVoxel],
+    ) {
+        for index in 0..LEAF_SIZE {
+            let padded_coord = LeafNode::<Voxel>::local_coord_from_index(index) + IVec3::ONE;
+            let voxel = &voxels[PaddedLeafShape::linearize(padded_coord.as_uvec3().to_array()) as usize];
+            if voxel.liquid_level == 0 {
+                continue;
+            }
+
+            let height = MeshVoxel::liquid_height(voxels, &padded_coord);
+            let cell = Vec2::new((voxel.texture_ids[3] % TEX_ATLAS_DIM as u32) as f32, (voxel.texture_ids[3] / TEX_ATLAS_DIM as u32) as f32);
+
+            for face in 0..6 {
+                let neighbor_coord = padded_coord + GRID_DIRECTIONS[face];
+                let neighbor = &voxels[PaddedLeafShape::linearize(neighbor_coord.as_uvec3().to_array()) as usize];
+                let is_side = face != 2 && face != 3;
+                // A surface below the top of the voxel shows even under an opaque ceiling
+                if neighbor.visibility == VoxelVisibility::Opaque && (is_side || face == 2 || height >= 1.0) {
+                    continue;
+                }
+
+                if neighbor.liquid_level > 0 && (!is_side || MeshVoxel::liquid_height(voxels, &neighbor_coord) >= height) {
+                    continue;
+                }
+
+                let vert_count = self.verts.len();
+
+                for vert_index in 0..4 {
+                    let vert = CUBE_VERTS[CUBE_QUAD_VERTS[face][vert_index]];
+                    let top = vert[1] > 0.0;
+                    self.verts.push([
+                        vert[0] + padded_coord.x as f32 - 1.0,
+                        if top { height - CUBE_HALF_DIM } else { -CUBE_HALF_DIM } + padded_coord.y as f32 - 1.0,
+                        vert[2] + padded_coord.z as f32 - 1.0,
+                    ]);
+
+                    let mut local = (Vec2::from(CUBE_UVS[vert_index]) / TEX_ATLAS_UV_DIM).round();
+                    if is_side && top {
+                        local.y = 1.0 - height;
+                    }
+                    self.uvs.push((cell * GREEDY_UV_CELL_STRIDE + local).to_array());
+                    self.push_ambient_occlusion(3);
+                }
+
+                self.normals.extend(vec![CUBE_NORMALS[face]; 4]);
+                self.indices.extend(CUBE_QUAD_INDICES.iter().map(|index| index + vert_count as u32));
+            }
+        }
+    }
+
+    /// Box shell around a uniform tile, split into `cell_dim` sized quads so each can be culled against its neighbour
+    /// 
+    /// `hidden` holds one flag per face cell, see [VoxelMeshJob::new_tile]
+    pub fn add_tile_shell(
+        &mut self,
+        tile_dim: usize,
+        cell_dim: usize,
+        texture_ids: &[u32; 6],
+        hidden: &[bool],
+    ) {
+        let cells = (tile_dim / cell_dim) as i32;
+
+        for face in 0..6 {
+            let axis = face / 2;
+            let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+            let texture_id = texture_ids[face];
+            let cell = Vec2::new((texture_id % TEX_ATLAS_DIM as u32) as f32, (texture_id / TEX_ATLAS_DIM as u32) as f32);
+
+            for u in 0..cells {
+                for v in 0..cells {
+                    if hidden[(face * cells as usize + u as usize) * cells as usize + v as usize] {
+                        continue;
+                    }
+
+                    let mut cell_min = IVec3::ZERO;
+                    cell_min[u_axis] = u * cell_dim as i32;
+                    cell_min[v_axis] = v * cell_dim as i32;
+
+                    let mut cell_size = Vec3::splat(cell_dim as f32);
+                    cell_size[axis] = tile_dim as f32;
+
+                    let vert_count = self.verts.len();
+
+                    for vert_index in 0..4 {
+                        let vert = Vec3::from(CUBE_VERTS[CUBE_QUAD_VERTS[face][vert_index]]);
+                        let vert = cell_min.as_vec3() + (vert + CUBE_HALF_DIM) * cell_size - CUBE_HALF_DIM;
+                        self.verts.push(vert.to_array());
+
+                        let local = (Vec2::from(CUBE_UVS[vert_index]) / TEX_ATLAS_UV_DIM).round() * cell_dim as f32;
+                        self.uvs.push((cell * GREEDY_UV_CELL_STRIDE + local).to_array());
+                        self.push_ambient_occlusion(3);
+                    }
+
+                    self.normals.extend(vec![CUBE_NORMALS[face]; 4]);
+                    self.indices.extend(CUBE_QUAD_INDICES.iter().map(|index| index + vert_count as u32));
+                }
+            }
+        }
+    }
+}
+
+/// Everything needed to mesh one leaf or tile, copied out of the tree so it can be built off the main thread
+pub enum VoxelMeshJob {
+    Leaf {
+        origin: IVec3,
+        /// Laid out as `PaddedLeafShape`
+        voxels: Vec<MeshVoxel>,
+        mode: VoxelMeshMode,
+    },
+    Tile {
+        origin: IVec3,
+        dim: usize,
+        cell_dim: usize,
+        texture_ids: [u32; 6],
+        hidden: Vec<bool>,
+        layer: VoxelMeshLayer,
+        solid: bool,
+    },
+    /// Inactive tile, any old mesh just gets despawned
+    Empty {
+        origin: IVec3,
+    },
+}
+
+impl VoxelMeshJob {
+    /// Looks up which cells of the shell are covered by opaque neighbours, or the same matter for translucent tiles
+    pub fn new_tile(
+        tile_origin: &IVec3,
+        tile_dim: usize,
+        cell_dim: usize,
         voxel: &Voxel,
+        active: bool,
         defs: &Res<Defs>,
         accessor: &mut Accessor<Voxel>,
-    ) {
-        let local_coord = LeafNode::<Voxel>::local_coord_from_index(index);
-        let global_coord = local_coord + *leaf_origin;
+    ) -> Self {
+        if !active {
+            return Self::Empty { origin: *tile_origin };
+        }
+
+        let layer = VoxelMeshLayer::new(voxel, defs);
+        let cells = (tile_dim / cell_dim) as i32;
+        let mut hidden = Vec::with_capacity(6 * (cells * cells) as usize);
 
-        let neighbors = accessor.get_adjacent_values(&global_coord);
-    
         for face in 0..6 {
-            if neighbors[face as usize].is_opaque(defs) {
+            let axis = face / 2;
+            let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+
+            for u in 0..cells {
+                for v in 0..cells {
+                    // Any coord inside the adjacent block works, the whole block has to be opaque to hide this cell
+                    let mut neighbor_coord = *tile_origin;
+                    neighbor_coord[u_axis] += u * cell_dim as i32;
+                    neighbor_coord[v_axis] += v * cell_dim as i32;
+                    neighbor_coord[axis] = if face % 2 == 1 { tile_origin[axis] + tile_dim as i32 } else { tile_origin[axis] - 1 };
+
+                    let neighbor = accessor.get_block_value(&neighbor_coord, cell_dim);
+                    hidden.push(neighbor.is_some_and(|neighbor| {
+                        neighbor.is_opaque(defs) || (layer == VoxelMeshLayer::Translucent && neighbor.matter_id() == voxel.matter_id())
+                    }));
+                }
+            }
+        }
+
+        Self::Tile {
+            origin: *tile_origin,
+            dim: tile_dim,
+            cell_dim,
+            // Tiles only join themselves, their shell is drawn as the inside of a connected surface
+            texture_ids: [0, 1, 2, 3, 4, 5].map(|face| defs.matter.get_u8(voxel.matter_id()).connected_texture_id(face, ConnectedTextureMask::Center as u8)),
+            hidden,
+            layer,
+            solid: voxel.is_solid(defs),
+        }
+    }
+
+    pub fn key(
+        &self,
+    ) -> IVec3 {
+        match self {
+            Self::Leaf { origin, .. } | Self::Tile { origin, .. } | Self::Empty { origin } => *origin,
+        }
+    }
+
+    /// The slow part, safe to run on any thread
+    pub fn build(
+        self,
+    ) -> VoxelMeshResult {
+        let key = self.key();
+        let mut mesh_data = MeshData::default();
+        let mut translucent_mesh_data = MeshData::default();
+        let mut liquid_mesh_data = MeshData::default();
+
+        let mut collider = None;
+
+        let mode = match self {
+            Self::Leaf { voxels, mode, .. } => {
+                collider = build_leaf_collider(&voxels);
+                match mode {
+                    VoxelMeshMode::PerFace => mesh_data.add_cube_voxels(&voxels),
+                    VoxelMeshMode::Greedy => mesh_data.add_greedy_quads(&voxels, &mut GreedyQuadsBuffer::new(voxels.len())),
+                }
+                translucent_mesh_data.add_translucent_voxels(&voxels);
+                liquid_mesh_data.add_liquid_voxels(&voxels);
+                mode
+            },
+            Self::Tile { dim, cell_dim, texture_ids, hidden, layer, solid, .. } => {
+                if solid {
+                    collider = Some(build_tile_collider(dim));
+                }
+
+                let layer_mesh_data = match layer {
+                        VoxelMeshLayer::Opaque => &mut mesh_data,
+                        VoxelMeshLayer::Translucent => &mut translucent_mesh_data,
+                        VoxelMeshLayer::Liquid => &mut liquid_mesh_data,
+                    };
+                layer_mesh_data.add_tile_shell(dim, cell_dim, &texture_ids, &hidden);
+                VoxelMeshMode::Greedy
+            },
+            Self::Empty { .. } => VoxelMeshMode::Greedy,
+        };
+
+        VoxelMeshResult {
+            key,
+            mesh: if mesh_data.is_empty() { None } else { Some((mesh_data.get_mesh(), mode)) },
+            collider,
+            translucent_mesh: if translucent_mesh_data.is_empty() { None } else { Some(translucent_mesh_data.get_mesh()) },
+            liquid_mesh: if liquid_mesh_data.is_empty() { None } else { Some(liquid_mesh_data.get_mesh()) },
+        }
+    }
+}
+
+pub struct VoxelMeshResult {
+    pub key: IVec3,
+    pub mesh: Option<(Mesh, VoxelMeshMode)>,
+    /// Solid matter only, see [build_leaf_collider]
+    pub collider: Option<Collider>,
+    pub translucent_mesh: Option<Mesh>,
+    pub liquid_mesh: Option<Mesh>,
+}
+
+impl VoxelMeshResult {
+    /// Nothing to spawn, any old mesh just gets despawned
+    pub fn is_empty(
+        &self,
+    ) -> bool {
+        self.mesh.is_none() && self.collider.is_none() && self.translucent_mesh.is_none() && self.liquid_mesh.is_none()
+    }
+}
+
+/// One cell of the padded buffer fed to the meshers
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct MeshVoxel {
+    pub visibility: VoxelVisibility,
+    pub matter_id: u8,
+    pub texture_ids: [u32; 6],
+    /// Per face, 2 bits per vertex in `CUBE_QUAD_VERTS` order, see [MeshVoxel::bake_ambient_occlusion]
+    pub ambient_occlusion: [u8; 6],
+    /// Fill level of liquids, 0 for anything else, see [Voxel::liquid_level]
+    pub liquid_level: u8,
+    /// Gets a collider, left unset in the padding
+    pub solid: bool,
+}
+
+impl Default for MeshVoxel {
+    fn default() -> Self {
+        Self {
+            visibility: VoxelVisibility::Empty,
+            matter_id: 0,
+            texture_ids: [0; 6],
+            ambient_occlusion: [AMBIENT_OCCLUSION_OPEN; 6],
+            liquid_level: 0,
+            solid: false,
+        }
+    }
+}
+
+impl block_mesh::Voxel for MeshVoxel {
+    fn get_visibility(&self) -> VoxelVisibility {
+        self.visibility
+    }
+}
+
+impl MeshVoxel {
+    pub fn new(
+        voxel: &Voxel,
+        active: bool,
+        defs: &Res<Defs>,
+    ) -> Self {
+        if !active {
+            return Self::default();
+        }
+
+        // Liquids are left out of the block meshers, see [MeshData::add_liquid_voxels]
+        if voxel.is_liquid(defs) {
+            return Self {
+                matter_id: voxel.matter_id(),
+                texture_ids: [0, 1, 2, 3, 4, 5].map(|face| voxel.face_texture_id(face, defs)),
+                liquid_level: voxel.liquid_level(),
+                ..default()
+            };
+        }
+
+        Self {
+            visibility: if voxel.is_opaque(defs) { VoxelVisibility::Opaque } else { VoxelVisibility::Translucent },
+            matter_id: voxel.matter_id(),
+            texture_ids: [0, 1, 2, 3, 4, 5].map(|face| voxel.face_texture_id(face, defs)),
+            ambient_occlusion: [AMBIENT_OCCLUSION_OPEN; 6],
+            liquid_level: 0,
+            solid: voxel.is_solid(defs),
+        }
+    }
+
+    /// Cells outside the leaf only cull faces & join textures, so liquids & void are left empty
+    pub fn new_padding(
+        voxel: &Voxel,
+        defs: &Res<Defs>,
+    ) -> Self {
+        let visibility = if voxel.is_opaque(defs) {
+                VoxelVisibility::Opaque
+            } else if voxel.is_liquid(defs) || defs.matter.get_u8(voxel.matter_id()).is_void() {
+                VoxelVisibility::Empty
+            } else {
+                VoxelVisibility::Translucent
+            };
+
+        Self {
+            visibility,
+            matter_id: voxel.matter_id(),
+            texture_ids: [0; 6],
+            ambient_occlusion: [AMBIENT_OCCLUSION_OPEN; 6],
+            liquid_level: if voxel.is_liquid(defs) { voxel.liquid_level() } else { 0 },
+            solid: false,
+        }
+    }
+
+    /// Swaps the face textures of leaf voxels for the connected ones their [MatterDef] declares
+    /// 
+    /// A face joins a neighbour in its plane of the same matter, unless that neighbour's face is covered
+    pub fn connect_face_textures(
+        voxels: &mut [MeshVoxel],
+        defs: &Res<Defs>,
+    ) {
+        for index in 0..LEAF_SIZE {
+            let padded_coord = LeafNode::<Voxel>::local_coord_from_index(index) + IVec3::ONE;
+            let padded_index = PaddedLeafShape::linearize(padded_coord.as_uvec3().to_array()) as usize;
+            let matter_id = voxels[padded_index].matter_id;
+            let matter = defs.matter.get_u8(matter_id);
+            if matter_id == 0 || !matter.has_connected_textures() {
                 continue;
             }
 
-            let vert_count = self.verts.len();
-    
-            for vert_index in 0..4 {
-                let vert = CUBE_VERTS[CUBE_QUAD_VERTS[face as usize][vert_index]];
-                self.verts.push([
-                    vert[0] + local_coord.x as f32,
-                    vert[1] + local_coord.y as f32,
-                    vert[2] + local_coord.z as f32,
-                ]);
-    
-                let uv = CUBE_UVS[vert_index];
-                let uv_offset = TEX_ATLAS_UV_DIM * voxel.face_texture_id(face, defs) as f32;
-                let uv_offset_floor = uv_offset.floor() as f32;
-                self.uvs.push([
-                    uv[0] + uv_offset - uv_offset_floor,
-                    uv[1] + uv_offset_floor * TEX_ATLAS_UV_DIM as f32,
-                ]);
+            for face in 0..6 {
+                let mut mask = 0;
+                for (bit, offset) in CONNECTED_TEXTURE_DIRECTIONS[face].iter().enumerate() {
+                    let neighbor_coord = padded_coord + *offset;
+                    let neighbor = &voxels[PaddedLeafShape::linearize(neighbor_coord.as_uvec3().to_array()) as usize];
+                    let cover = &voxels[PaddedLeafShape::linearize((neighbor_coord + GRID_DIRECTIONS[face]).as_uvec3().to_array()) as usize];
+                    if neighbor.matter_id == matter_id && cover.visibility != VoxelVisibility::Opaque {
+                        mask |= 1 << bit;
+                    }
+                }
+
+                voxels[padded_index].texture_ids[face] = matter.connected_texture_id(face as u8, mask);
+            }
+        }
+    }
+
+    /// Darkens the vertices of leaf voxel faces by the opaque voxels around them, 3 is open & 0 closed in
+    /// 
+    /// A vertex is closed in when both voxels beside it in front of the face are opaque, otherwise each opaque voxel
+    /// beside or diagonal to it takes a level off
+    pub fn bake_ambient_occlusion(
+        voxels: &mut [MeshVoxel],
+    ) {
+        let is_opaque = |voxels: &[MeshVoxel], coord: IVec3| {
+            voxels[PaddedLeafShape::linearize(coord.as_uvec3().to_array()) as usize].visibility == VoxelVisibility::Opaque
+        };
+
+        for index in 0..LEAF_SIZE {
+            let padded_coord = LeafNode::<Voxel>::local_coord_from_index(index) + IVec3::ONE;
+            let padded_index = PaddedLeafShape::linearize(padded_coord.as_uvec3().to_array()) as usize;
+            if voxels[padded_index].visibility == VoxelVisibility::Empty {
+                continue;
+            }
+
+            for face in 0..6 {
+                let axis = face / 2;
+                let front = padded_coord + GRID_DIRECTIONS[face];
+                let mut packed = 0;
+                for (vert_index, vert) in CUBE_QUAD_VERTS[face].iter().enumerate() {
+                    let offset = (Vec3::from(CUBE_VERTS[*vert]) * 2.0).as_ivec3();
+                    let (mut u, mut v) = (IVec3::ZERO, IVec3::ZERO);
+                    u[(axis + 1) % 3] = offset[(axis + 1) % 3];
+                    v[(axis + 2) % 3] = offset[(axis + 2) % 3];
+
+                    let side_u = is_opaque(voxels, front + u);
+                    let side_v = is_opaque(voxels, front + v);
+                    let level = if side_u && side_v {
+                            0
+                        } else {
+                            3 - side_u as u8 - side_v as u8 - is_opaque(voxels, front + u + v) as u8
+                        };
+                    packed |= level << (vert_index * 2);
+                }
+
+                voxels[padded_index].ambient_occlusion[face] = packed;
+            }
+        }
+    }
+
+    /// Level of a vertex of `face`, `vert_index` into `CUBE_QUAD_VERTS`
+    pub fn vertex_ambient_occlusion(
+        &self,
+        face: usize,
+        vert_index: usize,
+    ) -> u8 {
+        (self.ambient_occlusion[face] >> (vert_index * 2)) & 0x3
+    }
+
+    /// True if every vertex of `face` has the same level, only these can be merged into larger quads
+    fn has_even_ambient_occlusion(
+        &self,
+        face: usize,
+    ) -> bool {
+        self.ambient_occlusion[face] == (self.ambient_occlusion[face] & 0x3) * 0x55
+    }
+
+    /// Height of the liquid surface in the padded cell at `padded_coord`, liquid on top fills it to the brim
+    fn liquid_height(
+        voxels: &[MeshVoxel],
+        padded_coord: &IVec3,
+    ) -> f32 {
+        let above = &voxels[PaddedLeafShape::linearize((*padded_coord + IVec3::Y).as_uvec3().to_array()) as usize];
+        if above.liquid_level > 0 {
+            return 1.0;
+        }
+
+        voxels[PaddedLeafShape::linearize(padded_coord.as_uvec3().to_array()) as usize].liquid_level as f32 / VOXEL_LIQUID_LEVELS as f32
+    }
+
+    fn needs_face(
+        voxels: &[MeshVoxel],
+        visited: &[bool],
+        index: usize,
+        visibility_offset: u32,
+    ) -> bool {
+        let voxel = &voxels[index];
+        if voxel.visibility == VoxelVisibility::Empty || visited[index] {
+            return false;
+        }
+
+        match voxels[(index as u32).wrapping_add(visibility_offset) as usize].visibility {
+            VoxelVisibility::Empty => true,
+            VoxelVisibility::Translucent => voxel.visibility == VoxelVisibility::Opaque,
+            VoxelVisibility::Opaque => false,
+        }
+    }
+}
+
+/// Merges faces that share a `face_texture_id` & an even ambient occlusion level for the face being meshed, unlike
+/// `block_mesh::VoxelMerger` which needs every face to match
+pub struct FaceTextureMerger;
+
+impl FaceTextureMerger {
+    fn face_from_strides(
+        face_strides: &FaceStrides,
+    ) -> usize {
+        let axis = if face_strides.n_stride == PaddedLeafShape::linearize([1, 0, 0]) {
+                0
+            } else if face_strides.n_stride == PaddedLeafShape::linearize([0, 1, 0]) {
+                1
+            } else {
+                2
+            };
+
+        axis * 2 + (face_strides.visibility_offset == face_strides.n_stride) as usize
+    }
+
+    fn get_row_width(
+        voxels: &[MeshVoxel],
+        visited: &[bool],
+        face: usize,
+        start: &MeshVoxel,
+        face_strides: &FaceStrides,
+        start_stride: u32,
+        max_width: u32,
+    ) -> u32 {
+        let mut width = 0;
+        let mut stride = start_stride;
+
+        while width < max_width {
+            if !MeshVoxel::needs_face(voxels, visited, stride as usize, face_strides.visibility_offset)
+            || voxels[stride as usize].texture_ids[face] != start.texture_ids[face]
+            || voxels[stride as usize].ambient_occlusion[face] != start.ambient_occlusion[face] {
+                break;
             }
-    
-            self.normals.extend(vec![CUBE_NORMALS[face as usize]; 4]);
-    
-            for tri_index in 0..6 {
-                self.indices.push(CUBE_QUAD_INDICES[tri_index] + vert_count as u32);
+
+            width += 1;
+            stride += face_strides.u_stride;
+        }
+
+        width
+    }
+}
+
+impl MergeStrategy for FaceTextureMerger {
+    type Voxel = MeshVoxel;
+
+    unsafe fn find_quad(
+        min_index: u32,
+        max_width: u32,
+        max_height: u32,
+        face_strides: &FaceStrides,
+        voxels: &[MeshVoxel],
+        visited: &[bool],
+    ) -> (u32, u32) {
+        let face = Self::face_from_strides(face_strides);
+        let start = &voxels[min_index as usize];
+        if !start.has_even_ambient_occlusion(face) {
+            return (1, 1);
+        }
+
+        let width = Self::get_row_width(
+            voxels, visited, face, start, face_strides,
+            min_index, max_width,
+        );
+
+        let mut height = 1;
+        let mut row_stride = min_index + face_strides.v_stride;
+        while height < max_height {
+            let row_width = Self::get_row_width(
+                voxels, visited, face, start, face_strides,
+                row_stride, width,
+            );
+            if row_width < width {
+                break;
             }
+
+            height += 1;
+            row_stride += face_strides.v_stride;
         }
+
+        (width, height)
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// MATERIALS
+/// Samples `tex_atlas` per voxel across greedy quads, see `GREEDY_UV_CELL_STRIDE`
+#[derive(AsBindGroup, TypeUuid, Clone)]
+#[uuid = "6c1f4f7e-2b0d-4d8e-9a55-3b8e4f2d7a10"]
+pub struct VoxelAtlasMaterial {
+    #[texture(0)]
+    #[sampler(1)]
+    pub atlas: Handle<Image>,
+    /// Multiplies the atlas, alpha below 1 is blended when `alpha_mode` allows it
+    #[uniform(2)]
+    pub tint: Color,
+    pub alpha_mode: AlphaMode,
+}
+
+impl Material for VoxelAtlasMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/voxel_atlas.wgsl".into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
     }
 }
 
@@ -142,11 +946,18 @@ impl MeshData {
 #[derive(Component)]
 pub struct VoxelMaterials {
     pub base_material: Handle<StandardMaterial>,
+    pub atlas_material: Handle<VoxelAtlasMaterial>,
+    /// Blended over opaque meshes, see [MeshData::add_translucent_voxels]
+    pub translucent_material: Handle<VoxelAtlasMaterial>,
+    /// Drawn over everything else, see [MeshData::add_liquid_voxels]
+    pub liquid_material: Handle<VoxelAtlasMaterial>,
+    pub mesh_mode: VoxelMeshMode,
 }
 
 impl VoxelMaterials {
     pub fn new_world_materials(
         materials: &mut ResMut<Assets<StandardMaterial>>,
+        atlas_materials: &mut ResMut<Assets<VoxelAtlasMaterial>>,
         asset_loader: &Res<AssetLoader>,
     ) -> Self {
         Self {
@@ -157,6 +968,97 @@ impl VoxelMaterials {
                     reflectance: 0.0,
                     ..default()
                 }),
+            atlas_material: atlas_materials.add(VoxelAtlasMaterial {
+                    atlas: asset_loader.images.get_handle("tex_atlas"),
+                    tint: Color::WHITE,
+                    alpha_mode: AlphaMode::Opaque,
+                }),
+            translucent_material: atlas_materials.add(VoxelAtlasMaterial {
+                    atlas: asset_loader.images.get_handle("tex_atlas"),
+                    tint: Color::rgba(1.0, 1.0, 1.0, VOXEL_TRANSLUCENT_ALPHA),
+                    alpha_mode: AlphaMode::Blend,
+                }),
+            liquid_material: atlas_materials.add(VoxelAtlasMaterial {
+                    atlas: asset_loader.images.get_handle("tex_atlas"),
+                    tint: Color::rgba(1.0, 1.0, 1.0, VOXEL_LIQUID_ALPHA),
+                    alpha_mode: AlphaMode::Blend,
+                }),
+            mesh_mode: VoxelMeshMode::default(),
         }
     }
-}
\ No newline at end of file
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// FUNCTIONS
+/// Translucent & liquid meshes are spawned as children, under an empty parent if there's nothing opaque to draw.
+/// The collider goes on the parent
+pub fn spawn_voxel_mesh(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    voxel_materials: &VoxelMaterials,
+    result: VoxelMeshResult,
+) -> Entity {
+    let transform = Transform::from_translation(result.key.as_vec3());
+    let name = Name::new("Mesh ".to_string() + &result.key.to_string());
+    let mut mesh_commands = match result.mesh {
+        None => commands.spawn((SpatialBundle::from_transform(transform), name)),
+        Some((mesh, mesh_mode)) => {
+            let mesh_handle = meshes.add(mesh);
+            let mut mesh_commands = match mesh_mode {
+                VoxelMeshMode::PerFace => commands.spawn(PbrBundle {
+                        mesh: mesh_handle,
+                        material: voxel_materials.base_material.clone(),
+                        transform,
+                        global_transform: GlobalTransform::from(transform),
+                        ..default()
+                    }),
+                VoxelMeshMode::Greedy => commands.spawn(MaterialMeshBundle {
+                        mesh: mesh_handle,
+                        material: voxel_materials.atlas_material.clone(),
+                        transform,
+                        global_transform: GlobalTransform::from(transform),
+                        ..default()
+                    }),
+            };
+
+            mesh_commands.insert(name);
+            mesh_commands
+        },
+    };
+
+    if let Some(collider) = result.collider {
+        mesh_commands
+            .insert(RigidBody::Fixed)
+            .insert(collider);
+    }
+
+    if let Some(translucent_mesh) = result.translucent_mesh {
+        spawn_blended_mesh(&mut mesh_commands, meshes, &voxel_materials.translucent_material, translucent_mesh, "Translucent Mesh");
+    }
+
+    if let Some(liquid_mesh) = result.liquid_mesh {
+        spawn_blended_mesh(&mut mesh_commands, meshes, &voxel_materials.liquid_material, liquid_mesh, "Liquid Mesh");
+    }
+
+    mesh_commands.id()
+}
+
+fn spawn_blended_mesh(
+    parent_commands: &mut EntityCommands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    material: &Handle<VoxelAtlasMaterial>,
+    mesh: Mesh,
+    name: &str,
+) {
+    let mesh_handle = meshes.add(mesh);
+    parent_commands.with_children(|parent| {
+        parent.spawn((
+            MaterialMeshBundle {
+                mesh: mesh_handle,
+                material: material.clone(),
+                ..default()
+            },
+            Name::new(name.to_string()),
+        ));
+    });
+}
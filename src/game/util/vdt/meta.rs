@@ -0,0 +1,108 @@
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// USE
+use crate::*;
+use super::*;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// CONSTANTS
+/// Appended to a [VoxelTree] save path for the file holding its [VoxelMeta]
+pub const VOXEL_META_FILE_SUFFIX: &str = ".meta";
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// STRUCTS
+//================================-================================-================================
+// VoxelMeta
+/// Small per-voxel record kept beside a [VoxelTree], the default record means there is none
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct VoxelMeta {
+    /// Durability lost so far
+    pub damage: u16,
+    /// Stage of anything that grows
+    pub growth: u8,
+    /// Faction or player the voxel belongs to, 0 for none
+    pub owner: u32,
+}
+
+impl VoxelMeta {
+    pub fn is_empty(&self) -> bool { *self == Self::default() }
+}
+
+//================================-================================-================================
+// VoxelMetaLink
+/// Keeps [VoxelMeta] records in step with writes through an [Accessor] into the voxel tree
+///
+/// A record is dropped once its voxel changes matter, state changes keep it
+pub struct VoxelMetaLink {
+    root: Arc<RwLock<RootNode<VoxelMeta>>>,
+}
+
+impl VoxelMetaLink {
+    pub fn new(
+        root: &Arc<RwLock<RootNode<VoxelMeta>>>,
+    ) -> Self {
+        Self {
+            root: root.clone(),
+        }
+    }
+}
+
+impl LinkedTable<Voxel> for VoxelMetaLink {
+    fn clears(
+        &self,
+        old: &Voxel,
+        new: &Voxel,
+    ) -> bool {
+        old.matter_id() != new.matter_id()
+    }
+
+    fn clear_block(
+        &self,
+        coord: &IVec3,
+        dim: usize,
+    ) {
+        let mut root_write_lock = self.root.write().unwrap();
+        if dim == 1 {
+            if !root_write_lock.get_value_and_cache(coord).0.is_empty() {
+                root_write_lock.set_value_and_cache(coord, &VoxelMeta::default(), false);
+            }
+
+            return;
+        }
+
+        let block_origin = *coord & !(dim as i32 - 1);
+        let block_max = block_origin + IVec3::splat(dim as i32 - 1);
+        if root_write_lock.iter_active_regions().with_bounds(&block_origin, &block_max).next().is_some() {
+            root_write_lock.set_tile(&block_origin, dim, &VoxelMeta::default(), false);
+        }
+    }
+
+    fn move_record(
+        &self,
+        from: &IVec3,
+        to: &IVec3,
+    ) {
+        let mut root_write_lock = self.root.write().unwrap();
+        let meta = root_write_lock.get_value_and_cache(from).0;
+        if meta.is_empty() {
+            return;
+        }
+
+        root_write_lock.set_value_and_cache(to, &meta, true);
+        root_write_lock.set_value_and_cache(from, &VoxelMeta::default(), false);
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// FUNCTIONS
+pub fn meta_file_path(
+    path: &str,
+) -> String {
+    format!("{}{}", path, VOXEL_META_FILE_SUFFIX)
+}
+
+/// The records saved next to the tree at `path`, none if that tree was saved without them
+pub fn try_load_meta(
+    path: &str,
+) -> RootNode<VoxelMeta> {
+    try_load_root_node(&meta_file_path(path)).unwrap_or_else(|| RootNode::new(VoxelMeta::default()))
+}
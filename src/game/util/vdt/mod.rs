@@ -1,6 +1,12 @@
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // USE
 use crate::*;
+use std::collections::VecDeque;
+use bevy::{
+    tasks::{AsyncComputeTaskPool, Task},
+    utils::HashMap,
+};
+use futures_lite::future;
 
 mod root;
 pub use root::*;
@@ -14,32 +20,63 @@ pub use voxel::*;
 
 mod accessor;
 pub use accessor::*;
+mod file;
+pub use file::*;
 mod mesh;
 pub use mesh::*;
+mod collider;
+pub use collider::*;
+mod raycast;
+pub use raycast::*;
+mod region;
+pub use region::*;
+mod history;
+pub use history::*;
+mod stream;
+pub use stream::*;
+mod dims;
+pub use dims::*;
+mod iter;
+pub use iter::*;
+mod meta;
+pub use meta::*;
+mod damage;
+pub use damage::*;
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // CONSTANTS
-const LEAF_LOG2DIM: usize = 3;
-const BRANCH_LOG2DIM: usize = 4;
-const TRUNK_LOG2DIM: usize = 5;
-
-const LEAF_TOTAL: usize =     LEAF_LOG2DIM;
-const LEAF_DIM: usize =       1 << LEAF_TOTAL;
-const LEAF_SIZE: usize =      1 << (LEAF_LOG2DIM*3);
-const LEAF_MASK_SIZE: usize = LEAF_SIZE >> 6;
-const LEAF_ORIGIN_MASK: i32 = !(LEAF_DIM as i32 - 1);
-
-const BRANCH_TOTAL: usize =     BRANCH_LOG2DIM + LEAF_TOTAL;
-const BRANCH_DIM: usize =       1 << BRANCH_TOTAL;
-const BRANCH_SIZE: usize =      1 << (BRANCH_LOG2DIM*3);
-const BRANCH_MASK_SIZE: usize = BRANCH_SIZE >> 6;
-const BRANCH_ORIGIN_MASK: i32 = !(BRANCH_DIM as i32 - 1);
-
-const TRUNK_TOTAL: usize =     TRUNK_LOG2DIM + BRANCH_TOTAL;
-const TRUNK_DIM: usize =       1 << TRUNK_TOTAL;
-const TRUNK_SIZE: usize =      1 << (TRUNK_LOG2DIM*3);
-const TRUNK_MASK_SIZE: usize = TRUNK_SIZE >> 6;
-const TRUNK_ORIGIN_MASK: i32 = !(TRUNK_DIM as i32 - 1);
+/// Default layout, used by level trees
+/// 
+/// Trunks are what [VoxelStreamer]s page in & out, at 512 voxels across a [VOXEL_STREAM_RADIUS] keeps the few around each focus resident
+pub const LEAF_LOG2DIM: usize = 3;
+pub const BRANCH_LOG2DIM: usize = 4;
+pub const TRUNK_LOG2DIM: usize = 2;
+
+/// Layout of entity trees, units are sparse so leaves are kept small
+pub const ENTITY_LEAF_LOG2DIM: usize = 2;
+pub const ENTITY_BRANCH_LOG2DIM: usize = 4;
+pub const ENTITY_TRUNK_LOG2DIM: usize = 5;
+
+type DefaultDims = TreeDims<LEAF_LOG2DIM, BRANCH_LOG2DIM, TRUNK_LOG2DIM>;
+
+/// Nodes & accessors of [EntityTree]s
+pub type EntityRootNode = RootNode<Option<Entity>, ENTITY_LEAF_LOG2DIM, ENTITY_BRANCH_LOG2DIM, ENTITY_TRUNK_LOG2DIM>;
+pub type EntityAccessor = Accessor<Option<Entity>, ENTITY_LEAF_LOG2DIM, ENTITY_BRANCH_LOG2DIM, ENTITY_TRUNK_LOG2DIM>;
+
+const LEAF_TOTAL: usize =       DefaultDims::LEAF_TOTAL;
+const LEAF_DIM: usize =         DefaultDims::LEAF_DIM;
+const LEAF_SIZE: usize =        DefaultDims::LEAF_SIZE;
+
+const BRANCH_DIM: usize =       DefaultDims::BRANCH_DIM;
+
+const TRUNK_TOTAL: usize =      DefaultDims::TRUNK_TOTAL;
+const TRUNK_DIM: usize =        DefaultDims::TRUNK_DIM;
+const TRUNK_ORIGIN_MASK: i32 =  DefaultDims::TRUNK_ORIGIN_MASK;
+
+/// Default for [VoxelRemeshQueue::swap_budget]
+pub const VOXEL_REMESH_SWAP_BUDGET: usize = 64;
+/// Mesh jobs handed to each background task
+const VOXEL_REMESH_JOBS_PER_TASK: usize = 16;
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // PLUGIN
@@ -49,8 +86,16 @@ impl Plugin for VdtPlugin {
         &self,
         app: &mut App,
     ) {
-        app.add_system(sys_remesh_voxel_trees.in_schedule(OnEnter(AppState::MainMenu)))
-            .add_system(sys_remesh_voxel_trees); // .run_if(on_timer(Duration::from_secs_f32(0.1))));
+        app.add_plugin(MaterialPlugin::<VoxelAtlasMaterial>::default())
+            .add_system(sys_queue_voxel_remesh.in_schedule(OnEnter(AppState::MainMenu)))
+            .add_event::<VoxelChangedEvent>()
+            .add_systems((
+                    sys_stream_voxel_trunks,
+                    evsys_remesh_on_defs_reloaded,
+                    sys_queue_voxel_remesh,
+                    sys_apply_voxel_remesh,
+                ).chain())
+            .add_system(sys_publish_voxel_changes.in_base_set(CoreSet::Last));
     }
 }
 
@@ -99,6 +144,7 @@ pub struct LevelTreeBundle {
     name: Name,
     voxel_tree: VoxelTree,
     entity_tree: EntityTree,
+    history: VoxelHistory,
     level_tree: LevelTree,
     materials: VoxelMaterials,
     transform: Transform,
@@ -114,6 +160,7 @@ impl LevelTreeBundle {
             name: Name::new("Level Tree"),
             voxel_tree: VoxelTree::from_root_node(&voxel_root),
             entity_tree: EntityTree::new(),
+            history: VoxelHistory::default(),
             level_tree: LevelTree::default(),
             materials,
             transform: Transform::IDENTITY,
@@ -128,6 +175,7 @@ impl LevelTreeBundle {
             name: Name::new("Level Tree"),
             voxel_tree: tree,
             entity_tree: EntityTree::new(),
+            history: VoxelHistory::default(),
             level_tree: LevelTree::default(),
             materials,
             transform: Transform::IDENTITY,
@@ -135,6 +183,104 @@ impl LevelTreeBundle {
     }
 }
 
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// STRUCTS
+/// Mesh jobs running on the `AsyncComputeTaskPool` & finished meshes waiting to be swapped in
+pub struct VoxelRemeshQueue {
+    tasks: Vec<Task<Vec<(u64, VoxelMeshResult)>>>,
+    ready: VecDeque<(u64, VoxelMeshResult)>,
+    /// Newest job per key, older results for the same key are dropped
+    latest: HashMap<IVec3, u64>,
+    next_id: u64,
+    /// Most mesh entities swapped in per frame
+    pub swap_budget: usize,
+}
+
+impl Default for VoxelRemeshQueue {
+    fn default() -> Self {
+        Self {
+            tasks: vec![],
+            ready: VecDeque::new(),
+            latest: HashMap::default(),
+            next_id: 0,
+            swap_budget: VOXEL_REMESH_SWAP_BUDGET,
+        }
+    }
+}
+
+impl VoxelRemeshQueue {
+    pub fn is_empty(
+        &self,
+    ) -> bool {
+        self.tasks.is_empty() && self.ready.is_empty()
+    }
+
+    pub fn push_jobs(
+        &mut self,
+        jobs: Vec<VoxelMeshJob>,
+    ) {
+        let task_pool = AsyncComputeTaskPool::get();
+        let mut jobs = jobs.into_iter().peekable();
+
+        while jobs.peek().is_some() {
+            let mut task_jobs: Vec<(u64, VoxelMeshJob)> = Vec::with_capacity(VOXEL_REMESH_JOBS_PER_TASK);
+            for job in jobs.by_ref().take(VOXEL_REMESH_JOBS_PER_TASK) {
+                self.latest.insert(job.key(), self.next_id);
+                task_jobs.push((self.next_id, job));
+                self.next_id += 1;
+            }
+
+            self.tasks.push(task_pool.spawn(async move {
+                task_jobs.into_iter().map(|(id, job)| (id, job.build())).collect()
+            }));
+        }
+    }
+
+    pub fn poll_tasks(
+        &mut self,
+    ) {
+        let mut ready: Vec<(u64, VoxelMeshResult)> = vec![];
+        self.tasks.retain_mut(|task| {
+            if let Some(results) = future::block_on(future::poll_once(task)) {
+                ready.extend(results);
+                false
+            } else {
+                true
+            }
+        });
+
+        // Tasks finish out of order, apply in the order the jobs were made
+        ready.sort_by_key(|(id, _)| *id);
+        self.ready.extend(ready);
+    }
+
+    /// Drops every job still running or waiting to be swapped in, their tasks are cancelled
+    pub fn clear(
+        &mut self,
+    ) {
+        self.tasks.clear();
+        self.ready.clear();
+        self.latest.clear();
+    }
+
+    /// Next result that is still current, or `None` once nothing is ready
+    pub fn pop_ready(
+        &mut self,
+    ) -> Option<VoxelMeshResult> {
+        while let Some((id, result)) = self.ready.pop_front() {
+            match self.latest.get(&result.key) {
+                Some(latest_id) if *latest_id > id => continue,
+                Some(latest_id) if *latest_id == id => { self.latest.remove(&result.key); },
+                _ => {},
+            }
+
+            return Some(result);
+        }
+
+        None
+    }
+}
+
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // COMPONENTS
 //================================-================================-================================ 
@@ -147,29 +293,133 @@ pub struct LevelTree;
 #[derive(Component)]
 pub struct VoxelTree {
     root: Arc<RwLock<RootNode<Voxel>>>,
+    pub remesh_queue: VoxelRemeshQueue,
+    /// Filled by every accessor from [VoxelTree::get_accessor], drained into [VoxelChangedEvent] each tick
+    journal: ValueJournal<Voxel>,
+    /// Sparse [VoxelMeta] records, these are never streamed & stay resident
+    meta: Arc<RwLock<RootNode<VoxelMeta>>>,
+    meta_link: ValueLink<Voxel>,
 }
 
 impl VoxelTree {
     pub fn new(
         background: Voxel,
     ) -> Self {
-        Self {
-            root: Arc::new(RwLock::new(RootNode::new(background))),
-        }
+        Self::from_root_node(&Arc::new(RwLock::new(RootNode::new(background))))
     }
 
     pub fn from_root_node(
         root_node: &Arc<RwLock<RootNode<Voxel>>>,
     ) -> Self {
+        let meta = Arc::new(RwLock::new(RootNode::new(VoxelMeta::default())));
         Self {
             root: root_node.clone(),
+            remesh_queue: VoxelRemeshQueue::default(),
+            journal: ValueJournal::default(),
+            meta_link: Arc::new(VoxelMetaLink::new(&meta)),
+            meta,
         }
     }
     
+    /// Writes clear the [VoxelMeta] of voxels that change matter, use [Accessor::move_value] to carry it along
     pub fn get_accessor(
         &self,
     ) -> Accessor<Voxel> {
-        Accessor::new(&self.root)
+        Accessor::new(&self.root).with_journal(&self.journal).with_link(&self.meta_link)
+    }
+
+    pub fn get_meta_accessor(
+        &self,
+    ) -> Accessor<VoxelMeta> {
+        Accessor::new(&self.meta).with_remesh_mode(RemeshMode::Off)
+    }
+
+    /// False while the trunk holding `coord` is streamed out, reads there only see the background
+    pub fn is_resident(
+        &self,
+        coord: &IVec3,
+    ) -> bool {
+        let root_read_lock = self.root.read().unwrap();
+        root_read_lock.stream().is_none() || root_read_lock.is_resident(&(*coord & TRUNK_ORIGIN_MASK))
+    }
+
+    /// See [RootNode::iter_active_regions]
+    pub fn iter_active_regions(
+        &self,
+    ) -> ActiveRegionIter<Voxel> {
+        self.root.read().unwrap().iter_active_regions()
+    }
+
+    /// Every change written since the last call, in write order
+    pub fn drain_changes(
+        &self,
+    ) -> Vec<VoxelChange> {
+        std::mem::take(&mut *self.journal.lock().unwrap())
+    }
+
+    /// Queues every leaf & tile for remeshing next update, old meshes stay until their replacements spawn
+    pub fn remesh_all(
+        &self,
+    ) {
+        self.root.write().unwrap().set_redraw_all();
+    }
+
+    /// Folds uniform nodes back into tiles, their meshes are swapped out on the next remesh
+    pub fn prune(
+        &self,
+    ) -> PruneReport {
+        let report = self.root.write().unwrap().prune();
+        self.meta.write().unwrap().prune();
+
+        info!("Pruned voxel tree: {} leaves, {} branches, {} trunks, {} bytes reclaimed", report.leaves, report.branches, report.trunks, report.bytes);
+        report
+    }
+
+    /// [VoxelMeta] is saved next to `path`, see [meta_file_path]
+    pub fn try_save(
+        &self,
+        path: &str,
+    ) -> bool {
+        try_save_root_node(&self.root, path) && try_save_root_node(&self.meta, &meta_file_path(path))
+    }
+
+    pub fn try_load(
+        path: &str,
+    ) -> Option<Self> {
+        let root_node = try_load_root_node(path)?;
+        let tree = Self::from_root_node(&Arc::new(RwLock::new(root_node)));
+        *tree.meta.write().unwrap() = try_load_meta(path);
+        Some(tree)
+    }
+
+    /// Swaps in the tree saved at `path` and despawns the old meshes, everything is remeshed next update
+    /// 
+    /// Refused for streamed trees, their saves only hold resident trunks & the rest would come from the old world's files
+    pub fn try_load_in_place(
+        &mut self,
+        path: &str,
+        commands: &mut Commands,
+    ) -> bool {
+        if self.root.read().unwrap().stream().is_some() {
+            warn!("can't load {} in place of a streamed tree", path);
+            return false;
+        }
+
+        if let Some(root_node) = try_load_root_node(path) {
+            let mut root_write_lock = self.root.write().unwrap();
+            for mesh_entity in root_write_lock.get_mesh_entities() {
+                commands.entity(mesh_entity).despawn_recursive();
+            }
+
+            // Meshes still being built are of the old tree, they would be attached to the new nodes by key
+            self.remesh_queue.clear();
+            root_write_lock.replace(root_node);
+            self.meta.write().unwrap().replace(try_load_meta(path));
+            self.journal.lock().unwrap().clear();
+            return true;
+        }
+
+        false
     }
 }
 
@@ -177,7 +427,7 @@ impl VoxelTree {
 // EntityTree
 #[derive(Component)]
 pub struct EntityTree {
-    root: Arc<RwLock<RootNode<Option<Entity>>>>,
+    root: Arc<RwLock<EntityRootNode>>,
 }
 
 impl EntityTree {
@@ -188,7 +438,7 @@ impl EntityTree {
     }
 
     pub fn from_root_node(
-        root: &Arc<RwLock<RootNode<Option<Entity>>>>,
+        root: &Arc<RwLock<EntityRootNode>>,
     ) -> Self {
         Self {
             root: root.clone(),
@@ -197,22 +447,175 @@ impl EntityTree {
     
     pub fn get_accessor(
         &self,
-    ) -> Accessor<Option<Entity>> {
-        Accessor::new(&self.root)
+    ) -> EntityAccessor {
+        Accessor::new(&self.root).with_remesh_mode(RemeshMode::Off)
+    }
+
+    /// See [RootNode::iter_active_regions]
+    pub fn iter_active_regions(
+        &self,
+    ) -> ActiveRegionIter<Option<Entity>, ENTITY_LEAF_LOG2DIM, ENTITY_BRANCH_LOG2DIM, ENTITY_TRUNK_LOG2DIM> {
+        self.root.read().unwrap().iter_active_regions()
+    }
+
+    pub fn prune(
+        &self,
+    ) -> PruneReport {
+        self.root.write().unwrap().prune()
+    }
+
+    /// Entities are saved as raw ids, they only mean something if the same entities are restored with them
+    pub fn try_save(
+        &self,
+        path: &str,
+    ) -> bool {
+        try_save_root_node(&self.root, path)
+    }
+
+    pub fn try_load(
+        path: &str,
+    ) -> Option<Self> {
+        let root_node = try_load_root_node(path)?;
+        Some(Self::from_root_node(&Arc::new(RwLock::new(root_node))))
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// EVENTS
+pub type VoxelChange = ValueChange<Voxel>;
+
+/// Every voxel written in `tree` during the last tick, in write order
+pub struct VoxelChangedEvent {
+    pub tree: Entity,
+    pub changes: Vec<VoxelChange>,
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// EVENT SYSTEMS
+/// Textures, opacity, liquids & solidity all come from matter defs, so every mesh & collider is rebuilt
+fn evsys_remesh_on_defs_reloaded(
+    mut defs_reloaded_events: EventReader<DefsReloaded>,
+    tree_query: Query<&VoxelTree>,
+) {
+    if !defs_reloaded_events.iter().any(|event| event.has_changed("matter")) {
+        return;
+    }
+
+    for tree in tree_query.iter() {
+        tree.remesh_all();
     }
 }
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // SYSTEMS
-fn sys_remesh_voxel_trees(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
+/// Snapshots dirty leaves & tiles and hands them to the `AsyncComputeTaskPool`
+fn sys_queue_voxel_remesh(
     mut tree_query: Query<(&mut VoxelTree, &VoxelMaterials)>,
     defs: Res<Defs>,
 ) {
-    for (tree, materials) in tree_query.iter_mut() {
+    for (mut tree, materials) in tree_query.iter_mut() {
         let mut voxels = Accessor::new(&tree.root);
-        let key_mesh_entity_pairs = tree.root.read().unwrap().get_meshes(&mut commands, &mut meshes, &mut voxels, &materials, &defs);
+        let jobs = tree.root.read().unwrap().get_mesh_jobs(&mut voxels, materials.mesh_mode, &defs);
+        if jobs.is_empty() {
+            continue;
+        }
+
+        tree.root.write().unwrap().clear_redraw();
+        tree.remesh_queue.push_jobs(jobs);
+    }
+}
+
+/// Old meshes are despawned in the same frame their replacements spawn, so nothing flickers
+fn sys_apply_voxel_remesh(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut tree_query: Query<(&mut VoxelTree, &VoxelMaterials)>,
+) {
+    for (mut tree, materials) in tree_query.iter_mut() {
+        if tree.remesh_queue.is_empty() {
+            continue;
+        }
+
+        tree.remesh_queue.poll_tasks();
+
+        let mut key_mesh_entity_pairs: Vec<(IVec3, Option<Entity>)> = vec![];
+        while key_mesh_entity_pairs.len() < tree.remesh_queue.swap_budget {
+            if let Some(result) = tree.remesh_queue.pop_ready() {
+                let key = result.key;
+                let mesh_entity = if result.is_empty() {
+                        None
+                    } else {
+                        Some(spawn_voxel_mesh(&mut commands, &mut meshes, materials, result))
+                    };
+                key_mesh_entity_pairs.push((key, mesh_entity));
+            } else {
+                break;
+            }
+        }
+
         tree.root.write().unwrap().assign_meshes(&key_mesh_entity_pairs, &mut commands);
     }
-}
\ No newline at end of file
+}
+
+fn sys_publish_voxel_changes(
+    mut voxel_changed_events: EventWriter<VoxelChangedEvent>,
+    tree_query: Query<(Entity, &VoxelTree)>,
+) {
+    for (tree_entity, tree) in tree_query.iter() {
+        let changes = tree.drain_changes();
+        if !changes.is_empty() {
+            voxel_changed_events.send(VoxelChangedEvent { tree: tree_entity, changes });
+        }
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::CommandQueue;
+
+    fn temp_path(
+        name: &str,
+    ) -> String {
+        std::env::temp_dir().join(name).to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn load_in_place_restores_the_saved_tree() {
+        let world = World::new();
+        let mut command_queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut command_queue, &world);
+        let path = temp_path("vdt_load_in_place.vdt");
+
+        let mut tree = VoxelTree::new(Voxel::default());
+        tree.get_accessor().set_value_on(&IVec3::ONE, &Voxel::from_matter_id(1));
+        assert!(tree.try_save(&path));
+
+        tree.get_accessor().set_value_on(&IVec3::ONE, &Voxel::from_matter_id(2));
+        tree.get_accessor().set_value_on(&IVec3::NEG_ONE, &Voxel::from_matter_id(2));
+        assert!(tree.try_load_in_place(&path, &mut commands));
+
+        let voxels: Vec<(IVec3, Voxel)> = tree.iter_active_regions().voxels().collect();
+        assert_eq!(voxels, vec![(IVec3::ONE, Voxel::from_matter_id(1))]);
+        assert!(tree.drain_changes().is_empty());
+        assert!(tree.remesh_queue.is_empty());
+    }
+
+    #[test]
+    fn load_in_place_refuses_streamed_trees() {
+        let world = World::new();
+        let mut command_queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut command_queue, &world);
+        let path = temp_path("vdt_load_in_place_streamed.vdt");
+
+        let mut tree = VoxelTree::new(Voxel::default());
+        tree.get_accessor().set_value_on(&IVec3::ONE, &Voxel::from_matter_id(1));
+        assert!(tree.try_save(&path));
+
+        let _streamer = VoxelStreamer::new(&tree, &temp_path("vdt_load_in_place_trunks"), None);
+        assert!(!tree.try_load_in_place(&path, &mut commands));
+    }
+}
@@ -0,0 +1,271 @@
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// USE
+use crate::*;
+use super::*;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// STRUCTS
+//================================-================================-================================
+// VoxelRayHit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoxelRayHit {
+    pub coord: IVec3,
+    /// Face the ray entered through, zero if the ray started inside the voxel
+    pub normal: IVec3,
+    pub distance: f32,
+    pub voxel: Voxel,
+    pub occupant: Option<Entity>,
+}
+
+//================================-================================-================================
+// VoxelRay
+/// 3D DDA over the voxel grid, voxels are centered on integer coords
+struct VoxelRay {
+    /// Shifted by half a voxel so voxel bounds land on integers
+    origin: Vec3,
+    direction: Vec3,
+    step: IVec3,
+    t_delta: Vec3,
+    t_max: Vec3,
+
+    coord: IVec3,
+    normal: IVec3,
+    distance: f32,
+}
+
+impl VoxelRay {
+    fn new(
+        origin: Vec3,
+        direction: Vec3,
+    ) -> Self {
+        let origin = origin + Vec3::splat(0.5);
+        let mut ray = Self {
+            origin,
+            direction,
+            step: IVec3::ZERO,
+            t_delta: Vec3::splat(f32::INFINITY),
+            t_max: Vec3::splat(f32::INFINITY),
+
+            coord: origin.floor().as_ivec3(),
+            normal: IVec3::ZERO,
+            distance: 0.0,
+        };
+
+        for axis in 0..3 {
+            if direction[axis] != 0.0 {
+                ray.step[axis] = direction[axis].signum() as i32;
+                ray.t_delta[axis] = 1.0 / direction[axis].abs();
+            }
+        }
+
+        ray.reset_t_max();
+        ray
+    }
+
+    /// Distance along the ray to the next voxel boundary on each axis
+    fn reset_t_max(
+        &mut self,
+    ) {
+        for axis in 0..3 {
+            if self.step[axis] != 0 {
+                let boundary = self.coord[axis] + (self.step[axis] > 0) as i32;
+                self.t_max[axis] = (boundary as f32 - self.origin[axis]) / self.direction[axis];
+            }
+        }
+    }
+
+    fn enter(
+        &mut self,
+        axis: usize,
+        distance: f32,
+    ) {
+        self.distance = distance;
+        self.normal = IVec3::ZERO;
+        self.normal[axis] = -self.step[axis];
+    }
+
+    /// Into the neighbouring voxel the ray crosses next
+    fn step(
+        &mut self,
+    ) {
+        let axis = min_axis(&self.t_max);
+        self.enter(axis, self.t_max[axis]);
+        self.coord[axis] += self.step[axis];
+        self.t_max[axis] += self.t_delta[axis];
+    }
+
+    /// Out of the `dim` sized block around the current voxel in one go
+    fn skip_block(
+        &mut self,
+        dim: usize,
+    ) {
+        let block_min = self.coord & !(dim as i32 - 1);
+        let block_max = block_min + IVec3::splat(dim as i32 - 1);
+
+        let mut t_exit = Vec3::splat(f32::INFINITY);
+        for axis in 0..3 {
+            if self.step[axis] != 0 {
+                let edge = if self.step[axis] > 0 { block_max[axis] } else { block_min[axis] };
+                t_exit[axis] = self.t_max[axis] + (edge - self.coord[axis]).abs() as f32 * self.t_delta[axis];
+            }
+        }
+
+        let axis = min_axis(&t_exit);
+        let exit_coord = (self.origin + self.direction * t_exit[axis]).floor().as_ivec3().clamp(block_min, block_max);
+        self.enter(axis, t_exit[axis]);
+        self.coord = exit_coord;
+        self.coord[axis] = if self.step[axis] > 0 { block_max[axis] + 1 } else { block_min[axis] - 1 };
+        self.reset_t_max();
+    }
+}
+
+//================================-================================-================================
+// Accessor
+impl<const L: usize, const B: usize, const T: usize> Accessor<Voxel, L, B, T> {
+    /// Walks the voxels along a ray until one passes `filter` or is occupied in `entities`
+    ///
+    /// Tiles are skipped whole, so rays through empty trunks & branches stay cheap, `entities` may use its own layout
+    pub fn raycast<const EL: usize, const EB: usize, const ET: usize>(
+        &mut self,
+        entities: &mut Accessor<Option<Entity>, EL, EB, ET>,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+        filter: impl Fn(&Voxel) -> bool,
+    ) -> Option<VoxelRayHit> {
+        let mut ray = VoxelRay::new(origin, direction.try_normalize()?);
+        while ray.distance <= max_distance {
+            let (voxel, voxel_dim) = self.get_value_and_extent(&ray.coord);
+            let (occupant, entity_dim) = entities.get_value_and_extent(&ray.coord);
+            if occupant.is_some() || filter(&voxel) {
+                return Some(VoxelRayHit {
+                    coord: ray.coord,
+                    normal: ray.normal,
+                    distance: ray.distance,
+                    voxel,
+                    occupant,
+                });
+            }
+
+            let dim = voxel_dim.min(entity_dim);
+            if dim > 1 {
+                ray.skip_block(dim);
+            } else {
+                ray.step();
+            }
+        }
+
+        None
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// FUNCTIONS
+fn min_axis(
+    t: &Vec3,
+) -> usize {
+    if t.x <= t.y && t.x <= t.z {
+        0
+    } else if t.y <= t.z {
+        1
+    } else {
+        2
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid() -> Voxel { Voxel::from_matter_id(1) }
+
+    struct TestTrees {
+        voxels: Accessor<Voxel>,
+        entities: Accessor<Option<Entity>, ENTITY_LEAF_LOG2DIM, ENTITY_BRANCH_LOG2DIM, ENTITY_TRUNK_LOG2DIM>,
+    }
+
+    impl TestTrees {
+        fn new(
+            solids: &[IVec3],
+        ) -> Self {
+            let mut voxels = Accessor::new(&Arc::new(RwLock::new(RootNode::new(Voxel::default())))).with_remesh_mode(RemeshMode::Off);
+            for coord in solids.iter() {
+                voxels.set_value_on(coord, &solid());
+            }
+
+            Self {
+                voxels,
+                entities: Accessor::new(&Arc::new(RwLock::new(RootNode::new(None)))).with_remesh_mode(RemeshMode::Off),
+            }
+        }
+
+        fn cast(
+            &mut self,
+            origin: Vec3,
+            direction: Vec3,
+        ) -> Option<VoxelRayHit> {
+            self.voxels.raycast(&mut self.entities, origin, direction, 64.0, |voxel| voxel.matter_id() != 0)
+        }
+    }
+
+    #[test]
+    fn axis_aligned_rays_hit_the_face_they_enter() {
+        let mut trees = TestTrees::new(&[IVec3::new(5, 0, 0), IVec3::new(0, -3, 0)]);
+
+        let hit = trees.cast(Vec3::ZERO, Vec3::X).unwrap();
+        assert_eq!((hit.coord, hit.normal), (IVec3::new(5, 0, 0), IVec3::NEG_X));
+        assert!((hit.distance - 4.5).abs() < 1e-4);
+
+        let hit = trees.cast(Vec3::ZERO, Vec3::NEG_Y).unwrap();
+        assert_eq!((hit.coord, hit.normal), (IVec3::new(0, -3, 0), IVec3::Y));
+        assert!((hit.distance - 2.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn diagonal_rays_visit_every_voxel_they_cross() {
+        // Passes through (3, 1, 0) & (3, 2, 0) but never (4, 1, 0)
+        let mut trees = TestTrees::new(&[IVec3::new(4, 1, 0), IVec3::new(4, 2, 0)]);
+
+        let hit = trees.cast(Vec3::ZERO, Vec3::new(2.0, 1.0, 0.0)).unwrap();
+        assert_eq!((hit.coord, hit.normal), (IVec3::new(4, 2, 0), IVec3::NEG_X));
+        assert!((hit.distance - 1.75 * 5.0f32.sqrt()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn rays_starting_inside_a_solid_hit_it_without_a_normal() {
+        let mut trees = TestTrees::new(&[IVec3::ZERO]);
+
+        let hit = trees.cast(Vec3::new(0.2, -0.3, 0.1), Vec3::new(1.0, 1.0, 1.0)).unwrap();
+        assert_eq!((hit.coord, hit.normal, hit.distance), (IVec3::ZERO, IVec3::ZERO, 0.0));
+    }
+
+    #[test]
+    fn rays_that_miss_return_nothing() {
+        let mut trees = TestTrees::new(&[IVec3::new(5, 1, 0), IVec3::new(100, 0, 0)]);
+
+        assert!(trees.cast(Vec3::ZERO, Vec3::X).is_none());
+        assert!(trees.cast(Vec3::ZERO, Vec3::new(-1.0, 0.3, 0.2)).is_none());
+        assert!(trees.cast(Vec3::ZERO, Vec3::ZERO).is_none());
+    }
+
+    #[test]
+    fn tiles_are_skipped_whole_and_hit_on_their_face() {
+        let mut trees = TestTrees::new(&[]);
+        trees.voxels.set_tile(&IVec3::new(BRANCH_DIM as i32, 0, 0), BRANCH_DIM, &solid(), true);
+
+        let hit = trees.cast(Vec3::new(90.0, 5.0, 7.0), Vec3::new(1.0, 0.01, 0.0)).unwrap();
+        assert_eq!((hit.coord, hit.normal), (IVec3::new(BRANCH_DIM as i32, 5, 7), IVec3::NEG_X));
+    }
+
+    #[test]
+    fn occupants_stop_the_ray() {
+        let mut trees = TestTrees::new(&[IVec3::new(6, 0, 0)]);
+        let entity = Entity::from_raw(1);
+        trees.entities.set_value_on(&IVec3::new(3, 0, 0), &Some(entity));
+
+        let hit = trees.cast(Vec3::ZERO, Vec3::X).unwrap();
+        assert_eq!((hit.coord, hit.normal, hit.occupant), (IVec3::new(3, 0, 0), IVec3::NEG_X, Some(entity)));
+    }
+}
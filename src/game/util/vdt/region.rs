@@ -0,0 +1,441 @@
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// USE
+use crate::*;
+use super::*;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// ENUMS
+/// What a region op does with a block that is fully inside the shape
+enum BlockWrite {
+    Tile(Voxel, bool),
+    Skip,
+    Split,
+}
+
+enum RegionWrite<'a> {
+    Fill { value: Voxel, active: bool },
+    Replace { from: Voxel, to: Voxel },
+    Paste { buffer: &'a VoxelBuffer, offset: IVec3 },
+}
+
+impl RegionWrite<'_> {
+    /// `value` & `extent` are the uniform block around the block origin, see [Accessor::get_value_and_extent]
+    fn block_write(
+        &self,
+        value: &Voxel,
+        extent: usize,
+        dim: usize,
+        background: &Voxel,
+    ) -> BlockWrite {
+        match self {
+            Self::Fill { value, active } => BlockWrite::Tile(*value, *active),
+            Self::Replace { .. } if extent < dim => BlockWrite::Split,
+            Self::Replace { from, to } if value == from => BlockWrite::Tile(*to, to != background),
+            Self::Replace { .. } => BlockWrite::Skip,
+            Self::Paste { .. } => BlockWrite::Split,
+        }
+    }
+
+    fn voxel_write(
+        &self,
+        coord: &IVec3,
+        value: &Voxel,
+        background: &Voxel,
+    ) -> Option<(Voxel, bool)> {
+        match self {
+            Self::Fill { value, active } => Some((*value, *active)),
+            Self::Replace { from, to } => (value == from).then_some((*to, to != background)),
+            Self::Paste { buffer, offset } => {
+                let value = buffer.get_value(&(*coord - *offset));
+                Some((value, value != *background))
+            },
+        }
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// STRUCTS
+//================================-================================-================================
+// RegionReport
+/// What a region op wrote
+#[derive(Default, Debug)]
+pub struct RegionReport {
+    pub voxels: usize,
+    pub tiles: usize,
+    /// Meshes of trunks & branches replaced by tiles, these need to be despawned
+    pub mesh_entities: Vec<Entity>,
+}
+
+impl RegionReport {
+    pub fn despawn_meshes(
+        &self,
+        commands: &mut Commands,
+    ) {
+        for mesh_entity in self.mesh_entities.iter() {
+            commands.entity(*mesh_entity).despawn_recursive();
+        }
+    }
+}
+
+//================================-================================-================================
+// VoxelBuffer
+/// Voxels copied out of an [AreaShape], stored densely over its bounds with the min corner at zero
+pub struct VoxelBuffer {
+    shape: AreaShape,
+    size: IVec3,
+    voxels: Vec<Voxel>,
+    background: Voxel,
+}
+
+impl VoxelBuffer {
+    pub fn shape(&self) -> &AreaShape { &self.shape }
+    pub fn size(&self) -> &IVec3 { &self.size }
+
+    fn index_from_coord(
+        &self,
+        coord: &IVec3,
+    ) -> usize {
+        ((coord.x * self.size.y + coord.y) * self.size.z + coord.z) as usize
+    }
+
+    /// `coord` is relative to the min corner, anything outside the shape is background
+    pub fn get_value(
+        &self,
+        coord: &IVec3,
+    ) -> Voxel {
+        if self.shape.contains(coord) {
+            self.voxels[self.index_from_coord(coord)]
+        } else {
+            self.background
+        }
+    }
+}
+
+//================================-================================-================================
+// Accessor
+impl<const L: usize, const B: usize, const T: usize> Accessor<Voxel, L, B, T> {
+    pub fn fill(
+        &mut self,
+        shape: &AreaShape,
+        value: &Voxel,
+    ) -> RegionReport {
+        self.write_region(shape, &RegionWrite::Fill { value: *value, active: true })
+    }
+
+    pub fn clear(
+        &mut self,
+        shape: &AreaShape,
+    ) -> RegionReport {
+        self.write_region(shape, &RegionWrite::Fill { value: *self.background(), active: false })
+    }
+
+    pub fn replace(
+        &mut self,
+        shape: &AreaShape,
+        from: &Voxel,
+        to: &Voxel,
+    ) -> RegionReport {
+        self.write_region(shape, &RegionWrite::Replace { from: *from, to: *to })
+    }
+
+    pub fn copy(
+        &mut self,
+        shape: &AreaShape,
+    ) -> VoxelBuffer {
+        let (min, max) = shape.bounds();
+        let size = (max - min + IVec3::ONE).max(IVec3::ZERO);
+        let mut buffer = VoxelBuffer {
+            shape: shape.translated(&-min),
+            size,
+            voxels: vec![*self.background(); (size.x * size.y * size.z) as usize],
+            background: *self.background(),
+        };
+
+        for x in 0..size.x { for y in 0..size.y { for z in 0..size.z {
+            let coord = IVec3::new(x, y, z);
+            if buffer.shape.contains(&coord) {
+                let index = buffer.index_from_coord(&coord);
+                buffer.voxels[index] = self.get_value(&(coord + min));
+            }
+        }}}
+
+        buffer
+    }
+
+    /// Writes `buffer` back with its min corner at `offset`
+    pub fn paste(
+        &mut self,
+        buffer: &VoxelBuffer,
+        offset: &IVec3,
+    ) -> RegionReport {
+        self.write_region(&buffer.shape.translated(offset), &RegionWrite::Paste { buffer, offset: *offset })
+    }
+
+
+
+    /// Walks the shape top down, blocks fully inside it are written as tiles where the op allows
+    ///
+    /// Remeshing is batched over the whole op, so every touched mesh is rebuilt once on the next remesh pass
+    fn write_region(
+        &mut self,
+        shape: &AreaShape,
+        write: &RegionWrite,
+    ) -> RegionReport {
+        let batch = self.remesh_mode() == RemeshMode::Immediate;
+        if batch {
+            self.set_remesh_mode(RemeshMode::Batched);
+        }
+
+        let mut report = RegionReport::default();
+        let (min, max) = shape.bounds();
+        let trunk_dim = TreeDims::<L, B, T>::TRUNK_DIM;
+        for block_origin in aligned_blocks(&(min - IVec3::ONE), &(max + IVec3::ONE), trunk_dim) {
+            self.write_block(shape, write, &block_origin, trunk_dim, &mut report);
+        }
+
+        if batch {
+            self.set_remesh_mode(RemeshMode::Immediate);
+        }
+
+        report
+    }
+
+    fn write_block(
+        &mut self,
+        shape: &AreaShape,
+        write: &RegionWrite,
+        block_origin: &IVec3,
+        dim: usize,
+        report: &mut RegionReport,
+    ) {
+        if !shape.intersects_block(block_origin, dim, 1) {
+            return;
+        }
+
+        let (value, extent) = self.get_value_and_extent(block_origin);
+        if !shape.intersects_block(block_origin, dim, 0) {
+            // Only borders the shape, the faces it shows towards the shape may have changed
+            if extent >= dim {
+                self.mark_remesh(block_origin);
+                return;
+            }
+        } else if dim > 1 && shape.contains_block(block_origin, dim) {
+            match write.block_write(&value, extent, dim, self.background()) {
+                BlockWrite::Tile(value, active) => {
+                    if let Some(mesh_entities) = self.set_tile(block_origin, dim, &value, active) {
+                        report.tiles += 1;
+                        report.mesh_entities.extend(mesh_entities);
+                        return;
+                    }
+                },
+                BlockWrite::Skip => return,
+                BlockWrite::Split => {},
+            }
+        }
+
+        if dim == 1 {
+            if let Some((value, active)) = write.voxel_write(block_origin, &value, self.background()) {
+                self.set_value(block_origin, &value, active);
+                report.voxels += 1;
+            }
+
+            return;
+        }
+
+        let child_dim = if dim == TreeDims::<L, B, T>::TRUNK_DIM {
+                TreeDims::<L, B, T>::BRANCH_DIM
+            } else if dim == TreeDims::<L, B, T>::BRANCH_DIM {
+                TreeDims::<L, B, T>::LEAF_DIM
+            } else {
+                1
+            };
+
+        let (min, max) = shape.bounds();
+        let block_max = *block_origin + IVec3::splat(dim as i32 - 1);
+        for child_origin in aligned_blocks(&(min - IVec3::ONE).max(*block_origin), &(max + IVec3::ONE).min(block_max), child_dim) {
+            self.write_block(shape, write, &child_origin, child_dim, report);
+        }
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// FUNCTIONS
+/// Origins of every `dim` aligned block overlapping the inclusive `min` to `max` box
+fn aligned_blocks(
+    min: &IVec3,
+    max: &IVec3,
+    dim: usize,
+) -> impl Iterator<Item = IVec3> {
+    let dim = dim as i32;
+    let start = *min & !(dim - 1);
+    let count = if min.cmple(*max).all() { ((*max & !(dim - 1)) - start) / dim + IVec3::ONE } else { IVec3::ZERO };
+
+    (0..count.x).flat_map(move |x| (0..count.y).flat_map(move |y| (0..count.z).map(move |z| start + IVec3::new(x, y, z) * dim)))
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Leaves of 4, branches of 16 & trunks of 64 voxels
+    type TestAccessor = Accessor<Voxel, 2, 2, 2>;
+
+    fn test_shapes() -> [AreaShape; 3] {
+        [
+            AreaShape::Rect { start: IVec3::new(9, 5, 20), end: IVec3::new(-3, -2, 1) },
+            AreaShape::Sphere { origin: IVec3::new(2, 3, -4), radius: 6 },
+            AreaShape::Cylinder { origin: IVec3::new(5, -3, 2), radius: 5, height: 9 },
+        ]
+    }
+
+    fn new_accessor() -> TestAccessor {
+        Accessor::new(&Arc::new(RwLock::new(RootNode::new(Voxel::default())))).with_remesh_mode(RemeshMode::Off)
+    }
+
+    /// Mixed terrain, a third of it background
+    fn pattern(
+        coord: &IVec3,
+    ) -> Voxel {
+        Voxel::from_matter_id((coord.x * 7 + coord.y * 13 + coord.z * 5).rem_euclid(3) as u8)
+    }
+
+    fn write_pattern(
+        voxels: &mut TestAccessor,
+        min: &IVec3,
+        max: &IVec3,
+    ) {
+        for x in min.x..=max.x { for y in min.y..=max.y { for z in min.z..=max.z {
+            let coord = IVec3::new(x, y, z);
+            let value = pattern(&coord);
+            voxels.set_value(&coord, &value, value != *voxels.background());
+        }}}
+    }
+
+    /// Checks every voxel in & around `shape`
+    fn assert_region(
+        voxels: &mut TestAccessor,
+        shape: &AreaShape,
+        inside: impl Fn(&IVec3) -> Voxel,
+        outside: impl Fn(&IVec3) -> Voxel,
+    ) {
+        let (min, max) = shape.bounds();
+        for x in min.x - 2..=max.x + 2 { for y in min.y - 2..=max.y + 2 { for z in min.z - 2..=max.z + 2 {
+            let coord = IVec3::new(x, y, z);
+            let expected = if shape.contains(&coord) { inside(&coord) } else { outside(&coord) };
+            assert_eq!(voxels.get_value(&coord), expected, "at {}", coord);
+        }}}
+    }
+
+    fn padded_bounds(
+        shape: &AreaShape,
+    ) -> (IVec3, IVec3) {
+        let (min, max) = shape.bounds();
+        (min - IVec3::splat(2), max + IVec3::splat(2))
+    }
+
+    #[test]
+    fn fill_writes_exactly_the_shape() {
+        for shape in test_shapes().iter() {
+            let mut voxels = new_accessor();
+            let (min, max) = padded_bounds(shape);
+            write_pattern(&mut voxels, &min, &max);
+
+            let report = voxels.fill(shape, &Voxel::from_matter_id(5));
+            assert!(report.tiles > 0);
+            assert_region(&mut voxels, shape, |_| Voxel::from_matter_id(5), pattern);
+        }
+    }
+
+    #[test]
+    fn clear_leaves_nothing_active_in_the_shape() {
+        for shape in test_shapes().iter() {
+            let root_node = Arc::new(RwLock::new(RootNode::new(Voxel::default())));
+            let mut voxels: TestAccessor = Accessor::new(&root_node).with_remesh_mode(RemeshMode::Off);
+            let (min, max) = padded_bounds(shape);
+            write_pattern(&mut voxels, &min, &max);
+
+            voxels.clear(shape);
+            assert_region(&mut voxels, shape, |_| Voxel::default(), pattern);
+            assert!(root_node.read().unwrap().iter_active_regions().voxels().all(|(coord, _)| !shape.contains(&coord)));
+        }
+    }
+
+    #[test]
+    fn replace_only_touches_matching_voxels() {
+        let (from, to) = (Voxel::from_matter_id(1), Voxel::from_matter_id(7));
+        for shape in test_shapes().iter() {
+            let mut voxels = new_accessor();
+            let (min, max) = padded_bounds(shape);
+            write_pattern(&mut voxels, &min, &max);
+            // A uniform block of `from` that can be replaced as a tile
+            voxels.fill(&AreaShape::Rect { start: IVec3::ZERO, end: IVec3::splat(3) }, &from);
+            let before = |coord: &IVec3| if coord.cmpge(IVec3::ZERO).all() && coord.cmple(IVec3::splat(3)).all() { from } else { pattern(coord) };
+
+            voxels.replace(shape, &from, &to);
+            assert_region(&mut voxels, shape, |coord| if before(coord) == from { to } else { before(coord) }, before);
+        }
+    }
+
+    #[test]
+    fn paste_writes_the_copy_at_the_offset() {
+        let offset = IVec3::new(21, -6, 3);
+        for shape in test_shapes().iter() {
+            let mut voxels = new_accessor();
+            let (min, max) = padded_bounds(shape);
+            write_pattern(&mut voxels, &min, &max);
+
+            let buffer = voxels.copy(shape);
+            let (shape_min, _) = shape.bounds();
+            let pasted = shape.translated(&(offset - shape_min));
+            voxels.paste(&buffer, &offset);
+
+            let outside = |coord: &IVec3| if coord.cmpge(min).all() && coord.cmple(max).all() { pattern(coord) } else { Voxel::default() };
+            assert_region(&mut voxels, &pasted, |coord| pattern(&(*coord - offset + shape_min)), outside);
+        }
+    }
+
+    #[test]
+    fn covered_leaves_and_branches_become_tiles() {
+        let mut voxels = new_accessor();
+        let value = Voxel::from_matter_id(3);
+
+        let report = voxels.fill(&AreaShape::Rect { start: IVec3::new(4, 0, 0), end: IVec3::new(7, 3, 3) }, &value);
+        assert_eq!((report.tiles, report.voxels), (1, 0));
+        assert_eq!(voxels.get_value_and_extent(&IVec3::new(5, 1, 2)), (value, 4));
+
+        let report = voxels.fill(&AreaShape::Rect { start: IVec3::splat(16), end: IVec3::splat(31) }, &value);
+        assert_eq!((report.tiles, report.voxels), (1, 0));
+        assert_eq!(voxels.get_value_and_extent(&IVec3::splat(20)), (value, 16));
+    }
+
+    #[test]
+    fn journaled_fills_over_mixed_blocks_stay_tiles_and_undo_exactly() {
+        let journal: ValueJournal<Voxel> = Arc::new(Mutex::new(vec![]));
+        let mut voxels = new_accessor().with_journal(&journal);
+        let (min, max) = (IVec3::new(-2, 3, 5), IVec3::new(21, 19, 30));
+        write_pattern(&mut voxels, &min, &max);
+        journal.lock().unwrap().clear();
+
+        let trunk = AreaShape::Rect { start: IVec3::ZERO, end: IVec3::splat(63) };
+        let report = voxels.fill(&trunk, &Voxel::from_matter_id(4));
+        assert_eq!((report.tiles, report.voxels), (1, 0));
+        assert_eq!(voxels.get_value_and_extent(&IVec3::splat(40)), (Voxel::from_matter_id(4), 64));
+
+        let changes = std::mem::take(&mut *journal.lock().unwrap());
+        for change in changes.iter().rev() {
+            let active = change.old != *voxels.background();
+            if change.dim == 1 {
+                voxels.set_value(&change.coord, &change.old, active);
+            } else {
+                let block = AreaShape::Rect { start: change.coord, end: change.coord + IVec3::splat(change.dim as i32 - 1) };
+                if active { voxels.fill(&block, &change.old); } else { voxels.clear(&block); }
+            }
+        }
+
+        let outside_pattern = |coord: &IVec3| if coord.cmpge(min).all() && coord.cmple(max).all() { pattern(coord) } else { Voxel::default() };
+        assert_region(&mut voxels, &trunk, outside_pattern, outside_pattern);
+    }
+}
@@ -2,62 +2,48 @@
 // USE
 use crate::*;
 use super::*;
-use bevy::utils::HashMap;
+use bevy::utils::{HashMap, HashSet};
 pub use std::sync::{
+    atomic::{AtomicU64, Ordering},
     Arc,
     RwLock,
 };
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // STRUCTS
-pub struct RootNode<ValueType: Clone + Copy + PartialEq + Eq> {
-    table: HashMap<IVec3, RootData<ValueType>>,
+pub struct RootNode<ValueType: Clone + Copy + PartialEq + Eq, const L: usize = { LEAF_LOG2DIM }, const B: usize = { BRANCH_LOG2DIM }, const T: usize = { TRUNK_LOG2DIM }> {
+    table: HashMap<IVec3, RootData<ValueType, L, B, T>>,
     pub background: ValueType,
+    /// Set while trunks are paged to & from disk, see [VoxelStreamer]
+    stream: Option<Arc<RootStream<ValueType, L, B, T>>>,
+    /// Bumped whenever nodes are dropped from the tree, [Accessor]s forget their cached nodes when it changes
+    pub(super) generation: Arc<AtomicU64>,
 }
 
 impl RootNode<Voxel> {
-    pub fn get_meshes(
+    /// Snapshots every leaf & tile marked for redraw, see [RootNode::clear_redraw]
+    pub fn get_mesh_jobs(
         &self,
-        commands: &mut Commands,
-        meshes: &mut ResMut<Assets<Mesh>>,
         voxels: &mut Accessor<Voxel>,
-        voxel_materials: &VoxelMaterials,
+        mesh_mode: VoxelMeshMode,
         defs: &Res<Defs>,
-    ) -> Vec<(IVec3, Option<Entity>)> {
-        let mut key_mesh_entity_pairs: Vec<(IVec3, Option<Entity>)> = vec![];
+    ) -> Vec<VoxelMeshJob> {
+        let mut jobs: Vec<VoxelMeshJob> = vec![];
 
-        for root_data in self.table.values() {
-            if root_data.redraw {
-                if let Some(trunk_node) = &root_data.child {
-                    let trunk_read_lock = trunk_node.read().unwrap();
-                    let key_mesh_pairs = trunk_read_lock.get_meshes(defs, voxels);
-
-                    for (key, mesh) in key_mesh_pairs.iter() {
-                        if let Some(mesh) = mesh {
-                            let mesh_entity = commands.spawn(PbrBundle {
-                                    mesh: meshes.add(mesh.clone()),
-                                    material: voxel_materials.base_material.clone(),
-                                    transform: Transform::from_translation(key.as_vec3()),
-                                    global_transform: GlobalTransform::from(Transform::from_translation(key.as_vec3())),
-                                    ..default()
-                                })
-                                .insert(RigidBody::Fixed)
-                                .insert(Collider::from_bevy_mesh(&mesh, &ComputedColliderShape::TriMesh).unwrap())
-                                .insert(Name::new("Mesh ".to_string() + &key.to_string()))
-                                .id();
-    
-                            key_mesh_entity_pairs.push((*key, Some(mesh_entity)));
-                        } else {
-                            key_mesh_entity_pairs.push((*key, None));
-                        }
-                    }
-                } else {
-                    error!("needed TRUNK tile mesh but developer is lazy!");
-                }
+        for (key, root_data) in self.table.iter() {
+            if !root_data.redraw {
+                continue;
+            }
+
+            if let Some(trunk_node) = &root_data.child {
+                jobs.extend(trunk_node.read().unwrap().get_mesh_jobs(defs, voxels, mesh_mode));
+            } else {
+                let active = root_data.tile != self.background;
+                jobs.push(VoxelMeshJob::new_tile(key, TRUNK_DIM, BRANCH_DIM, &root_data.tile, active, defs, voxels));
             }
         }
 
-        key_mesh_entity_pairs
+        jobs
     }
 
     pub fn assign_meshes(
@@ -85,10 +71,12 @@ impl RootNode<Voxel> {
 
         let key = *coord & TRUNK_ORIGIN_MASK;
         if let Some(root_data) = self.table.get_mut(&key) {
-            root_data.redraw = false;
+            despawn.append(&mut root_data.retired);
             if let Some(trunk_node) = &root_data.child {
                 let mut trunk_write_lock = trunk_node.write().unwrap();
                 despawn.extend(trunk_write_lock.assign_mesh_entity(coord, new_mesh_entity));
+                // Shell of the tile this trunk replaced
+                despawn.extend(root_data.mesh.take());
             } else {
                 if let Some(old_mesh_entity) = root_data.mesh {
                     despawn.push(old_mesh_entity);
@@ -97,18 +85,208 @@ impl RootNode<Voxel> {
                 root_data.mesh = *new_mesh_entity;
             }
         } else {
-            error!("assign_mesh_entity failed to find struct for mesh - this should never happen");
+            // Meshes are built in the background, the tree may have been swapped out since
+            despawn.extend(*new_mesh_entity);
         }
 
         despawn
     }
+}
+
+impl<ValueType: Clone + Copy + PartialEq + Eq, const L: usize, const B: usize, const T: usize> RootNode<ValueType, L, B, T> {
+    pub fn new(
+        background: ValueType,
+    ) -> Self {
+        let () = TreeDims::<L, B, T>::CHECK;
+        Self {
+            table: HashMap::<IVec3, RootData<ValueType, L, B, T>>::default(),
+            background,
+            stream: None,
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn stream(&self) -> Option<&Arc<RootStream<ValueType, L, B, T>>> { self.stream.as_ref() }
+
+    pub fn set_stream(
+        &mut self,
+        stream: Option<Arc<RootStream<ValueType, L, B, T>>>,
+    ) {
+        self.stream = stream;
+    }
+
+    pub fn generation(&self) -> u64 { self.generation.load(Ordering::Acquire) }
+
+    /// Marks every node cached by an [Accessor] as possibly dropped
+    pub(super) fn bump_generation(
+        &self,
+    ) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Swaps in `root_node` while keeping this root's generation, so accessors into this root drop their cached nodes
+    pub fn replace(
+        &mut self,
+        mut root_node: Self,
+    ) {
+        root_node.generation = self.generation.clone();
+        root_node.bump_generation();
+        *self = root_node;
+    }
+
+    pub fn is_resident(
+        &self,
+        key: &IVec3,
+    ) -> bool {
+        self.table.contains_key(key)
+    }
+
+    pub fn resident_keys(
+        &self,
+    ) -> Vec<IVec3> {
+        self.table.keys().copied().collect()
+    }
+
+    /// Key & value of every resident active tile, trunks are left out
+    pub fn iter_active_tiles(
+        &self,
+    ) -> impl Iterator<Item = (IVec3, ValueType)> + '_ {
+        self.table.iter()
+            .filter(|(_, root_data)| root_data.child.is_none() && root_data.tile != self.background)
+            .map(|(key, root_data)| (*key, root_data.tile))
+    }
+
+    /// Key of every resident trunk
+    pub fn iter_children(
+        &self,
+    ) -> impl Iterator<Item = (IVec3, &Arc<RwLock<TrunkNode<ValueType, L, B, T>>>)> + '_ {
+        self.table.iter().filter_map(|(key, root_data)| root_data.child.as_ref().map(|trunk_node| (*key, trunk_node)))
+    }
+
+    /// Keys of trunks that were written while not resident, since the last call
+    pub fn drain_requests(
+        &self,
+    ) -> Vec<IVec3> {
+        match &self.stream {
+            Some(stream) => stream.requested.lock().unwrap().drain().collect(),
+            None => vec![],
+        }
+    }
+
+    /// Asks the [VoxelStreamer] to load a trunk that isn't resident, only held back writes do this
+    /// 
+    /// Reads just see the background, which trunks stay resident is up to the focus radius
+    fn request(
+        &self,
+        key: &IVec3,
+    ) {
+        if let Some(stream) = &self.stream {
+            stream.requested.lock().unwrap().insert(*key);
+        }
+    }
+
+    /// Loads a streamed trunk on the spot, blocking until it's resident, for preloading before play starts
+    pub fn ensure_resident(
+        &mut self,
+        key: &IVec3,
+    ) {
+        if self.table.contains_key(key) {
+            return;
+        }
+
+        if let Some(stream) = self.stream.clone() {
+            self.insert_streamed(key, (stream.loader)(key));
+        }
+    }
+
+    /// Takes the loaded trunk at `key` out of `loaded`, or an empty tile if there was nothing to load, then replays
+    /// the writes held back while it wasn't resident
+    /// 
+    /// False if the trunk became resident in the meantime, the loaded one is dropped
+    pub fn insert_streamed(
+        &mut self,
+        key: &IVec3,
+        loaded: Option<RootNode<ValueType, L, B, T>>,
+    ) -> bool {
+        if self.table.contains_key(key) {
+            return false;
+        }
+
+        let root_data = loaded.and_then(|mut root_node| root_node.table.remove(key)).unwrap_or_else(|| RootData::new_tile(&self.background));
+        self.table.insert(*key, root_data);
+
+        let pending = self.stream.as_ref().and_then(|stream| stream.pending.lock().unwrap().remove(key));
+        for write in pending.unwrap_or_default() {
+            // Journals get the loaded values as old, not the background the write was made over
+            let recorded = !write.journals.is_empty() || !write.links.is_empty();
+            let changes = match write.kind {
+                PendingWriteKind::Value { coord, value, active } => {
+                    let old = self.get_value_and_cache(&coord).0;
+                    self.set_value_and_cache(&coord, &value, active);
+                    vec![ValueChange { coord, dim: 1, old, new: value }]
+                },
+                PendingWriteKind::Tile { coord, dim, value, active } => {
+                    let changes = if recorded { tile_changes(self, &coord, dim, &value) } else { vec![] };
+                    // A trunk that was just loaded has no meshes yet, so nothing is dropped
+                    self.set_tile(&coord, dim, &value, active);
+                    changes
+                },
+            };
+
+            for change in changes.iter() {
+                record_value_change(&write.journals, &write.links, change);
+            }
+        }
+
+        true
+    }
+
+    /// Holds back a write to a streamed trunk that isn't resident & requests its background load
+    /// 
+    /// True if the write was held back, it's applied once the trunk is loaded, see [RootNode::insert_streamed]
+    pub(super) fn defer_write(
+        &self,
+        write: PendingWrite<ValueType>,
+    ) -> bool {
+        let key = &(write.coord() & TreeDims::<L, B, T>::TRUNK_ORIGIN_MASK);
+        if self.table.contains_key(key) {
+            return false;
+        }
+
+        let Some(stream) = &self.stream else { return false; };
+        stream.pending.lock().unwrap().entry(*key).or_default().push(write);
+        self.request(key);
+        true
+    }
+
+    /// Drops the trunk at `key` from memory, its meshes are returned & must be despawned
+    pub fn take_trunk(
+        &mut self,
+        key: &IVec3,
+    ) -> Option<(StreamedTrunk<ValueType, L, B, T>, Vec<Entity>)> {
+        let root_data = self.table.remove(key)?;
+        self.bump_generation();
+        let mut mesh_entities: Vec<Entity> = root_data.mesh.into_iter().chain(root_data.retired.iter().copied()).collect();
+        if let Some(trunk_node) = &root_data.child {
+            mesh_entities.extend(trunk_node.read().unwrap().get_mesh_entities());
+        }
+
+        let trunk = StreamedTrunk {
+            key: *key,
+            background: self.background,
+            tile: root_data.tile,
+            child: root_data.child,
+        };
+
+        Some((trunk, mesh_entities))
+    }
 
     pub fn set_redraw_and_cache(
         &mut self,
         coord: &IVec3,
-    ) -> (Option<Arc<RwLock<BranchNode<Voxel>>>>, Option<Arc<RwLock<TrunkNode<Voxel>>>>) {
-        let key = *coord & TRUNK_ORIGIN_MASK;
-        let mut child: Option<Arc<RwLock<TrunkNode<Voxel>>>> = None;
+    ) -> (BranchRef<ValueType, L, B, T>, TrunkRef<ValueType, L, B, T>) {
+        let key = *coord & TreeDims::<L, B, T>::TRUNK_ORIGIN_MASK;
+        let mut child: Option<Arc<RwLock<TrunkNode<ValueType, L, B, T>>>> = None;
         if let Some(root_data) = self.table.get_mut(&key) {
 
             root_data.redraw = true;
@@ -116,7 +294,7 @@ impl RootNode<Voxel> {
             if let Some(trunk_node) = &root_data.child {
                 child = Some(trunk_node.clone());
             }
-        } else {
+        } else if self.stream.is_none() {
             self.table.insert(key, RootData::new_tile(&self.background));
         }
 
@@ -127,25 +305,105 @@ impl RootNode<Voxel> {
 
         (None, None)
     }
-}
 
-impl<ValueType: Clone + Copy + PartialEq + Eq> RootNode<ValueType> {
-    pub fn new(
-        background: ValueType,
-    ) -> Self {
-        Self {
-            table: HashMap::<IVec3, RootData<ValueType>>::default(),
-            background,
+    pub fn get_mesh_entities(
+        &self,
+    ) -> Vec<Entity> {
+        let mut mesh_entities: Vec<Entity> = vec![];
+
+        for root_data in self.table.values() {
+            if let Some(trunk_node) = &root_data.child {
+                mesh_entities.extend(trunk_node.read().unwrap().get_mesh_entities());
+            }
+
+            if let Some(mesh_entity) = root_data.mesh {
+                mesh_entities.push(mesh_entity);
+            }
+
+            mesh_entities.extend(root_data.retired.iter());
+        }
+
+        mesh_entities
+    }
+
+    /// Called once every redraw has been turned into a mesh job
+    pub fn clear_redraw(
+        &mut self,
+    ) {
+        for root_data in self.table.values_mut() {
+            if root_data.redraw {
+                if let Some(trunk_node) = &root_data.child {
+                    trunk_node.write().unwrap().clear_redraw();
+                }
+
+                root_data.redraw = false;
+            }
         }
     }
 
+    /// Marks every trunk, branch & leaf for redraw, for when something all meshes depend on changes
+    pub fn set_redraw_all(
+        &mut self,
+    ) {
+        for root_data in self.table.values_mut() {
+            if let Some(trunk_node) = &root_data.child {
+                trunk_node.write().unwrap().set_redraw_all();
+            }
+
+            root_data.redraw = true;
+        }
+    }
+
+    /// Folds every uniform leaf, branch & trunk back into a tile in its parent
+    /// 
+    /// Meshes of collapsed branches & trunks stay until the tiles replacing them are remeshed, so nothing flickers
+    pub fn prune(
+        &mut self,
+    ) -> PruneReport {
+        let mut report = PruneReport::default();
+        let background = self.background;
+
+        for root_data in self.table.values_mut() {
+            let branches = report.branches;
+            let uniform = if let Some(trunk_node) = &root_data.child {
+                    trunk_node.write().unwrap().prune(&mut report)
+                } else {
+                    continue;
+                };
+
+            // Branch tiles are meshed through the redraw of their trunk
+            if report.branches > branches {
+                root_data.redraw = true;
+            }
+
+            // Root tiles have no value mask, they are active whenever they differ from the background
+            if let Some((value, active)) = uniform {
+                if active == (value != background) {
+                    let trunk_node = root_data.child.take().unwrap();
+                    let retired = trunk_node.read().unwrap().get_mesh_entities();
+                    root_data.retired.extend(retired);
+                    report.trunks += 1;
+                    report.bytes += TrunkNode::<ValueType, L, B, T>::mem_size();
+
+                    root_data.tile = value;
+                    root_data.redraw = true;
+                }
+            }
+        }
+
+        if report.leaves > 0 || report.branches > 0 || report.trunks > 0 {
+            self.bump_generation();
+        }
+
+        report
+    }
 
 
     pub fn get_value_and_cache(
         &self,
         coord: &IVec3,
-    ) -> (ValueType, Option<Arc<RwLock<LeafNode<ValueType>>>>, Option<Arc<RwLock<BranchNode<ValueType>>>>, Option<Arc<RwLock<TrunkNode<ValueType>>>>) {
-        let key = *coord & TRUNK_ORIGIN_MASK;
+    ) -> (ValueType, LeafRef<ValueType, L, B, T>, BranchRef<ValueType, L, B, T>, TrunkRef<ValueType, L, B, T>) {
+        let key = *coord & TreeDims::<L, B, T>::TRUNK_ORIGIN_MASK;
         if let Some(root_data) = self.table.get(&key) {
             if let Some(trunk_node) = &root_data.child {
                 let (value, leaf_node, branch_node) = trunk_node.read().unwrap().get_value_and_cache(coord);
@@ -158,14 +416,57 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> RootNode<ValueType> {
         }
     }
 
+    /// `Some` if the `dim` sized block around `coord` is a single tile, used to cull tile shells
+    pub fn get_block_value(
+        &self,
+        coord: &IVec3,
+        dim: usize,
+    ) -> Option<ValueType> {
+        let key = *coord & TreeDims::<L, B, T>::TRUNK_ORIGIN_MASK;
+        if let Some(root_data) = self.table.get(&key) {
+            if let Some(trunk_node) = &root_data.child {
+                if dim >= TreeDims::<L, B, T>::TRUNK_DIM {
+                    None
+                } else {
+                    trunk_node.read().unwrap().get_block_value(coord, dim)
+                }
+            } else {
+                Some(root_data.tile)
+            }
+        } else {
+            Some(self.background)
+        }
+    }
+
+    /// The value at `coord` & the dim of the uniform block it sits in, 1 if it's inside a leaf
+    pub fn get_value_and_extent(
+        &self,
+        coord: &IVec3,
+    ) -> (ValueType, usize) {
+        let key = *coord & TreeDims::<L, B, T>::TRUNK_ORIGIN_MASK;
+        if let Some(root_data) = self.table.get(&key) {
+            if let Some(trunk_node) = &root_data.child {
+                trunk_node.read().unwrap().get_value_and_extent(coord)
+            } else {
+                (root_data.tile, TreeDims::<L, B, T>::TRUNK_DIM)
+            }
+        } else {
+            (self.background, TreeDims::<L, B, T>::TRUNK_DIM)
+        }
+    }
+
     pub fn set_value_and_cache(
         &mut self,
         coord: &IVec3,
         value: &ValueType,
         active: bool,
-    ) -> (Option<Arc<RwLock<LeafNode<ValueType>>>>, Option<Arc<RwLock<BranchNode<ValueType>>>>, Option<Arc<RwLock<TrunkNode<ValueType>>>>) {
-        let key = *coord & TRUNK_ORIGIN_MASK;
-        let mut child: Option<Arc<RwLock<TrunkNode<ValueType>>>> = None;
+    ) -> (LeafRef<ValueType, L, B, T>, BranchRef<ValueType, L, B, T>, TrunkRef<ValueType, L, B, T>) {
+        let key = *coord & TreeDims::<L, B, T>::TRUNK_ORIGIN_MASK;
+        if self.defer_write(PendingWrite::new(PendingWriteKind::Value { coord: *coord, value: *value, active })) {
+            return (None, None, None);
+        }
+
+        let mut child: Option<Arc<RwLock<TrunkNode<ValueType, L, B, T>>>> = None;
         if let Some(root_data) = self.table.get_mut(&key) {
 
             root_data.redraw = true;
@@ -190,19 +491,188 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> RootNode<ValueType> {
 
         (None, None, None)
     }
+
+    /// Replaces the `dim` sized block around `coord` with a tile, creating the nodes above it as needed
+    /// 
+    /// Root tiles are active whenever they differ from the background, `active` only applies below the root
+    /// 
+    /// Returns the meshes of any trunks or branches that were dropped, these must be despawned
+    pub fn set_tile(
+        &mut self,
+        coord: &IVec3,
+        dim: usize,
+        value: &ValueType,
+        active: bool,
+    ) -> Vec<Entity> {
+        let key = *coord & TreeDims::<L, B, T>::TRUNK_ORIGIN_MASK;
+        if self.defer_write(PendingWrite::new(PendingWriteKind::Tile { coord: *coord, dim, value: *value, active })) {
+            return vec![];
+        }
+
+        // Any node below the tile may be dropped
+        self.bump_generation();
+        let background = self.background;
+        let root_data = self.table.entry(key).or_insert_with(|| RootData::new_tile(&background));
+
+        if dim >= TreeDims::<L, B, T>::TRUNK_DIM {
+            let mut despawn: Vec<Entity> = vec![];
+            if let Some(trunk_node) = root_data.child.take() {
+                despawn.extend(trunk_node.read().unwrap().get_mesh_entities());
+            }
+
+            root_data.tile = *value;
+            root_data.redraw = true;
+            return despawn;
+        }
+
+        if root_data.child.is_none() {
+            if root_data.tile == *value && active == (*value != background) {
+                return vec![];
+            }
+
+            root_data.child = Some(Arc::new(RwLock::new(TrunkNode::new(coord, &root_data.tile, root_data.tile != background))));
+        }
+
+        root_data.redraw = true;
+        root_data.child.as_ref().unwrap().write().unwrap().set_tile(coord, dim, value, active)
+    }
+}
+
+impl<ValueType: Clone + Copy + PartialEq + Eq + Serialize + for<'a> Deserialize<'a>, const L: usize, const B: usize, const T: usize> RootNode<ValueType, L, B, T> {
+    pub fn to_file(
+        &self,
+    ) -> RootFile<ValueType> {
+        let mut entries: Vec<RootDataFile<ValueType>> = vec![];
+
+        for (key, root_data) in self.table.iter() {
+            entries.push(RootDataFile {
+                key: *key,
+                tile: root_data.tile,
+                child: root_data.child.as_ref().map(|trunk_node| trunk_node.read().unwrap().to_file()),
+            });
+        }
+
+        RootFile {
+            magic: VDT_FILE_MAGIC,
+            version: VDT_FILE_VERSION,
+            log2dims: TreeDims::<L, B, T>::LOG2DIMS,
+            background: self.background,
+            entries,
+        }
+    }
+
+    /// Rebuilds the node hierarchy with every trunk marked for redraw
+    pub fn from_file(
+        file: RootFile<ValueType>,
+    ) -> bincode::Result<Self> {
+        if file.magic != VDT_FILE_MAGIC {
+            return Err(vdt_file_error("not a voxel tree file"));
+        }
+
+        if file.version != VDT_FILE_VERSION {
+            return Err(vdt_file_error(&format!("voxel tree file version {} is not supported (expected {})", file.version, VDT_FILE_VERSION)));
+        }
+
+        if file.log2dims != TreeDims::<L, B, T>::LOG2DIMS {
+            return Err(vdt_file_error(&format!("voxel tree file has node dimensions {:?}", file.log2dims)));
+        }
+
+        let mut root_node = Self::new(file.background);
+        for entry in file.entries.into_iter() {
+            let key = entry.key & TreeDims::<L, B, T>::TRUNK_ORIGIN_MASK;
+            let mut root_data = RootData::new_tile(&entry.tile);
+            if let Some(trunk_file) = entry.child {
+                root_data.child = Some(Arc::new(RwLock::new(TrunkNode::from_file(trunk_file, &entry.tile)?)));
+            }
+
+            root_node.table.insert(key, root_data);
+        }
+
+        Ok(root_node)
+    }
+
+    pub fn to_bytes(
+        &self,
+    ) -> bincode::Result<Vec<u8>> {
+        bincode::serialize(&self.to_file())
+    }
+
+    pub fn from_bytes(
+        bytes: &[u8],
+    ) -> bincode::Result<Self> {
+        Self::from_file(bincode::deserialize(bytes)?)
+    }
 }
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // STRUCTS
-struct RootData<ValueType: Clone + Copy + PartialEq + Eq> {
-    child: Option<Arc<RwLock<TrunkNode<ValueType>>>>,
+/// What a prune pass folded back into tiles
+#[derive(Default, Debug)]
+pub struct PruneReport {
+    pub leaves: usize,
+    pub branches: usize,
+    pub trunks: usize,
+    /// Approximate memory reclaimed
+    pub bytes: usize,
+}
+
+/// A trunk taken out of a [RootNode] to be paged out, saved as a root file holding just this trunk
+/// 
+/// Clones share the trunk's nodes
+#[derive(Clone)]
+pub struct StreamedTrunk<ValueType: Clone + Copy + PartialEq + Eq, const L: usize = { LEAF_LOG2DIM }, const B: usize = { BRANCH_LOG2DIM }, const T: usize = { TRUNK_LOG2DIM }> {
+    key: IVec3,
+    background: ValueType,
+    tile: ValueType,
+    child: Option<Arc<RwLock<TrunkNode<ValueType, L, B, T>>>>,
+}
+
+impl<ValueType: Clone + Copy + PartialEq + Eq, const L: usize, const B: usize, const T: usize> StreamedTrunk<ValueType, L, B, T> {
+    pub fn key(&self) -> &IVec3 { &self.key }
+
+    /// A background tile, nothing worth saving unless it replaces something on disk
+    pub fn is_empty(
+        &self,
+    ) -> bool {
+        self.child.is_none() && self.tile == self.background
+    }
+}
+
+impl<ValueType: Clone + Copy + PartialEq + Eq + Serialize + for<'a> Deserialize<'a>, const L: usize, const B: usize, const T: usize> StreamedTrunk<ValueType, L, B, T> {
+    pub fn to_file(
+        &self,
+    ) -> RootFile<ValueType> {
+        RootFile {
+            magic: VDT_FILE_MAGIC,
+            version: VDT_FILE_VERSION,
+            log2dims: TreeDims::<L, B, T>::LOG2DIMS,
+            background: self.background,
+            entries: vec![RootDataFile {
+                key: self.key,
+                tile: self.tile,
+                child: self.child.as_ref().map(|trunk_node| trunk_node.read().unwrap().to_file()),
+            }],
+        }
+    }
+
+    pub fn to_bytes(
+        &self,
+    ) -> bincode::Result<Vec<u8>> {
+        bincode::serialize(&self.to_file())
+    }
+}
+
+struct RootData<ValueType: Clone + Copy + PartialEq + Eq, const L: usize, const B: usize, const T: usize> {
+    child: Option<Arc<RwLock<TrunkNode<ValueType, L, B, T>>>>,
     tile: ValueType,
 
     mesh: Option<Entity>,
+    /// Meshes of a trunk pruned into this tile, shown until the tile's own mesh replaces them
+    retired: Vec<Entity>,
     redraw: bool,
 }
 
-impl<ValueType: Clone + Copy + PartialEq + Eq> RootData<ValueType> {
+impl<ValueType: Clone + Copy + PartialEq + Eq, const L: usize, const B: usize, const T: usize> RootData<ValueType, L, B, T> {
     pub fn new_tile(
         background: &ValueType,
     ) -> Self {
@@ -211,12 +681,13 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> RootData<ValueType> {
             tile: *background,
 
             mesh: None,
+            retired: vec![],
             redraw: true,
         }
     }
 
     pub fn new_child(
-        child: &Arc<RwLock<TrunkNode<ValueType>>>,
+        child: &Arc<RwLock<TrunkNode<ValueType, L, B, T>>>,
         background: &ValueType,
     ) -> Self {
         Self {
@@ -224,6 +695,7 @@ impl<ValueType: Clone + Copy + PartialEq + Eq> RootData<ValueType> {
             tile: *background,
 
             mesh: None,
+            retired: vec![],
             redraw: true,
         }
     }
@@ -0,0 +1,549 @@
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// USE
+use crate::*;
+use super::*;
+use bevy::{
+    tasks::{AsyncComputeTaskPool, Task},
+    utils::{HashMap, HashSet},
+};
+use futures_lite::future;
+use std::sync::Mutex;
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// CONSTANTS
+/// Default for [VoxelStreamer::radius], in voxels
+pub const VOXEL_STREAM_RADIUS: f32 = 256.0;
+/// Seconds a trunk stays resident after it was last in range or touched
+const VOXEL_STREAM_EVICT_DELAY: f32 = 5.0;
+const VOXEL_STREAM_FILE_EXTENSION: &str = "vdt";
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// STRUCTS
+/// Fills a fresh trunk the first time it is loaded, given the inclusive bounds of the trunk
+pub type TrunkGenerator<ValueType> = Arc<dyn Fn(&IVec3, &IVec3, &mut Accessor<ValueType>) + Send + Sync>;
+/// Loads the trunk at a key as a root holding just that trunk, `None` if there is nothing to load
+pub type TrunkLoader<ValueType, const L: usize = { LEAF_LOG2DIM }, const B: usize = { BRANCH_LOG2DIM }, const T: usize = { TRUNK_LOG2DIM }> = Arc<dyn Fn(&IVec3) -> Option<RootNode<ValueType, L, B, T>> + Send + Sync>;
+
+/// Shared between a streamed [RootNode] & its [VoxelStreamer]
+pub struct RootStream<ValueType: Clone + Copy + PartialEq + Eq, const L: usize = { LEAF_LOG2DIM }, const B: usize = { BRANCH_LOG2DIM }, const T: usize = { TRUNK_LOG2DIM }> {
+    pub(super) loader: TrunkLoader<ValueType, L, B, T>,
+    pub(super) requested: Mutex<HashSet<IVec3>>,
+    /// Writes to trunks that weren't resident, in write order, replayed once each trunk is loaded
+    pub(super) pending: Mutex<HashMap<IVec3, Vec<PendingWrite<ValueType>>>>,
+}
+
+/// A write held back until its trunk is resident, see [RootStream::pending]
+#[derive(Clone)]
+pub(super) struct PendingWrite<ValueType> {
+    pub(super) kind: PendingWriteKind<ValueType>,
+    /// Of the [Accessor] that made the write, the change is only recorded once the old values are loaded
+    pub(super) journals: Vec<ValueJournal<ValueType>>,
+    pub(super) links: Vec<ValueLink<ValueType>>,
+}
+
+#[derive(Clone, Copy)]
+pub(super) enum PendingWriteKind<ValueType> {
+    Value { coord: IVec3, value: ValueType, active: bool },
+    Tile { coord: IVec3, dim: usize, value: ValueType, active: bool },
+}
+
+impl<ValueType> PendingWrite<ValueType> {
+    /// A write that nothing records
+    pub(super) fn new(
+        kind: PendingWriteKind<ValueType>,
+    ) -> Self {
+        Self { kind, journals: vec![], links: vec![] }
+    }
+
+    pub(super) fn coord(
+        &self,
+    ) -> IVec3 {
+        match self.kind {
+            PendingWriteKind::Value { coord, .. } | PendingWriteKind::Tile { coord, .. } => coord,
+        }
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// COMPONENTS
+//================================-================================-================================
+// StreamFocus
+/// Trunks around these are kept resident in every streamed tree, put on cameras & units
+#[derive(Component, Default)]
+pub struct StreamFocus;
+
+//================================-================================-================================
+// VoxelStreamer
+/// Pages the trunks of the [VoxelTree] on the same entity to & from one file per trunk
+///
+/// Trunks within `radius` of a [StreamFocus] are loaded or generated on the `AsyncComputeTaskPool`, the rest are saved & dropped
+#[derive(Component)]
+pub struct VoxelStreamer {
+    directory: String,
+    stream: Arc<RootStream<Voxel>>,
+    /// Taken out of the tree but not saved yet, loads are served from here until the file is written
+    evicted: Arc<Mutex<HashMap<IVec3, StreamedTrunk<Voxel>>>>,
+    load_tasks: Vec<(IVec3, Task<Option<RootNode<Voxel>>>)>,
+    save_tasks: Vec<(IVec3, Task<bool>)>,
+    /// Last time each resident trunk was in range or touched
+    last_needed: HashMap<IVec3, f32>,
+    generated: bool,
+    /// Trunks closer than this to a focus are kept resident, in voxels
+    pub radius: f32,
+}
+
+impl VoxelStreamer {
+    /// Streams `tree` from `directory`, trunks without a file come from `generator` or start empty
+    pub fn new(
+        tree: &VoxelTree,
+        directory: &str,
+        generator: Option<TrunkGenerator<Voxel>>,
+    ) -> Self {
+        if let Err(error) = create_dir_all(directory) {
+            error!("failed to create trunk directory {}: {}", directory, error);
+        }
+
+        let mut root_write_lock = tree.root.write().unwrap();
+        let background = root_write_lock.background;
+        let evicted: Arc<Mutex<HashMap<IVec3, StreamedTrunk<Voxel>>>> = Arc::new(Mutex::new(HashMap::default()));
+
+        let generated = generator.is_some();
+        let loader_directory = directory.to_string();
+        let loader_evicted = evicted.clone();
+        let loader: TrunkLoader<Voxel> = Arc::new(move |key| {
+            if let Some(trunk) = loader_evicted.lock().unwrap().get(key) {
+                return RootNode::from_file(trunk.to_file()).ok();
+            }
+
+            if let Some(root_node) = try_load_root_node(&trunk_path(&loader_directory, key)) {
+                return Some(root_node);
+            }
+
+            let generator = generator.as_ref()?;
+            let root_node = Arc::new(RwLock::new(RootNode::new(background)));
+            let mut voxels = Accessor::new(&root_node).with_remesh_mode(RemeshMode::Off);
+            generator(key, &(*key + IVec3::splat(TRUNK_DIM as i32 - 1)), &mut voxels);
+
+            // Generators write voxel by voxel, fold what came out uniform back into tiles
+            let mut root_write_lock = root_node.write().unwrap();
+            root_write_lock.prune();
+
+            // Round trip through the file form so the generated trunk comes in marked for redraw
+            let (trunk, _) = root_write_lock.take_trunk(key)?;
+            RootNode::from_file(trunk.to_file()).ok()
+        });
+
+        let stream = Arc::new(RootStream { loader, requested: Mutex::new(HashSet::default()), pending: Mutex::new(HashMap::default()) });
+        root_write_lock.set_stream(Some(stream.clone()));
+
+        Self {
+            directory: directory.to_string(),
+            stream,
+            evicted,
+            load_tasks: vec![],
+            save_tasks: vec![],
+            last_needed: HashMap::default(),
+            generated,
+            radius: VOXEL_STREAM_RADIUS,
+        }
+    }
+
+    pub fn directory(&self) -> &str { &self.directory }
+
+    pub fn is_idle(
+        &self,
+    ) -> bool {
+        self.load_tasks.is_empty() && self.save_tasks.is_empty()
+    }
+
+    /// Deletes every trunk file, for starting a new world in the same directory
+    pub fn clear_files(
+        &self,
+    ) {
+        if let Ok(entries) = read_dir(&self.directory) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_some_and(|extension| extension == VOXEL_STREAM_FILE_EXTENSION) {
+                    if let Err(error) = remove_file(&path) {
+                        error!("failed to remove trunk file {}: {}", path.display(), error);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Loads every trunk in range of `point` right away, blocking until they are resident
+    pub fn preload(
+        &mut self,
+        tree: &VoxelTree,
+        point: &Vec3,
+        time: f32,
+    ) {
+        let mut root_write_lock = tree.root.write().unwrap();
+        for key in self.keys_in_range(point) {
+            root_write_lock.ensure_resident(&key);
+            self.last_needed.insert(key, time);
+        }
+    }
+
+    /// Finishes background loads & saves, starts new ones for requested or nearby trunks and evicts stale ones
+    ///
+    /// `focus_points` are in tree space
+    pub fn update(
+        &mut self,
+        tree: &VoxelTree,
+        focus_points: &[Vec3],
+        time: f32,
+        commands: &mut Commands,
+    ) {
+        self.poll_loads(tree, time);
+        self.poll_saves();
+
+        let mut wanted: HashSet<IVec3> = HashSet::default();
+        for point in focus_points.iter() {
+            for key in self.keys_in_range(point) {
+                self.last_needed.insert(key, time);
+                wanted.insert(key);
+            }
+        }
+
+        for key in tree.root.read().unwrap().drain_requests() {
+            self.last_needed.insert(key, time);
+            wanted.insert(key);
+        }
+
+        self.start_loads(tree, &wanted);
+        self.evict_stale(tree, time, commands);
+    }
+
+    fn poll_loads(
+        &mut self,
+        tree: &VoxelTree,
+        time: f32,
+    ) {
+        let mut loaded: Vec<(IVec3, Option<RootNode<Voxel>>)> = vec![];
+        self.load_tasks.retain_mut(|(key, task)| {
+            if let Some(root_node) = future::block_on(future::poll_once(task)) {
+                loaded.push((*key, root_node));
+                false
+            } else {
+                true
+            }
+        });
+
+        if loaded.is_empty() {
+            return;
+        }
+
+        let mut root_write_lock = tree.root.write().unwrap();
+        for (key, root_node) in loaded.into_iter() {
+            root_write_lock.insert_streamed(&key, root_node);
+            self.last_needed.insert(key, time);
+        }
+    }
+
+    fn poll_saves(
+        &mut self,
+    ) {
+        let mut saved: Vec<IVec3> = vec![];
+        self.save_tasks.retain_mut(|(key, task)| {
+            if future::block_on(future::poll_once(task)).is_some() {
+                saved.push(*key);
+                false
+            } else {
+                true
+            }
+        });
+
+        let mut evicted = self.evicted.lock().unwrap();
+        for key in saved.iter() {
+            evicted.remove(key);
+        }
+    }
+
+    fn start_loads(
+        &mut self,
+        tree: &VoxelTree,
+        wanted: &HashSet<IVec3>,
+    ) {
+        let task_pool = AsyncComputeTaskPool::get();
+        let root_read_lock = tree.root.read().unwrap();
+        for key in wanted.iter() {
+            if root_read_lock.is_resident(key) || self.load_tasks.iter().any(|(loading_key, _)| loading_key == key) {
+                continue;
+            }
+
+            let key = *key;
+            let loader = self.stream.loader.clone();
+            self.load_tasks.push((key, task_pool.spawn(async move { loader(&key) })));
+        }
+    }
+
+    fn evict_stale(
+        &mut self,
+        tree: &VoxelTree,
+        time: f32,
+        commands: &mut Commands,
+    ) {
+        let mut root_write_lock = tree.root.write().unwrap();
+        let mut stale: Vec<IVec3> = vec![];
+        for key in root_write_lock.resident_keys() {
+            let last_needed = *self.last_needed.entry(key).or_insert(time);
+            // Wait for the last save of this trunk, so files are never written out of order
+            if time - last_needed > VOXEL_STREAM_EVICT_DELAY && !self.save_tasks.iter().any(|(saving_key, _)| *saving_key == key) {
+                stale.push(key);
+            }
+        }
+
+        let task_pool = AsyncComputeTaskPool::get();
+        for key in stale.iter() {
+            self.last_needed.remove(key);
+            let Some((trunk, mesh_entities)) = root_write_lock.take_trunk(key) else { continue; };
+            for mesh_entity in mesh_entities.iter() {
+                commands.entity(*mesh_entity).despawn_recursive();
+            }
+
+            // An empty trunk that was never saved reads back the same, unless a generator would fill it
+            let path = trunk_path(&self.directory, key);
+            if trunk.is_empty() && !self.generated && !std::path::Path::new(&path).exists() {
+                continue;
+            }
+
+            // Serialized in the task, the trunk is shared with the evicted table rather than copied
+            self.evicted.lock().unwrap().insert(*key, trunk.clone());
+            self.save_tasks.push((*key, task_pool.spawn(async move {
+                match trunk.to_bytes() {
+                    Ok(bytes) => Data::try_write_file(&path, &bytes),
+                    Err(error) => {
+                        error!("failed to save trunk to {}: {}", path, error);
+                        false
+                    }
+                }
+            })));
+        }
+    }
+
+    /// Keys of every trunk whose bounds come within `radius` of `point`
+    fn keys_in_range(
+        &self,
+        point: &Vec3,
+    ) -> Vec<IVec3> {
+        let min = (*point - Vec3::splat(self.radius)).floor().as_ivec3() & TRUNK_ORIGIN_MASK;
+        let max = (*point + Vec3::splat(self.radius)).ceil().as_ivec3() & TRUNK_ORIGIN_MASK;
+
+        let mut keys: Vec<IVec3> = vec![];
+        for x in (min.x..=max.x).step_by(TRUNK_DIM) { for y in (min.y..=max.y).step_by(TRUNK_DIM) { for z in (min.z..=max.z).step_by(TRUNK_DIM) {
+            let key = IVec3::new(x, y, z);
+            // Voxels are centered on integer coords
+            let bounds_min = key.as_vec3() - Vec3::splat(0.5);
+            let bounds_max = bounds_min + Vec3::splat(TRUNK_DIM as f32);
+            if point.clamp(bounds_min, bounds_max).distance(*point) <= self.radius {
+                keys.push(key);
+            }
+        }}}
+
+        keys
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// SYSTEMS
+pub(super) fn sys_stream_voxel_trunks(
+    mut commands: Commands,
+    mut tree_query: Query<(&mut VoxelTree, &mut VoxelStreamer, &Transform)>,
+    focus_query: Query<&GlobalTransform, With<StreamFocus>>,
+    time: Res<Time>,
+) {
+    // Trees are taken mutably so no accessor from another system holds on to a trunk while it is paged out
+    for (tree, mut streamer, transform) in tree_query.iter_mut() {
+        let world_to_tree = transform.compute_affine().inverse();
+        let focus_points: Vec<Vec3> = focus_query.iter().map(|focus| world_to_tree.transform_point3(focus.translation())).collect();
+        streamer.update(&tree, &focus_points, time.elapsed_seconds(), &mut commands);
+    }
+}
+
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// FUNCTIONS
+fn trunk_path(
+    directory: &str,
+    key: &IVec3,
+) -> String {
+    let key = *key >> TRUNK_TOTAL as i32;
+    format!("{}/{}_{}_{}.{}", directory, key.x, key.y, key.z, VOXEL_STREAM_FILE_EXTENSION)
+}
+//////////////////////////////////=////////////////////////////////=////////////////////////////////
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::{ecs::system::CommandQueue, tasks::TaskPool};
+
+    type TestRoot = RootNode<u8, 2, 2, 2>;
+
+    /// A streamed root whose trunk at the origin loads with a 7 at (1, 1, 1), others load empty
+    fn streamed_root() -> TestRoot {
+        let loader: TrunkLoader<u8, 2, 2, 2> = Arc::new(|key| {
+            if *key != IVec3::ZERO {
+                return None;
+            }
+
+            let root_node = Arc::new(RwLock::new(TestRoot::new(0)));
+            Accessor::new(&root_node).with_remesh_mode(RemeshMode::Off).set_value_on(&IVec3::ONE, &7);
+            let root_node = Arc::try_unwrap(root_node).ok()?;
+            root_node.into_inner().ok()
+        });
+
+        let mut root_node = TestRoot::new(0);
+        root_node.set_stream(Some(Arc::new(RootStream { loader, requested: Mutex::new(HashSet::default()), pending: Mutex::new(HashMap::default()) })));
+        root_node
+    }
+
+    fn active_values(
+        root_node: &TestRoot,
+    ) -> Vec<(IVec3, u8)> {
+        let mut voxels: Vec<(IVec3, u8)> = root_node.iter_active_regions().voxels().collect();
+        voxels.sort_by_key(|(coord, _)| coord.to_array());
+        voxels
+    }
+
+    #[test]
+    fn writes_to_unloaded_trunks_wait_for_the_load() {
+        let mut root_node = streamed_root();
+        root_node.set_value_and_cache(&IVec3::new(2, 1, 1), &9, true);
+        root_node.set_tile(&IVec3::new(16, 0, 0), 16, &3, true);
+
+        // Nothing is loaded on the write path, the trunk is requested instead
+        assert!(active_values(&root_node).is_empty());
+        assert_eq!(root_node.drain_requests(), vec![IVec3::ZERO]);
+
+        let loaded = (root_node.stream().unwrap().loader)(&IVec3::ZERO);
+        assert!(root_node.insert_streamed(&IVec3::ZERO, loaded));
+
+        let values = active_values(&root_node);
+        assert!(values.contains(&(IVec3::ONE, 7)));
+        assert!(values.contains(&(IVec3::new(2, 1, 1), 9)));
+        assert_eq!(values.iter().filter(|(_, value)| *value == 3).count(), 16 * 16 * 16);
+        assert_eq!(values.len(), 2 + 16 * 16 * 16);
+    }
+
+    #[test]
+    fn reads_of_unloaded_trunks_see_the_background() {
+        let root_node = streamed_root();
+        assert_eq!(root_node.get_value_and_cache(&IVec3::ONE).0, 0);
+        assert_eq!(root_node.get_block_value(&IVec3::ZERO, 4), Some(0));
+        assert_eq!(root_node.get_value_and_extent(&IVec3::ONE), (0, 64));
+
+        // Reading alone never pages a trunk in, or it would be evicted & reloaded over & over
+        assert!(root_node.drain_requests().is_empty());
+    }
+
+    #[test]
+    fn pending_writes_replay_in_order() {
+        let mut root_node = streamed_root();
+        let key = IVec3::splat(64);
+        root_node.set_tile(&key, 4, &3, true);
+        root_node.set_value_and_cache(&key, &5, true);
+        root_node.set_value_and_cache(&(key + IVec3::X), &0, false);
+
+        let loaded = (root_node.stream().unwrap().loader)(&key);
+        assert!(root_node.insert_streamed(&key, loaded));
+
+        let values = active_values(&root_node);
+        assert_eq!(values.len(), 4 * 4 * 4 - 1);
+        assert_eq!(values[0], (key, 5));
+        assert!(!values.iter().any(|(coord, _)| *coord == key + IVec3::X));
+    }
+
+    #[test]
+    fn deferred_writes_are_journaled_over_the_loaded_values() {
+        let root_node = Arc::new(RwLock::new(streamed_root()));
+        let journal: ValueJournal<u8> = ValueJournal::default();
+        let mut values = Accessor::new(&root_node).with_remesh_mode(RemeshMode::Off).with_journal(&journal);
+        values.set_value_on(&IVec3::ONE, &9);
+        values.set_tile(&IVec3::new(4, 0, 0), 4, &3, true);
+        assert!(journal.lock().unwrap().is_empty());
+
+        let mut root_write_lock = root_node.write().unwrap();
+        let loaded = (root_write_lock.stream().unwrap().loader)(&IVec3::ZERO);
+        assert!(root_write_lock.insert_streamed(&IVec3::ZERO, loaded));
+
+        assert_eq!(*journal.lock().unwrap(), vec![
+            ValueChange { coord: IVec3::ONE, dim: 1, old: 7, new: 9 },
+            ValueChange { coord: IVec3::new(4, 0, 0), dim: 4, old: 0, new: 3 },
+        ]);
+    }
+
+    /// Updates until every load & save has finished
+    fn settle(
+        streamer: &mut VoxelStreamer,
+        tree: &VoxelTree,
+        focus_point: &Vec3,
+        time: f32,
+        commands: &mut Commands,
+    ) {
+        for _ in 0..1000 {
+            streamer.update(tree, &[*focus_point], time, commands);
+            if streamer.is_idle() {
+                return;
+            }
+
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        panic!("streamer never settled");
+    }
+
+    #[test]
+    fn trunks_out_of_range_are_saved_and_reloaded() {
+        AsyncComputeTaskPool::init(TaskPool::default);
+        let world = World::new();
+        let mut command_queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut command_queue, &world);
+
+        let tree = VoxelTree::new(Voxel::default());
+        let directory = std::env::temp_dir().join("vdt_evicted_trunks").to_string_lossy().into_owned();
+        let mut streamer = VoxelStreamer::new(&tree, &directory, None);
+        streamer.clear_files();
+        streamer.preload(&tree, &Vec3::ZERO, 0.0);
+        // Only the trunks meeting at the focus are in range
+        assert_eq!(tree.root.read().unwrap().resident_keys().len(), 8);
+        tree.get_accessor().set_value_on(&IVec3::ONE, &Voxel::from_matter_id(1));
+
+        // The focus moves a few trunks away & stays there past the evict delay
+        let far_point = Vec3::splat(4.0 * TRUNK_DIM as f32);
+        settle(&mut streamer, &tree, &far_point, 0.0, &mut commands);
+        assert!(tree.root.read().unwrap().is_resident(&IVec3::ZERO));
+        settle(&mut streamer, &tree, &far_point, VOXEL_STREAM_EVICT_DELAY + 1.0, &mut commands);
+        assert!(!tree.root.read().unwrap().is_resident(&IVec3::ZERO));
+        assert!(std::path::Path::new(&trunk_path(&directory, &IVec3::ZERO)).exists());
+
+        settle(&mut streamer, &tree, &Vec3::ZERO, VOXEL_STREAM_EVICT_DELAY + 2.0, &mut commands);
+        assert!(tree.root.read().unwrap().is_resident(&IVec3::ZERO));
+        assert_eq!(tree.get_accessor().get_value(&IVec3::ONE), Voxel::from_matter_id(1));
+    }
+
+    #[test]
+    fn generated_trunks_come_in_pruned() {
+        let tree = VoxelTree::new(Voxel::default());
+        let generator: TrunkGenerator<Voxel> = Arc::new(|min, _, voxels| {
+            if *min != IVec3::ZERO {
+                return;
+            }
+
+            for x in 0..LEAF_DIM as i32 { for y in 0..LEAF_DIM as i32 { for z in 0..LEAF_DIM as i32 {
+                voxels.set_value_on(&IVec3::new(x, y, z), &Voxel::from_matter_id(1));
+            }}}
+        });
+
+        let directory = std::env::temp_dir().join("vdt_generated_trunks").to_string_lossy().into_owned();
+        let streamer = VoxelStreamer::new(&tree, &directory, Some(generator));
+        streamer.clear_files();
+        tree.root.write().unwrap().ensure_resident(&IVec3::ZERO);
+
+        // One leaf tile rather than a leaf of loose voxels
+        let regions: Vec<ActiveRegion<Voxel>> = tree.iter_active_regions().collect();
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].max - regions[0].min, IVec3::splat(LEAF_DIM as i32 - 1));
+    }
+}
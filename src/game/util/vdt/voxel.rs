@@ -14,6 +14,9 @@ pub const GRID_DIRECTIONS: [IVec3; 6] = [
     IVec3::new( 0,  0,  1), // Front
 ];
 
+/// Fill levels a liquid voxel can hold, the highest is full
+pub const VOXEL_LIQUID_LEVELS: u8 = 8;
+
 pub const VOXEL_SIDE_FACE_CHECKS: [IVec3; 4] = [
     IVec3::new(-1,  0,  0), // Left
     IVec3::new( 1,  0,  0), // Right
@@ -84,7 +87,7 @@ pub enum VoxelMatterState {
 // Solid: Soft + Dense + Light + Opaque = Dirt
 // Solid: Hard + Dense + Heavy + Opaque = Stone
 // Solid: Hard + Loose + Light = Ice
-#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum VoxelMatterProperty {
     // ON [Hard]:
@@ -124,12 +127,14 @@ pub enum VoxelMatterProperty {
 #[repr(u8)]
 pub enum VoxelState {
     None = 0x00,
+    /// Liquids only, how far the surface sits below [VOXEL_LIQUID_LEVELS] so new liquid voxels are full
+    Drained = 0x07,
     Blocked = 0x80,
 }
 
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // STRUCTS
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Voxel {
     matter_id: u8,
     state: u8,
@@ -150,6 +155,19 @@ impl Voxel {
         Self { matter_id, state: 0 }
     }
 
+    pub fn matter_id(&self) -> u8 { self.matter_id }
+    pub fn state(&self) -> u8 { self.state }
+    /// 1 up to [VOXEL_LIQUID_LEVELS], meaningless for anything but liquids
+    pub fn liquid_level(&self) -> u8 { VOXEL_LIQUID_LEVELS - (self.state & VoxelState::Drained as u8) }
+
+    pub fn with_liquid_level(
+        &self,
+        level: u8,
+    ) -> Self {
+        let drained = VOXEL_LIQUID_LEVELS - level.clamp(1, VOXEL_LIQUID_LEVELS);
+        Self { matter_id: self.matter_id, state: (self.state & !(VoxelState::Drained as u8)) | drained }
+    }
+
     pub fn face_texture_id(&self, face: u8, defs: &Res<Defs>) -> u32 {
         defs.matter.get_u8(self.matter_id).texture_id(face)
     }
@@ -163,6 +181,19 @@ impl Voxel {
         defs.matter.get_u8(self.matter_id).is_solid()
     }
 
+    pub fn is_structural(&self, defs: &Res<Defs>) -> bool {
+        defs.matter.get_u8(self.matter_id).is_structural()
+    }
+
+    pub fn is_liquid(&self, defs: &Res<Defs>) -> bool {
+        defs.matter.get_u8(self.matter_id).is_liquid()
+    }
+
+    /// Extra steps a unit spends moving through this voxel
+    pub fn move_cost(&self, defs: &Res<Defs>) -> u32 {
+        defs.matter.get_u8(self.matter_id).move_cost()
+    }
+
     pub fn is_blocked(&self, defs: &Res<Defs>) -> bool {
         defs.matter.get_u8(self.matter_id).is_solid() || self.state & VoxelState::Blocked as u8 == VoxelState::Blocked as u8
     }
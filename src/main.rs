@@ -12,6 +12,10 @@ pub use game::*;
 //////////////////////////////////=////////////////////////////////=////////////////////////////////
 // MAIN
 fn main() {
+    if std::env::args().any(|arg| arg == DEFS_CHECK_ARG) {
+        std::process::exit(check_defs());
+    }
+
     App::new()
         .add_plugin(GamePlugin)
         .run();